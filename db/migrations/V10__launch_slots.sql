@@ -0,0 +1,13 @@
+ALTER TABLE trade
+    ADD COLUMN IF NOT EXISTS slot bigint;
+
+ALTER TABLE trade
+    ADD COLUMN IF NOT EXISTS signature text;
+
+ALTER TABLE token
+    ADD COLUMN IF NOT EXISTS created_slot bigint;
+
+ALTER TABLE token
+    ADD COLUMN IF NOT EXISTS created_signature text;
+
+CREATE INDEX IF NOT EXISTS trade_token_slot_idx ON trade (token_id, slot);
@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS wallet_funding (
+    wallet text PRIMARY KEY,
+    funder text,
+    funding_signature text,
+    funding_slot bigint,
+    resolved_at timestamptz NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS wallet_funding_funder_idx ON wallet_funding (funder);
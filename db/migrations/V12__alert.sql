@@ -0,0 +1,16 @@
+CREATE TABLE IF NOT EXISTS alert (
+    id uuid PRIMARY KEY,
+    rule text NOT NULL,
+    contract_address text NOT NULL,
+    wallet text,
+    message text NOT NULL,
+    value double precision NOT NULL,
+    threshold double precision NOT NULL,
+    signature text NOT NULL,
+    slot bigint NOT NULL,
+    created_at timestamptz NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS alert_created_at_idx ON alert (created_at DESC);
+
+CREATE INDEX IF NOT EXISTS alert_contract_address_idx ON alert (contract_address);
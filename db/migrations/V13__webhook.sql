@@ -0,0 +1,30 @@
+CREATE TABLE IF NOT EXISTS webhook (
+    id uuid PRIMARY KEY,
+    url text NOT NULL,
+    secret text NOT NULL,
+    event_types text[] NOT NULL,
+    mints text[],
+    creators text[],
+    enabled boolean NOT NULL DEFAULT true,
+    consecutive_failures int NOT NULL DEFAULT 0,
+    created_at timestamptz NOT NULL,
+    disabled_at timestamptz
+);
+
+CREATE TABLE IF NOT EXISTS webhook_delivery (
+    id uuid PRIMARY KEY,
+    webhook_id uuid NOT NULL REFERENCES webhook(id) ON DELETE CASCADE,
+    event_type text NOT NULL,
+    payload text NOT NULL,
+    status text NOT NULL DEFAULT 'pending',
+    attempts int NOT NULL DEFAULT 0,
+    next_attempt_at timestamptz NOT NULL,
+    last_status_code int,
+    last_error text,
+    created_at timestamptz NOT NULL,
+    delivered_at timestamptz
+);
+
+CREATE INDEX IF NOT EXISTS webhook_delivery_due_idx ON webhook_delivery (next_attempt_at) WHERE status = 'pending';
+
+CREATE INDEX IF NOT EXISTS webhook_delivery_webhook_idx ON webhook_delivery (webhook_id, created_at DESC);
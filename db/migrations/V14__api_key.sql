@@ -0,0 +1,19 @@
+CREATE TABLE IF NOT EXISTS api_key (
+    id uuid PRIMARY KEY,
+    name text NOT NULL,
+    key_prefix text NOT NULL,
+    key_hash text NOT NULL UNIQUE,
+    tier text NOT NULL,
+    is_admin boolean NOT NULL DEFAULT false,
+    created_at timestamptz NOT NULL,
+    last_used_at timestamptz,
+    revoked_at timestamptz
+);
+
+CREATE TABLE IF NOT EXISTS api_key_usage (
+    api_key_id uuid NOT NULL REFERENCES api_key(id) ON DELETE CASCADE,
+    day date NOT NULL,
+    requests bigint NOT NULL DEFAULT 0,
+    rate_limited bigint NOT NULL DEFAULT 0,
+    PRIMARY KEY (api_key_id, day)
+);
@@ -0,0 +1,2 @@
+ALTER TABLE token
+    ADD COLUMN IF NOT EXISTS market_cap_slot bigint;
@@ -0,0 +1,4 @@
+ALTER TABLE trade
+    ADD COLUMN IF NOT EXISTS event_index bigint;
+
+CREATE UNIQUE INDEX IF NOT EXISTS trade_signature_event_idx ON trade (signature, event_index);
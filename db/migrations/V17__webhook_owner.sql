@@ -0,0 +1,4 @@
+ALTER TABLE webhook
+    ADD COLUMN IF NOT EXISTS api_key_id uuid REFERENCES api_key(id) ON DELETE CASCADE;
+
+CREATE INDEX IF NOT EXISTS webhook_api_key_idx ON webhook (api_key_id);
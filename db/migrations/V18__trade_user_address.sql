@@ -0,0 +1 @@
+CREATE INDEX IF NOT EXISTS trade_user_address_idx ON trade (user_address, created_at DESC);
@@ -0,0 +1 @@
+CREATE INDEX IF NOT EXISTS webhook_delivery_finished_idx ON webhook_delivery (created_at) WHERE status <> 'pending';
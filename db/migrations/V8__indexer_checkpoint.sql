@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS indexer_checkpoint (
+    datasource text PRIMARY KEY,
+    slot bigint NOT NULL,
+    signature text NOT NULL,
+    updated_at timestamptz NOT NULL
+)
@@ -0,0 +1,18 @@
+CREATE TABLE IF NOT EXISTS token_rank (
+    token_id uuid PRIMARY KEY,
+    rank int NOT NULL,
+    score double precision NOT NULL,
+    volume_sol double precision NOT NULL,
+    unique_buyers bigint NOT NULL,
+    holder_growth bigint NOT NULL,
+    curve_velocity double precision NOT NULL,
+    market_cap_momentum double precision NOT NULL,
+    bonding_curve_percentage int NOT NULL,
+    market_cap bigint NOT NULL,
+    updated_at timestamptz NOT NULL,
+    FOREIGN KEY (token_id) REFERENCES token(id)
+);
+
+CREATE INDEX IF NOT EXISTS token_rank_rank_idx ON token_rank (rank);
+
+CREATE INDEX IF NOT EXISTS trade_created_at_idx ON trade (created_at);
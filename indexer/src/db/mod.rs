@@ -1,3 +0,0 @@
-pub mod query;
-pub mod token;
-pub mod trade;
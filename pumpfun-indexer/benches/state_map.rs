@@ -0,0 +1,170 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use pumpfun_indexer::{
+    state::{BondingState, ShardedBondingState, DEFAULT_SHARDS},
+    types::BondingCurveAndMcInfo,
+};
+use tokio::sync::RwLock;
+use tokio_util::sync::CancellationToken;
+
+// Synthetic burst: a few hot tokens take most of the trades, like right after a popular launch
+const TOKENS: usize = 10_000;
+const HOT_TOKENS: usize = 100;
+const TRADES: usize = 1_000_000;
+const TRADERS: usize = 8;
+const FLUSH_INTERVAL: Duration = Duration::from_millis(50);
+
+fn tokens() -> Vec<BondingCurveAndMcInfo> {
+    (0..TOKENS)
+        .map(|index| BondingCurveAndMcInfo {
+            contract_address: format!("mint{}", index),
+            bonding_curve_address: format!("curve{}", index),
+            bonding_curve_percentage: 0,
+            market_cap: Some(0),
+            market_cap_slot: None,
+        })
+        .collect()
+}
+
+// Mint of the n-th trade of a trader, every fourth trade goes to a cold token
+fn trade_mint(mints: &[String], trader: usize, trade: usize) -> &str {
+    let seed = trade.wrapping_mul(2_654_435_761).wrapping_add(trader);
+
+    if trade % 4 == 0 {
+        &mints[seed % TOKENS]
+    } else {
+        &mints[seed % HOT_TOKENS]
+    }
+}
+
+fn report(name: &str, elapsed: Duration, flushed: usize) {
+    println!(
+        "{:<24} {:>10.0} trades/s {:>8.1} ms {:>8} flushed",
+        name,
+        TRADES as f64 / elapsed.as_secs_f64(),
+        elapsed.as_secs_f64() * 1000.0,
+        flushed
+    );
+}
+
+// Every trade takes the write lock of the whole map, as before the map was sharded
+async fn global_lock(mints: Arc<Vec<String>>) -> (Duration, usize) {
+    let state = Arc::new(RwLock::new(BondingState::from_tokens(tokens(), TOKENS)));
+    let stop = CancellationToken::new();
+
+    let flusher = {
+        let state = state.clone();
+        let stop = stop.clone();
+
+        tokio::spawn(async move {
+            let mut flushed = 0;
+
+            while !stop.is_cancelled() {
+                tokio::time::sleep(FLUSH_INTERVAL).await;
+                flushed += state.write().await.take_dirty().len();
+            }
+
+            flushed
+        })
+    };
+
+    let start = Instant::now();
+
+    let traders = (0..TRADERS).map(|trader| {
+        let state = state.clone();
+        let mints = mints.clone();
+
+        tokio::spawn(async move {
+            for trade in 0..TRADES / TRADERS {
+                let mint = trade_mint(&mints, trader, trade);
+
+                state
+                    .write()
+                    .await
+                    .update(mint, (trade % 100) as i32, trade as i64, 0);
+            }
+        })
+    });
+
+    for trader in traders.collect::<Vec<_>>() {
+        trader.await.unwrap();
+    }
+
+    let elapsed = start.elapsed();
+
+    stop.cancel();
+
+    (elapsed, flusher.await.unwrap())
+}
+
+async fn sharded(mints: Arc<Vec<String>>, shards: usize) -> (Duration, usize) {
+    let state = Arc::new(ShardedBondingState::from_tokens(tokens(), TOKENS, shards));
+    let stop = CancellationToken::new();
+
+    let flusher = {
+        let state = state.clone();
+        let stop = stop.clone();
+
+        tokio::spawn(async move {
+            let mut flushed = 0;
+
+            while !stop.is_cancelled() {
+                tokio::time::sleep(FLUSH_INTERVAL).await;
+                flushed += state.take_dirty().len();
+            }
+
+            flushed
+        })
+    };
+
+    let start = Instant::now();
+
+    let traders = (0..TRADERS).map(|trader| {
+        let state = state.clone();
+        let mints = mints.clone();
+
+        tokio::spawn(async move {
+            for trade in 0..TRADES / TRADERS {
+                let mint = trade_mint(&mints, trader, trade);
+
+                state.update(mint, (trade % 100) as i32, trade as i64, 0);
+            }
+        })
+    });
+
+    for trader in traders.collect::<Vec<_>>() {
+        trader.await.unwrap();
+    }
+
+    let elapsed = start.elapsed();
+
+    stop.cancel();
+
+    (elapsed, flusher.await.unwrap())
+}
+
+#[tokio::main(flavor = "multi_thread")]
+async fn main() {
+    let mints = Arc::new(
+        tokens()
+            .into_iter()
+            .map(|info| info.contract_address)
+            .collect::<Vec<_>>(),
+    );
+
+    println!(
+        "{} trades of {} tokens from {} tasks, flushed every {:?}",
+        TRADES, TOKENS, TRADERS, FLUSH_INTERVAL
+    );
+
+    let (elapsed, flushed) = global_lock(mints.clone()).await;
+    report("global lock", elapsed, flushed);
+
+    for shards in [8, 16, DEFAULT_SHARDS, 256] {
+        let (elapsed, flushed) = sharded(mints.clone(), shards).await;
+        report(&format!("{} shards", shards), elapsed, flushed);
+    }
+}
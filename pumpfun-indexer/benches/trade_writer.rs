@@ -0,0 +1,211 @@
+use std::{
+    collections::HashSet,
+    env, fs,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use pumpfun_indexer::{
+    config::IndexerConfig,
+    events::{EventEnvelope, EventPayload, TradeData},
+    trade_writer::TradeWriter,
+    utils::connect_db,
+};
+use sqlx::{
+    types::chrono::{DateTime, Utc},
+    PgPool,
+};
+use uuid::Uuid;
+
+// Trades replayed when no capture is given, spread over this many synthetic tokens
+const SYNTHETIC_TRADES: usize = 100_000;
+const SYNTHETIC_TOKENS: usize = 1_000;
+
+// Trades of a capture, one JSON envelope per line as published on the "trade" channel with EVENT_ENCODING=json
+fn load_capture(path: &str) -> Vec<EventEnvelope> {
+    let capture = fs::read_to_string(path).expect("Failed to read the capture");
+
+    capture
+        .lines()
+        .filter_map(|line| EventEnvelope::decode(line.trim().as_bytes()).ok())
+        .filter(|envelope| matches!(envelope.payload, EventPayload::Trade(_)))
+        .collect()
+}
+
+fn synthetic_trades() -> Vec<EventEnvelope> {
+    (0..SYNTHETIC_TRADES)
+        .map(|index| {
+            EventEnvelope::new(
+                Uuid::new_v4().to_string(),
+                index as u64 / 100,
+                &format!("bench-signature-{}", index),
+                format!("bench-mint-{}", index % SYNTHETIC_TOKENS),
+                EventPayload::Trade(TradeData {
+                    user: format!("bench-wallet-{}", index % 5_000),
+                    is_buy: index % 3 != 0,
+                    sol_amount: 1_000_000 + index as u64,
+                    token_amount: 35_000_000_000 + index as u64,
+                    timestamp: Utc::now().timestamp(),
+                    virtual_sol_reserves: 30_000_000_000,
+                    virtual_token_reserves: 1_073_000_000_000_000,
+                    real_sol_reserves: 0,
+                    real_token_reserves: 793_100_000_000_000,
+                    fee_recipient: "bench-fee-recipient".to_string(),
+                    fee_basis_points: 100,
+                    fee: 10_000,
+                    price_sol: 0.000000028,
+                    price_usd: 0.000004,
+                    sol_amount_usd: 0.15,
+                    market_cap_usd: 4_000,
+                }),
+            )
+        })
+        .collect()
+}
+
+// The merge drops trades of unknown tokens, so every replayed mint gets a token row first. Returns the created ones.
+async fn ensure_tokens(db: &PgPool, trades: &[EventEnvelope]) -> Vec<Uuid> {
+    let mints: Vec<String> = trades
+        .iter()
+        .map(|trade| trade.mint.clone())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+
+    let ids: Vec<Uuid> = mints.iter().map(|_| Uuid::new_v4()).collect();
+    let curves: Vec<String> = mints.iter().map(|mint| format!("{}-curve", mint)).collect();
+
+    sqlx::query(
+        r#"
+        INSERT INTO token (id, created_at, updated_at, name, ticker, contract_address, bonding_curve_address, creator_address, bond_status)
+        SELECT i, NOW(), NOW(), 'bench', 'BENCH', ca, bc, 'bench-creator', 'newly_launched'
+        FROM UNNEST($1::uuid[], $2::text[], $3::text[]) AS tmp(i, ca, bc)
+        ON CONFLICT DO NOTHING
+        RETURNING id
+        "#,
+    )
+    .bind(&ids)
+    .bind(&mints)
+    .bind(&curves)
+    .fetch_all(db)
+    .await
+    .expect("Failed to create the replayed tokens")
+}
+
+// Deletes the replayed trades and the tokens created for them, so repeated runs start from the same database
+async fn clean_up(db: &PgPool, trades: &[EventEnvelope], tokens: &[Uuid], since: DateTime<Utc>) {
+    let signatures: Vec<String> = trades
+        .iter()
+        .map(|trade| trade.signature.clone())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+
+    sqlx::query("DELETE FROM trade WHERE created_at >= $1 AND signature = ANY($2)")
+        .bind(since)
+        .bind(&signatures)
+        .execute(db)
+        .await
+        .expect("Failed to delete the replayed trades");
+
+    sqlx::query("DELETE FROM trade WHERE token_id = ANY($1)")
+        .bind(tokens)
+        .execute(db)
+        .await
+        .expect("Failed to delete the trades of the replayed tokens");
+
+    sqlx::query("DELETE FROM token WHERE id = ANY($1)")
+        .bind(tokens)
+        .execute(db)
+        .await
+        .expect("Failed to delete the replayed tokens");
+}
+
+async fn stored_trades(db: &PgPool) -> i64 {
+    sqlx::query_scalar("SELECT COUNT(*) FROM trade")
+        .fetch_one(db)
+        .await
+        .expect("Failed to count the trades")
+}
+
+// Replays the trades of TRADE_CAPTURE, or synthetic ones, REPLAY_ROUNDS times through the TradeWriter against
+// BENCH_DATABASE_URL and reports the sustained write throughput. The replayed trades and the tokens created for them
+// are deleted afterwards.
+#[tokio::main(flavor = "multi_thread")]
+async fn main() {
+    let config = IndexerConfig::get_config();
+
+    // Never DATABASE_URL, the bench writes and deletes rows so it needs a database of its own
+    let database_url = env::var("BENCH_DATABASE_URL")
+        .expect("BENCH_DATABASE_URL must point to a scratch database");
+
+    let trades = match env::var("TRADE_CAPTURE") {
+        Ok(path) => load_capture(&path),
+        Err(_) => synthetic_trades(),
+    };
+
+    let rounds: usize = env::var("REPLAY_ROUNDS")
+        .ok()
+        .and_then(|rounds| rounds.parse().ok())
+        .unwrap_or(3);
+
+    assert!(!trades.is_empty(), "No trades to replay");
+
+    let db = Arc::new(
+        connect_db(&database_url)
+            .await
+            .expect("Failed to connect to BENCH_DATABASE_URL"),
+    );
+
+    let replay_started_at = Utc::now();
+    let tokens = ensure_tokens(&db, &trades).await;
+
+    println!(
+        "Replaying {} trades {} times, batches of {} to {} trades, {} writes in flight",
+        trades.len(),
+        rounds,
+        config.trade_writer.min_batch_size,
+        config.trade_writer.max_batch_size,
+        config.trade_writer.max_in_flight
+    );
+
+    let stored_before = stored_trades(&db).await;
+
+    let mut writer = TradeWriter::new(db.clone(), config.trade_writer.clone());
+    let mut report_at = Instant::now() + Duration::from_secs(1);
+    let mut replayed_since = 0;
+    let started = Instant::now();
+
+    for _ in 0..rounds {
+        for trade in trades.iter() {
+            writer.push(trade.clone()).await;
+            replayed_since += 1;
+
+            if Instant::now() >= report_at {
+                println!(
+                    "{:>10} trades/s pushed, batch size {}",
+                    replayed_since,
+                    writer.batch_size()
+                );
+
+                replayed_since = 0;
+                report_at += Duration::from_secs(1);
+            }
+        }
+    }
+
+    writer.close().await;
+
+    let elapsed = started.elapsed();
+    let stored = stored_trades(&db).await - stored_before;
+
+    println!(
+        "Stored {} of {} trades in {:.1}s, {:.0} trades/s sustained",
+        stored,
+        trades.len() * rounds,
+        elapsed.as_secs_f64(),
+        stored as f64 / elapsed.as_secs_f64()
+    );
+
+    clean_up(&db, &trades, &tokens, replay_started_at).await;
+}
@@ -0,0 +1,565 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::Arc,
+    time::Duration,
+};
+
+use actix_web::{get, web, HttpResponse};
+use redis::{aio::MultiplexedConnection, AsyncCommands};
+use serde::Deserialize;
+use solana_client::client_error::reqwest;
+use sqlx::{types::chrono::Utc, PgPool};
+use tokio::sync::{broadcast, mpsc::UnboundedReceiver, mpsc::UnboundedSender};
+use tokio_util::sync::CancellationToken;
+use utoipa::IntoParams;
+
+use crate::{
+    config::AlertRule,
+    db::alert::{fetch_recent_alerts, save_alert},
+    errors::{ApiError, ErrorResponse},
+    events::TradeData,
+    leader::Leadership,
+    types::Alert,
+};
+
+// Mints without a trade for this long are forgotten by the engine
+const STATE_TTL_SECS: i64 = 24 * 60 * 60;
+
+// How many trades are processed between two sweeps of inactive mints
+const PRUNE_EVERY_TRADES: u64 = 10_000;
+
+// Redis channel the fired alerts are published on, the API processes relay it to their /alerts/stream subscribers
+pub const ALERTS_CHANNEL: &str = "alerts";
+
+const DEFAULT_ALERTS_LIMIT: i64 = 100;
+const MAX_ALERTS_LIMIT: i64 = 1000;
+
+#[derive(Debug, Default)]
+struct MintState {
+    creator: Option<String>,
+    launched_at: Option<i64>,
+    last_trade_at: i64,
+    creator_bought: u64,
+    creator_sold: u64,
+    // Per wallet balances, only kept while a top holder rule can still fire
+    balances: HashMap<String, u64>,
+    market_caps: VecDeque<(i64, i64)>,
+    // Indexes of the rules that already fired once for this mint
+    fired: HashSet<usize>,
+    last_drop_alert_at: Option<i64>,
+}
+
+// Evaluates the configured rules on every processed trade. Owned by the instruction processor, fired alerts are
+// handed to the dispatcher over a channel so evaluation never waits on IO.
+pub struct AlertEngine {
+    rules: Vec<AlertRule>,
+    state: HashMap<String, MintState>,
+    sender: UnboundedSender<Alert>,
+    trades_seen: u64,
+}
+
+impl AlertEngine {
+    pub fn new(rules: Vec<AlertRule>, sender: UnboundedSender<Alert>) -> Self {
+        Self {
+            rules,
+            state: HashMap::new(),
+            sender,
+            trades_seen: 0,
+        }
+    }
+
+    // Whether the engine keeps a state for the mint, the ones it doesn't know are seeded before their first trade
+    pub fn is_tracking(&self, mint: &str) -> bool {
+        self.state.contains_key(mint)
+    }
+
+    pub fn on_create(&mut self, mint: String, creator: String, timestamp: i64) {
+        let state = self.state.entry(mint).or_default();
+
+        state.creator = Some(creator);
+        state.launched_at = Some(timestamp);
+        state.last_trade_at = timestamp;
+    }
+
+    pub fn on_trade(
+        &mut self,
+        mint: &str,
+        trade: &TradeData,
+        market_cap: Option<i64>,
+        slot: u64,
+        signature: &str,
+    ) {
+        let mint = mint.to_string();
+        let wallet = trade.user.clone();
+        let now = trade.timestamp;
+
+        let max_holder_window_secs = self
+            .rules
+            .iter()
+            .filter_map(|rule| match rule {
+                AlertRule::TopHolderDump { minutes, .. } => Some(minutes * 60),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0);
+
+        let max_drop_window_secs = self
+            .rules
+            .iter()
+            .filter_map(|rule| match rule {
+                AlertRule::MarketCapDrop { window_secs, .. } => Some(*window_secs),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0);
+
+        let state = self.state.entry(mint.clone()).or_default();
+        state.last_trade_at = now;
+
+        let balance_before = state.balances.get(&wallet).copied().unwrap_or(0);
+
+        let in_holder_window = state
+            .launched_at
+            .map(|launched_at| now - launched_at <= max_holder_window_secs)
+            .unwrap_or(false);
+
+        // Top holders before this trade, used by the dump rule
+        let mut ranked_balances: Vec<(&String, &u64)> = state.balances.iter().collect();
+        ranked_balances.sort_by(|a, b| b.1.cmp(a.1));
+        let holder_position = ranked_balances
+            .iter()
+            .position(|(holder, _)| **holder == wallet);
+
+        let is_creator = state.creator.as_deref() == Some(wallet.as_str());
+
+        if is_creator {
+            if trade.is_buy {
+                state.creator_bought += trade.token_amount;
+            } else {
+                state.creator_sold += trade.token_amount;
+            }
+        }
+
+        if in_holder_window {
+            let balance = state.balances.entry(wallet.clone()).or_insert(0);
+
+            if trade.is_buy {
+                *balance += trade.token_amount;
+            } else {
+                *balance = balance.saturating_sub(trade.token_amount);
+            }
+        } else {
+            state.balances.clear();
+        }
+
+        if let Some(market_cap) = market_cap.filter(|mc| *mc > 0) {
+            state.market_caps.push_back((now, market_cap));
+
+            while let Some((at, _)) = state.market_caps.front() {
+                if now - at > max_drop_window_secs {
+                    state.market_caps.pop_front();
+                } else {
+                    break;
+                }
+            }
+        }
+
+        let mut fired = Vec::new();
+
+        for (index, rule) in self.rules.iter().enumerate() {
+            match rule {
+                AlertRule::CreatorSell { percent } => {
+                    if !is_creator
+                        || trade.is_buy
+                        || state.creator_bought == 0
+                        || state.fired.contains(&index)
+                    {
+                        continue;
+                    }
+
+                    let sold_percent =
+                        state.creator_sold as f64 / state.creator_bought as f64 * 100.0;
+
+                    if sold_percent >= *percent {
+                        state.fired.insert(index);
+                        fired.push((
+                            "creator_sell",
+                            Some(wallet.clone()),
+                            format!("Creator sold {:.1}% of their tokens", sold_percent),
+                            sold_percent,
+                            *percent,
+                        ));
+                    }
+                }
+                AlertRule::TopHolderDump {
+                    top_n,
+                    minutes,
+                    percent,
+                } => {
+                    let Some(launched_at) = state.launched_at else {
+                        continue;
+                    };
+
+                    if trade.is_buy
+                        || now - launched_at > minutes * 60
+                        || balance_before == 0
+                        || !holder_position.map(|p| p < *top_n).unwrap_or(false)
+                    {
+                        continue;
+                    }
+
+                    let sold_percent = trade.token_amount.min(balance_before) as f64
+                        / balance_before as f64
+                        * 100.0;
+
+                    if sold_percent >= *percent {
+                        fired.push((
+                            "top_holder_dump",
+                            Some(wallet.clone()),
+                            format!(
+                                "Top {} holder sold {:.1}% of their balance {} minutes after launch",
+                                top_n,
+                                sold_percent,
+                                (now - launched_at) / 60
+                            ),
+                            sold_percent,
+                            *percent,
+                        ));
+                    }
+                }
+                AlertRule::MarketCapDrop {
+                    percent,
+                    window_secs,
+                } => {
+                    let Some(market_cap) = market_cap.filter(|mc| *mc > 0) else {
+                        continue;
+                    };
+
+                    // Fire at most once per window for the same mint
+                    if state
+                        .last_drop_alert_at
+                        .map(|at| now - at < *window_secs)
+                        .unwrap_or(false)
+                    {
+                        continue;
+                    }
+
+                    let peak = state
+                        .market_caps
+                        .iter()
+                        .filter(|(at, _)| now - at <= *window_secs)
+                        .map(|(_, mc)| *mc)
+                        .max()
+                        .unwrap_or(market_cap);
+
+                    let drop_percent = (peak - market_cap) as f64 / peak as f64 * 100.0;
+
+                    if drop_percent >= *percent {
+                        state.last_drop_alert_at = Some(now);
+                        fired.push((
+                            "market_cap_drop",
+                            None,
+                            format!(
+                                "Market cap dropped {:.1}% from {} to {} within {}s",
+                                drop_percent, peak, market_cap, window_secs
+                            ),
+                            drop_percent,
+                            *percent,
+                        ));
+                    }
+                }
+            }
+        }
+
+        for (rule, wallet, message, value, threshold) in fired {
+            let alert = Alert {
+                id: uuid::Uuid::new_v4().to_string(),
+                rule: rule.to_string(),
+                contract_address: mint.clone(),
+                wallet,
+                message,
+                value,
+                threshold,
+                signature: signature.to_string(),
+                slot: slot as i64,
+                created_at: Utc::now(),
+            };
+
+            tracing::info!(rule = %alert.rule, mint = %alert.contract_address, "{}", alert.message);
+
+            let _ = self.sender.send(alert);
+        }
+
+        self.trades_seen += 1;
+
+        if self.trades_seen % PRUNE_EVERY_TRADES == 0 {
+            self.state
+                .retain(|_, state| now - state.last_trade_at <= STATE_TTL_SECS);
+        }
+    }
+}
+
+// Persists fired alerts, publishes them for the /alerts/stream subscribers and posts them to the webhook if configured.
+// The alerts fired while this replica is a standby are dropped, the leader fires the same ones. A webhook that doesn't
+// answer within the timeout is given up on so it can't hold back the next alerts.
+pub async fn run_alert_dispatcher(
+    db: Arc<PgPool>,
+    webhook_url: Option<String>,
+    webhook_timeout_secs: u64,
+    mut rx: UnboundedReceiver<Alert>,
+    mut redis: MultiplexedConnection,
+    leadership: Leadership,
+    shutdown: CancellationToken,
+) {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(webhook_timeout_secs))
+        .build()
+        .unwrap_or_default();
+
+    loop {
+        let alert = tokio::select! {
+            alert = rx.recv() => match alert {
+                Some(alert) => alert,
+                None => break,
+            },
+            // Drain whatever is left before stopping
+            _ = shutdown.cancelled() => match rx.try_recv() {
+                Ok(alert) => alert,
+                Err(_) => break,
+            },
+        };
+
+        if !leadership.is_leader() {
+            continue;
+        }
+
+        save_alert(db.clone(), &alert).await;
+
+        match serde_json::to_string(&alert) {
+            Ok(payload) => {
+                if let Err(err) = redis.publish::<_, _, ()>(ALERTS_CHANNEL, payload).await {
+                    tracing::error!(error = ?err, "Failed to publish alert");
+                }
+            }
+            Err(err) => tracing::error!(error = ?err, "Failed to serialize alert"),
+        }
+
+        if let Some(url) = &webhook_url {
+            if let Err(err) = client.post(url).json(&alert).send().await {
+                tracing::error!(error = ?err, "Failed to deliver alert to webhook");
+            }
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct AlertsQuery {
+    // Only return the alerts of this token
+    pub mint: Option<String>,
+    // Number of alerts to return, 1 to 1000 (default 100)
+    pub limit: Option<i64>,
+}
+
+//* This endpoint returns the most recent alerts, use ?mint=<address> to filter by token */
+#[utoipa::path(
+    tag = "alerts",
+    params(AlertsQuery),
+    responses(
+        (status = 200, description = "Alerts, newest first", body = [Alert]),
+        (status = 500, body = ErrorResponse),
+    )
+)]
+#[get("/alerts")]
+pub async fn get_alerts(
+    db: web::Data<Arc<PgPool>>,
+    query: web::Query<AlertsQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_ALERTS_LIMIT)
+        .clamp(1, MAX_ALERTS_LIMIT);
+
+    let result = fetch_recent_alerts(db.get_ref(), query.mint.as_deref(), limit).await?;
+
+    Ok(HttpResponse::Ok().json(&result))
+}
+
+//* Server-sent events stream of alerts as they fire */
+#[utoipa::path(
+    tag = "alerts",
+    responses((status = 200, description = "`alert` events carrying an Alert as JSON", body = String, content_type = "text/event-stream"))
+)]
+#[get("/alerts/stream")]
+pub async fn get_alert_stream(stream: web::Data<broadcast::Sender<Alert>>) -> HttpResponse {
+    let rx = stream.subscribe();
+
+    let events = futures::stream::unfold(rx, |mut rx| async move {
+        loop {
+            match rx.recv().await {
+                Ok(alert) => {
+                    let data = serde_json::to_string(&alert).unwrap_or_default();
+                    let event = web::Bytes::from(format!("event: alert\ndata: {}\n\n", data));
+
+                    return Some((Ok::<_, actix_web::Error>(event), rx));
+                }
+                // A slow client skips the alerts it missed instead of disconnecting
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::mpsc::unbounded_channel;
+
+    use super::*;
+
+    fn trade(wallet: &str, is_buy: bool, token_amount: u64, timestamp: i64) -> TradeData {
+        TradeData {
+            user: wallet.to_string(),
+            is_buy,
+            sol_amount: 0,
+            token_amount,
+            timestamp,
+            virtual_sol_reserves: 0,
+            virtual_token_reserves: 0,
+            real_sol_reserves: 0,
+            real_token_reserves: 0,
+            fee_recipient: "fee".to_string(),
+            fee_basis_points: 0,
+            fee: 0,
+            price_sol: 0.0,
+            price_usd: 0.0,
+            sol_amount_usd: 0.0,
+            market_cap_usd: 0,
+        }
+    }
+
+    fn engine(rule: AlertRule) -> (AlertEngine, UnboundedReceiver<Alert>) {
+        let (tx, rx) = unbounded_channel();
+
+        (AlertEngine::new(vec![rule], tx), rx)
+    }
+
+    fn fired(rx: &mut UnboundedReceiver<Alert>) -> Vec<Alert> {
+        let mut alerts = Vec::new();
+
+        while let Ok(alert) = rx.try_recv() {
+            alerts.push(alert);
+        }
+
+        alerts
+    }
+
+    #[test]
+    fn fires_once_when_the_creator_sells() {
+        let (mut engine, mut rx) = engine(AlertRule::CreatorSell { percent: 50.0 });
+
+        engine.on_create("mint".to_string(), "creator".to_string(), 0);
+        engine.on_trade("mint", &trade("creator", true, 100, 1), None, 1, "a");
+        engine.on_trade("mint", &trade("creator", false, 40, 2), None, 2, "b");
+
+        assert!(fired(&mut rx).is_empty());
+
+        engine.on_trade("mint", &trade("creator", false, 20, 3), None, 3, "c");
+
+        let alerts = fired(&mut rx);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].rule, "creator_sell");
+        assert_eq!(alerts[0].wallet.as_deref(), Some("creator"));
+        assert_eq!(alerts[0].signature, "c");
+        assert!((alerts[0].value - 60.0).abs() < 1e-9);
+
+        engine.on_trade("mint", &trade("creator", false, 40, 4), None, 4, "d");
+
+        assert!(fired(&mut rx).is_empty());
+    }
+
+    #[test]
+    fn needs_the_creator_of_the_token() {
+        let (mut engine, mut rx) = engine(AlertRule::CreatorSell { percent: 50.0 });
+
+        assert!(!engine.is_tracking("mint"));
+
+        engine.on_trade("mint", &trade("creator", true, 100, 1), None, 1, "a");
+        engine.on_trade("mint", &trade("creator", false, 100, 2), None, 2, "b");
+
+        assert!(engine.is_tracking("mint"));
+        assert!(fired(&mut rx).is_empty());
+    }
+
+    #[test]
+    fn fires_when_a_top_holder_dumps_after_launch() {
+        let (mut engine, mut rx) = engine(AlertRule::TopHolderDump {
+            top_n: 1,
+            minutes: 10,
+            percent: 50.0,
+        });
+
+        engine.on_create("mint".to_string(), "creator".to_string(), 0);
+        engine.on_trade("mint", &trade("whale", true, 1_000, 10), None, 1, "a");
+        engine.on_trade("mint", &trade("minnow", true, 10, 20), None, 2, "b");
+
+        // Not a top holder
+        engine.on_trade("mint", &trade("minnow", false, 10, 30), None, 3, "c");
+        assert!(fired(&mut rx).is_empty());
+
+        engine.on_trade("mint", &trade("whale", false, 600, 40), None, 4, "d");
+
+        let alerts = fired(&mut rx);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].rule, "top_holder_dump");
+        assert_eq!(alerts[0].wallet.as_deref(), Some("whale"));
+    }
+
+    #[test]
+    fn ignores_top_holders_after_the_launch_window() {
+        let (mut engine, mut rx) = engine(AlertRule::TopHolderDump {
+            top_n: 1,
+            minutes: 10,
+            percent: 50.0,
+        });
+
+        engine.on_create("mint".to_string(), "creator".to_string(), 0);
+        engine.on_trade("mint", &trade("whale", true, 1_000, 10), None, 1, "a");
+        engine.on_trade("mint", &trade("whale", false, 1_000, 11 * 60), None, 2, "b");
+
+        assert!(fired(&mut rx).is_empty());
+    }
+
+    #[test]
+    fn fires_once_per_window_when_the_market_cap_drops() {
+        let (mut engine, mut rx) = engine(AlertRule::MarketCapDrop {
+            percent: 50.0,
+            window_secs: 300,
+        });
+
+        engine.on_trade("mint", &trade("a", true, 1, 0), Some(1_000), 1, "a");
+        engine.on_trade("mint", &trade("b", false, 1, 30), Some(600), 2, "b");
+
+        assert!(fired(&mut rx).is_empty());
+
+        engine.on_trade("mint", &trade("c", false, 1, 60), Some(400), 3, "c");
+
+        let alerts = fired(&mut rx);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].rule, "market_cap_drop");
+        assert!((alerts[0].value - 60.0).abs() < 1e-9);
+
+        engine.on_trade("mint", &trade("d", false, 1, 90), Some(300), 4, "d");
+        assert!(fired(&mut rx).is_empty());
+
+        // The peak left the window, measured from the market caps of the last 300 seconds again
+        engine.on_trade("mint", &trade("e", true, 1, 400), Some(1_000), 5, "e");
+        engine.on_trade("mint", &trade("f", false, 1, 420), Some(100), 6, "f");
+
+        assert_eq!(fired(&mut rx).len(), 1);
+    }
+}
@@ -0,0 +1,608 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use actix_cors::Cors;
+use actix_web::{
+    body::MessageBody,
+    delete,
+    dev::{ServiceRequest, ServiceResponse},
+    get,
+    http::{
+        header::{HeaderName, HeaderValue, AUTHORIZATION},
+        Method,
+    },
+    middleware::Next,
+    post, web, HttpMessage, HttpRequest, HttpResponse,
+};
+use redis::aio::MultiplexedConnection;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use tokio::sync::{Mutex, RwLock};
+use tokio_util::sync::CancellationToken;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    config::{ApiConfig, RateLimitTier},
+    db::api_key::{
+        create_api_key, fetch_api_key_by_hash, fetch_api_key_usage, fetch_api_keys, revoke_api_key,
+        save_api_key_usage,
+    },
+    errors::{ApiError, ErrorResponse},
+    types::{ApiKey, ApiKeyUsage, OwnerScope},
+};
+
+const API_KEY_HEADER: &str = "x-api-key";
+
+// Browsers can't set headers on EventSource and WebSocket connections, those clients pass the key in the query string
+const API_KEY_QUERY_PARAM: &str = "api_key";
+
+// Probes, metrics scraping and the API docs are served without a key
+const PUBLIC_PATHS: [&str; 4] = ["/healthz", "/readyz", "/metrics", "/openapi.json"];
+const PUBLIC_PREFIXES: [&str; 1] = ["/swagger-ui"];
+
+// Looked up keys kept in memory, the cache is dropped as a whole when it grows past this (e.g. a flood of bad keys)
+const KEY_CACHE_MAX_ENTRIES: usize = 10_000;
+
+const KEY_PREFIX_LEN: usize = 10;
+
+const DEFAULT_USAGE_DAYS: i64 = 30;
+const MAX_USAGE_DAYS: i64 = 366;
+
+// Token bucket kept in a Redis hash so every API instance draws from the same bucket. Redis' clock is used so
+// instances with skewed clocks agree on the refill. Returns {allowed, remaining tokens, ms until the cost is available}.
+const TOKEN_BUCKET_SCRIPT: &str = r#"
+local capacity = tonumber(ARGV[1])
+local refill_per_ms = tonumber(ARGV[2]) / 1000
+local cost = tonumber(ARGV[3])
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+local bucket = redis.call('HMGET', KEYS[1], 'tokens', 'at')
+local tokens = tonumber(bucket[1]) or capacity
+local at = tonumber(bucket[2]) or now
+tokens = math.min(capacity, tokens + math.max(0, now - at) * refill_per_ms)
+local allowed = 0
+local retry_ms = 0
+if tokens >= cost then
+    tokens = tokens - cost
+    allowed = 1
+else
+    retry_ms = math.ceil((cost - tokens) / refill_per_ms)
+end
+redis.call('HSET', KEYS[1], 'tokens', tokens, 'at', now)
+redis.call('PEXPIRE', KEYS[1], math.ceil(capacity / refill_per_ms) + 1000)
+return {allowed, math.floor(tokens), retry_ms}
+"#;
+
+// Who made the request, available to the handlers through the request extensions
+#[derive(Debug, Clone)]
+pub enum Caller {
+    Key(ApiKey),
+    // Holder of API_ADMIN_KEY, not rate limited
+    BootstrapAdmin,
+    // Request without a key while API_AUTH_REQUIRED=false, identified by the peer IP
+    Anonymous(String),
+}
+
+impl Caller {
+    fn is_admin(&self) -> bool {
+        match self {
+            Caller::Key(key) => key.is_admin,
+            Caller::BootstrapAdmin => true,
+            Caller::Anonymous(_) => false,
+        }
+    }
+}
+
+struct RateLimitDecision {
+    allowed: bool,
+    limit: f64,
+    remaining: i64,
+    retry_after_secs: u64,
+}
+
+// Authenticates the API requests, enforces the per-tier rate limits and counts the requests of every key
+pub struct ApiAccess {
+    db: Arc<PgPool>,
+    redis: MultiplexedConnection,
+    config: ApiConfig,
+    token_bucket: redis::Script,
+    // Key hash -> key (None for unknown keys) and when it was looked up
+    keys: RwLock<HashMap<String, (Option<ApiKey>, Instant)>>,
+    // Key id -> (requests served, requests rate limited) since the last flush
+    usage: Mutex<HashMap<String, (i64, i64)>>,
+}
+
+pub fn hash_api_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+fn generate_api_key() -> String {
+    format!(
+        "pk_{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    )
+}
+
+fn is_public(path: &str) -> bool {
+    PUBLIC_PATHS.contains(&path)
+        || PUBLIC_PREFIXES
+            .iter()
+            .any(|prefix| path.starts_with(prefix))
+}
+
+// Key from the X-API-Key header, a bearer token or the api_key query parameter
+fn request_api_key(req: &ServiceRequest) -> Option<String> {
+    if let Some(key) = req
+        .headers()
+        .get(API_KEY_HEADER)
+        .and_then(|value| value.to_str().ok())
+    {
+        return Some(key.trim().to_string());
+    }
+
+    if let Some(key) = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+    {
+        return Some(key.trim().to_string());
+    }
+
+    web::Query::<HashMap<String, String>>::from_query(req.query_string())
+        .ok()
+        .and_then(|query| query.get(API_KEY_QUERY_PARAM).cloned())
+}
+
+impl ApiAccess {
+    pub fn new(db: Arc<PgPool>, redis: MultiplexedConnection, config: ApiConfig) -> Self {
+        Self {
+            db,
+            redis,
+            config,
+            token_bucket: redis::Script::new(TOKEN_BUCKET_SCRIPT),
+            keys: RwLock::new(HashMap::new()),
+            usage: Mutex::new(HashMap::new()),
+        }
+    }
+
+    async fn lookup_key(&self, key: &str) -> Result<Option<ApiKey>, ApiError> {
+        let key_hash = hash_api_key(key);
+        let ttl = Duration::from_secs(self.config.key_cache_ttl_secs);
+
+        if let Some((cached, looked_up_at)) = self.keys.read().await.get(&key_hash) {
+            if looked_up_at.elapsed() < ttl {
+                return Ok(cached.clone());
+            }
+        }
+
+        let api_key = fetch_api_key_by_hash(&self.db, &key_hash).await?;
+
+        let mut keys = self.keys.write().await;
+
+        if keys.len() >= KEY_CACHE_MAX_ENTRIES {
+            keys.clear();
+        }
+
+        keys.insert(key_hash, (api_key.clone(), Instant::now()));
+
+        Ok(api_key)
+    }
+
+    async fn identify(&self, req: &ServiceRequest) -> Result<Caller, ApiError> {
+        let Some(key) = request_api_key(req) else {
+            if self.config.auth_required {
+                return Err(ApiError::Unauthorized(
+                    "Missing API key, pass it in the X-API-Key header".to_string(),
+                ));
+            }
+
+            let ip = req
+                .peer_addr()
+                .map(|addr| addr.ip().to_string())
+                .unwrap_or_else(|| "unknown".to_string());
+
+            return Ok(Caller::Anonymous(ip));
+        };
+
+        // Compared by hash like the stored keys, so the time taken says nothing about how much of the key matched
+        if self
+            .config
+            .admin_key
+            .as_deref()
+            .is_some_and(|admin_key| hash_api_key(admin_key) == hash_api_key(&key))
+        {
+            return Ok(Caller::BootstrapAdmin);
+        }
+
+        match self.lookup_key(&key).await? {
+            Some(api_key) => Ok(Caller::Key(api_key)),
+            None => Err(ApiError::Unauthorized("Invalid API key".to_string())),
+        }
+    }
+
+    fn tier(&self, name: &str) -> Option<RateLimitTier> {
+        self.config.tiers.get(name).copied().or_else(|| {
+            tracing::warn!(tier = %name, "Unknown rate limit tier, using the default tier");
+            self.config.tiers.get(&self.config.default_tier).copied()
+        })
+    }
+
+    // Bucket tokens taken by a request
+    fn request_cost(&self, path: &str) -> f64 {
+        if path == "/tokens" {
+            self.config.tokens_request_cost
+        } else {
+            1.0
+        }
+    }
+
+    async fn check_rate_limit(&self, caller: &Caller, cost: f64) -> Option<RateLimitDecision> {
+        let (bucket, tier) = match caller {
+            Caller::Key(key) => (format!("ratelimit:key:{}", key.id), self.tier(&key.tier)?),
+            Caller::Anonymous(ip) => (
+                format!("ratelimit:ip:{}", ip),
+                self.tier(&self.config.anonymous_tier)?,
+            ),
+            Caller::BootstrapAdmin => return None,
+        };
+
+        let refill_per_sec = tier.refill_per_sec.max(0.001);
+        // A request costing more than the burst could never pass
+        let cost = cost.min(tier.capacity);
+
+        let mut redis = self.redis.clone();
+
+        match self
+            .token_bucket
+            .key(&bucket)
+            .arg(tier.capacity)
+            .arg(refill_per_sec)
+            .arg(cost)
+            .invoke_async::<(i64, i64, i64)>(&mut redis)
+            .await
+        {
+            Ok((allowed, remaining, retry_ms)) => Some(RateLimitDecision {
+                allowed: allowed == 1,
+                limit: tier.capacity,
+                remaining,
+                retry_after_secs: (retry_ms.max(0) as u64).div_ceil(1000),
+            }),
+            // Serving without a limit beats failing every request while Redis is away
+            Err(err) => {
+                tracing::warn!(error = ?err, "Rate limit check failed, letting the request through");
+                None
+            }
+        }
+    }
+
+    // Takes an extra cost from the caller's bucket once the request is already let through, false when the bucket
+    // can't cover it
+    pub async fn charge(&self, caller: &Caller, cost: f64) -> bool {
+        match self.check_rate_limit(caller, cost).await {
+            Some(decision) => decision.allowed,
+            None => true,
+        }
+    }
+
+    async fn record_usage(&self, caller: &Caller, rate_limited: bool) {
+        let Caller::Key(key) = caller else {
+            return;
+        };
+
+        let mut usage = self.usage.lock().await;
+        let entry = usage.entry(key.id.clone()).or_insert((0, 0));
+
+        if rate_limited {
+            entry.1 += 1;
+        } else {
+            entry.0 += 1;
+        }
+    }
+
+    async fn flush_usage(&self) {
+        let usage: Vec<(String, i64, i64)> = self
+            .usage
+            .lock()
+            .await
+            .drain()
+            .map(|(id, (requests, rate_limited))| (id, requests, rate_limited))
+            .collect();
+
+        if usage.is_empty() {
+            return;
+        }
+
+        save_api_key_usage(self.db.clone(), usage).await;
+    }
+}
+
+fn insert_rate_limit_headers<B>(res: &mut ServiceResponse<B>, decision: &RateLimitDecision) {
+    let headers = res.headers_mut();
+
+    if let Ok(limit) = HeaderValue::from_str(&decision.limit.floor().to_string()) {
+        headers.insert(HeaderName::from_static("x-ratelimit-limit"), limit);
+    }
+
+    headers.insert(
+        HeaderName::from_static("x-ratelimit-remaining"),
+        HeaderValue::from(decision.remaining.max(0)),
+    );
+}
+
+// Actix middleware authenticating the request and taking its cost from the caller's token bucket
+pub async fn authenticate(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let access = req.app_data::<web::Data<ApiAccess>>().cloned();
+
+    let Some(access) = access.filter(|_| req.method() != Method::OPTIONS && !is_public(req.path()))
+    else {
+        return Ok(next.call(req).await?.map_into_left_body());
+    };
+
+    let caller = match access.identify(&req).await {
+        Ok(caller) => caller,
+        Err(err) => return Ok(req.error_response(err).map_into_right_body()),
+    };
+
+    let decision = access
+        .check_rate_limit(&caller, access.request_cost(req.path()))
+        .await;
+
+    if let Some(decision) = decision.as_ref().filter(|decision| !decision.allowed) {
+        access.record_usage(&caller, true).await;
+
+        let mut res = req.error_response(ApiError::TooManyRequests {
+            retry_after_secs: decision.retry_after_secs,
+        });
+        insert_rate_limit_headers(&mut res, decision);
+
+        return Ok(res.map_into_right_body());
+    }
+
+    access.record_usage(&caller, false).await;
+
+    req.extensions_mut().insert(caller);
+
+    let mut res = next.call(req).await?;
+
+    if let Some(decision) = &decision {
+        insert_rate_limit_headers(&mut res, decision);
+    }
+
+    Ok(res.map_into_left_body())
+}
+
+// Writes the buffered per key request counts to Postgres
+pub async fn run_usage_flusher(access: web::Data<ApiAccess>, shutdown: CancellationToken) {
+    let interval = Duration::from_secs(access.config.usage_flush_interval_secs.max(1));
+
+    loop {
+        let stopping = tokio::select! {
+            _ = shutdown.cancelled() => true,
+            _ = tokio::time::sleep(interval) => false,
+        };
+
+        access.flush_usage().await;
+
+        if stopping {
+            break;
+        }
+    }
+}
+
+// CORS policy of the API, any origin when API_CORS_ORIGINS contains "*"
+pub fn cors(origins: &[String]) -> Cors {
+    let cors = if origins.iter().any(|origin| origin == "*") {
+        Cors::default().allow_any_origin()
+    } else {
+        origins
+            .iter()
+            .fold(Cors::default(), |cors, origin| cors.allowed_origin(origin))
+    };
+
+    cors.allowed_methods(vec!["GET", "POST", "DELETE"])
+        .allow_any_header()
+        .expose_headers(vec![
+            "x-ratelimit-limit",
+            "x-ratelimit-remaining",
+            "retry-after",
+        ])
+}
+
+// Id of the key that made the request, None for the bootstrap admin and requests without a key
+pub fn caller_key_id(req: &HttpRequest) -> Option<uuid::Uuid> {
+    match req.extensions().get::<Caller>() {
+        Some(Caller::Key(key)) => uuid::Uuid::parse_str(&key.id).ok(),
+        _ => None,
+    }
+}
+
+// What the caller may see of the resources registered through the API. Without the authentication middleware there
+// is no caller and nothing to scope.
+pub fn owner_scope(req: &HttpRequest) -> OwnerScope {
+    match req.extensions().get::<Caller>() {
+        None => OwnerScope::All,
+        Some(caller) if caller.is_admin() => OwnerScope::All,
+        Some(_) => OwnerScope::Key(caller_key_id(req)),
+    }
+}
+
+fn require_admin(req: &HttpRequest) -> Result<(), ApiError> {
+    match req.extensions().get::<Caller>() {
+        Some(caller) if caller.is_admin() => Ok(()),
+        _ => Err(ApiError::Forbidden(
+            "This endpoint requires an admin API key".to_string(),
+        )),
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateApiKeyRequest {
+    pub name: String,
+    // Rate limit tier, API_DEFAULT_TIER when not provided
+    pub tier: Option<String>,
+    #[serde(default)]
+    pub is_admin: bool,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct UsageQuery {
+    // Number of days to return, 1 to 366 (default 30)
+    pub days: Option<i64>,
+}
+
+//* This endpoint creates an API key, the response is the only place where the key itself is returned */
+#[utoipa::path(
+    tag = "admin",
+    request_body = CreateApiKeyRequest,
+    responses(
+        (status = 201, description = "The created key together with its `key`", body = ApiKey),
+        (status = 400, body = ErrorResponse),
+        (status = 403, body = ErrorResponse),
+        (status = 500, body = ErrorResponse),
+    )
+)]
+#[post("/admin/api-keys")]
+pub async fn post_api_key(
+    req: HttpRequest,
+    access: web::Data<ApiAccess>,
+    body: web::Json<CreateApiKeyRequest>,
+) -> Result<HttpResponse, ApiError> {
+    require_admin(&req)?;
+
+    if body.name.trim().is_empty() {
+        return Err(ApiError::BadRequest("name must not be empty".to_string()));
+    }
+
+    let tier = body
+        .tier
+        .clone()
+        .unwrap_or_else(|| access.config.default_tier.clone());
+
+    if !access.config.tiers.contains_key(&tier) {
+        let mut tiers: Vec<&str> = access.config.tiers.keys().map(|t| t.as_str()).collect();
+        tiers.sort();
+
+        return Err(ApiError::BadRequest(format!(
+            "Unknown tier {}, expected one of {}",
+            tier,
+            tiers.join(", ")
+        )));
+    }
+
+    let key = generate_api_key();
+
+    let api_key = create_api_key(
+        &access.db,
+        body.name.trim(),
+        &key[..KEY_PREFIX_LEN],
+        &hash_api_key(&key),
+        &tier,
+        body.is_admin,
+    )
+    .await?;
+
+    let mut result = serde_json::to_value(&api_key).unwrap_or_default();
+    result["key"] = serde_json::Value::String(key);
+
+    Ok(HttpResponse::Created().json(result))
+}
+
+//* This endpoint returns every API key, revoked ones included */
+#[utoipa::path(
+    tag = "admin",
+    responses(
+        (status = 200, body = [ApiKey]),
+        (status = 403, body = ErrorResponse),
+        (status = 500, body = ErrorResponse),
+    )
+)]
+#[get("/admin/api-keys")]
+pub async fn get_api_keys(
+    req: HttpRequest,
+    access: web::Data<ApiAccess>,
+) -> Result<HttpResponse, ApiError> {
+    require_admin(&req)?;
+
+    let result = fetch_api_keys(&access.db).await?;
+
+    Ok(HttpResponse::Ok().json(&result))
+}
+
+// API key ids are UUIDs, anything else can't name a key
+fn parse_api_key_id(id: &str) -> Result<uuid::Uuid, ApiError> {
+    uuid::Uuid::parse_str(id)
+        .map_err(|_| ApiError::BadRequest(format!("Invalid API key id {}", id)))
+}
+
+//* This endpoint revokes an API key. Other API instances keep accepting it for up to API_KEY_CACHE_TTL_SECS. */
+#[utoipa::path(
+    tag = "admin",
+    params(("id" = String, Path, description = "API key id")),
+    responses(
+        (status = 200, body = ApiKey),
+        (status = 400, body = ErrorResponse),
+        (status = 403, body = ErrorResponse),
+        (status = 404, body = ErrorResponse),
+        (status = 500, body = ErrorResponse),
+    )
+)]
+#[delete("/admin/api-keys/{id}")]
+pub async fn delete_api_key(
+    req: HttpRequest,
+    access: web::Data<ApiAccess>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    require_admin(&req)?;
+
+    let id = parse_api_key_id(&id)?;
+
+    match revoke_api_key(&access.db, id).await? {
+        Some(result) => {
+            access.keys.write().await.clear();
+
+            Ok(HttpResponse::Ok().json(&result))
+        }
+        None => Err(ApiError::NotFound("API key not found".to_string())),
+    }
+}
+
+//* This endpoint returns the daily request counts of an API key */
+#[utoipa::path(
+    tag = "admin",
+    params(("id" = String, Path, description = "API key id"), UsageQuery),
+    responses(
+        (status = 200, description = "Daily usage, newest first", body = [ApiKeyUsage]),
+        (status = 400, body = ErrorResponse),
+        (status = 403, body = ErrorResponse),
+        (status = 500, body = ErrorResponse),
+    )
+)]
+#[get("/admin/api-keys/{id}/usage")]
+pub async fn get_api_key_usage(
+    req: HttpRequest,
+    access: web::Data<ApiAccess>,
+    id: web::Path<String>,
+    query: web::Query<UsageQuery>,
+) -> Result<HttpResponse, ApiError> {
+    require_admin(&req)?;
+
+    let days = query
+        .days
+        .unwrap_or(DEFAULT_USAGE_DAYS)
+        .clamp(1, MAX_USAGE_DAYS);
+
+    let id = parse_api_key_id(&id)?;
+
+    let result = fetch_api_key_usage(&access.db, id, days).await?;
+
+    Ok(HttpResponse::Ok().json(&result))
+}
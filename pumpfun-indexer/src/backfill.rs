@@ -0,0 +1,383 @@
+use std::{str::FromStr, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use carbon_core::{
+    datasource::{Datasource, TransactionUpdate, Update, UpdateType},
+    error::{CarbonResult, Error as CarbonError},
+    metrics::MetricsCollection,
+    transformers::transaction_metadata_from_original_meta,
+};
+use carbon_pumpfun_decoder::PROGRAM_ID;
+use solana_client::{
+    nonblocking::rpc_client::RpcClient,
+    rpc_client::GetConfirmedSignaturesForAddress2Config,
+    rpc_config::RpcTransactionConfig,
+};
+use solana_sdk::{commitment_config::CommitmentConfig, signature::Signature};
+use solana_transaction_status::UiTransactionEncoding;
+use sqlx::PgPool;
+use tokio::sync::mpsc::UnboundedSender;
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    checkpoint::{CheckpointTracker, LIVE_DATASOURCE},
+    db::checkpoint::get_checkpoint,
+    leader::Leadership,
+    sinks::EVENT_SINK_DATASOURCE,
+    types::Checkpoint,
+};
+
+// Maximum number of signatures returned by a single getSignaturesForAddress call
+const SIGNATURES_PAGE_LIMIT: usize = 1000;
+
+// Longest wait between two attempts of a failed RPC call
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+// Replayed transactions that don't reach the processor for this long are considered to have nothing to process
+const REPLAY_STALL_TIMEOUT: Duration = Duration::from_secs(60);
+
+// Datasource that replays every Pump.fun transaction between the last checkpoint and the live head, then stops.
+// The checkpoint is held back until every replayed transaction has been processed, failed RPC calls are retried
+// until they succeed so the gap is never skipped.
+pub struct CheckpointBackfill {
+    pub rpc_url: String,
+    pub checkpoint: Checkpoint,
+    pub tracker: CheckpointTracker,
+    // Cancelled once the backfill returns, lets a one-shot run stop the pipeline when the gap is replayed. The run
+    // then clears the backfilling flag itself once the pipeline has drained.
+    pub done: Option<CancellationToken>,
+}
+
+// Waits before the next attempt, doubling from a second up to MAX_RETRY_DELAY. Returns false once cancelled.
+async fn retry_backoff(attempt: u32, cancellation_token: &CancellationToken) -> bool {
+    let delay = Duration::from_secs(1 << attempt.min(6)).min(MAX_RETRY_DELAY);
+
+    tokio::select! {
+        _ = cancellation_token.cancelled() => false,
+        _ = tokio::time::sleep(delay) => true,
+    }
+}
+
+// Walks the program signatures backwards from the head until `until` is reached, returned oldest first
+pub async fn fetch_signatures_since(
+    rpc: &RpcClient,
+    until: Signature,
+) -> CarbonResult<Vec<Signature>> {
+    Ok(fetch_signature_times_since(rpc, until)
+        .await?
+        .into_iter()
+        .map(|(signature, _)| signature)
+        .collect())
+}
+
+// Same as `fetch_signatures_since`, with the block time of each signature when the node reports it
+pub async fn fetch_signature_times_since(
+    rpc: &RpcClient,
+    until: Signature,
+) -> CarbonResult<Vec<(Signature, Option<i64>)>> {
+    let mut signatures = Vec::new();
+    let mut before = None;
+
+    loop {
+        let page = rpc
+            .get_signatures_for_address_with_config(
+                &PROGRAM_ID,
+                GetConfirmedSignaturesForAddress2Config {
+                    before,
+                    until: Some(until),
+                    limit: Some(SIGNATURES_PAGE_LIMIT),
+                    commitment: Some(CommitmentConfig::confirmed()),
+                },
+            )
+            .await
+            .map_err(|err| CarbonError::Custom(format!("Failed to fetch signatures: {}", err)))?;
+
+        let Some(last) = page.last() else {
+            break;
+        };
+
+        before = Signature::from_str(&last.signature).ok();
+
+        for status in page.iter() {
+            if status.err.is_some() {
+                continue;
+            }
+
+            if let Ok(signature) = Signature::from_str(&status.signature) {
+                signatures.push((signature, status.block_time));
+            }
+        }
+
+        if page.len() < SIGNATURES_PAGE_LIMIT {
+            break;
+        }
+    }
+
+    // Replay in chronological order so trades of a token stay ordered
+    signatures.reverse();
+
+    Ok(signatures)
+}
+
+// Fetches a confirmed transaction and turns it into a pipeline update. Fails if the RPC call fails, None if the
+// transaction can't be decoded.
+pub async fn fetch_transaction_update(
+    rpc: &RpcClient,
+    signature: Signature,
+) -> CarbonResult<Option<Update>> {
+    let transaction = rpc
+        .get_transaction_with_config(
+            &signature,
+            RpcTransactionConfig {
+                encoding: Some(UiTransactionEncoding::Base64),
+                commitment: Some(CommitmentConfig::confirmed()),
+                max_supported_transaction_version: Some(0),
+            },
+        )
+        .await
+        .map_err(|err| {
+            CarbonError::Custom(format!(
+                "Failed to fetch transaction {}: {}",
+                signature, err
+            ))
+        })?;
+
+    let Some(meta) = transaction.transaction.meta else {
+        tracing::error!("Transaction {} has no meta", signature);
+        return Ok(None);
+    };
+
+    let Some(decoded) = transaction.transaction.transaction.decode() else {
+        tracing::error!("Failed to decode transaction {}", signature);
+        return Ok(None);
+    };
+
+    let Ok(meta) = transaction_metadata_from_original_meta(meta) else {
+        tracing::error!("Failed to convert transaction meta for {}", signature);
+        return Ok(None);
+    };
+
+    Ok(Some(Update::Transaction(Box::new(TransactionUpdate {
+        signature,
+        transaction: decoded,
+        meta,
+        is_vote: false,
+        slot: transaction.slot,
+        block_time: transaction.block_time,
+    }))))
+}
+
+#[async_trait]
+impl Datasource for CheckpointBackfill {
+    async fn consume(
+        &self,
+        sender: &UnboundedSender<Update>,
+        cancellation_token: CancellationToken,
+        _metrics: Arc<MetricsCollection>,
+    ) -> CarbonResult<()> {
+        let _done = self.done.clone().map(|done| done.drop_guard());
+
+        let rpc =
+            RpcClient::new_with_commitment(self.rpc_url.clone(), CommitmentConfig::confirmed());
+
+        // Every early return below leaves the flag set, so the checkpoint can't move past the gap
+        self.tracker.begin_replay();
+
+        let until = Signature::from_str(&self.checkpoint.signature)
+            .map_err(|err| CarbonError::Custom(format!("Invalid checkpoint signature: {}", err)))?;
+
+        let mut attempt = 0;
+
+        let signatures = loop {
+            match fetch_signatures_since(&rpc, until).await {
+                Ok(signatures) => break signatures,
+                Err(err) => {
+                    tracing::error!(
+                        attempt,
+                        "Backfill failed to list missed transactions: {:?}",
+                        err
+                    );
+                }
+            }
+
+            if !retry_backoff(attempt, &cancellation_token).await {
+                tracing::info!("Backfill cancelled before listing the missed transactions");
+                return Ok(());
+            }
+
+            attempt += 1;
+        };
+
+        tracing::info!(
+            "Backfilling {} transactions since slot {}",
+            signatures.len(),
+            self.checkpoint.slot
+        );
+
+        for signature in signatures {
+            let mut attempt = 0;
+
+            let update = loop {
+                if cancellation_token.is_cancelled() {
+                    // Leave the checkpoint untouched so the next start resumes the same gap
+                    tracing::info!("Backfill cancelled before reaching the live head");
+                    return Ok(());
+                }
+
+                match fetch_transaction_update(&rpc, signature).await {
+                    Ok(update) => break update,
+                    Err(err) => tracing::error!(attempt, "Backfill failed: {:?}", err),
+                }
+
+                if !retry_backoff(attempt, &cancellation_token).await {
+                    tracing::info!("Backfill cancelled before reaching the live head");
+                    return Ok(());
+                }
+
+                attempt += 1;
+            };
+
+            // Only a transaction that can't be decoded is skipped, the pipeline couldn't process it either
+            let Some(update) = update else {
+                continue;
+            };
+
+            self.tracker.expect_replayed(signature.to_string());
+
+            if sender.send(update).is_err() {
+                tracing::error!("Pipeline closed while backfilling");
+                return Ok(());
+            }
+        }
+
+        self.tracker.replay_sent();
+
+        if self.done.is_some() {
+            tracing::info!("Backfill sent every missed transaction");
+            return Ok(());
+        }
+
+        if !self
+            .tracker
+            .wait_replayed(REPLAY_STALL_TIMEOUT, &cancellation_token)
+            .await
+        {
+            tracing::info!("Backfill cancelled before its transactions were processed");
+            return Ok(());
+        }
+
+        tracing::info!("Backfill reached the live head");
+
+        self.tracker.set_backfilling(false);
+
+        Ok(())
+    }
+
+    fn update_types(&self) -> Vec<UpdateType> {
+        vec![UpdateType::Transaction]
+    }
+}
+
+// Where to resume from. The event sink falls behind the live checkpoint while its broker is down, so the earlier of
+// the two is used when publishing is enabled.
+pub async fn resume_checkpoint(
+    db: Arc<PgPool>,
+    publishing: bool,
+) -> Result<Option<Checkpoint>, anyhow::Error> {
+    let last_checkpoint = get_checkpoint(db.clone(), LIVE_DATASOURCE).await?;
+
+    if !publishing {
+        return Ok(last_checkpoint);
+    }
+
+    let sink_checkpoint = get_checkpoint(db, EVENT_SINK_DATASOURCE).await?;
+
+    Ok(match (last_checkpoint, sink_checkpoint) {
+        (Some(live), Some(sink)) if sink.slot < live.slot => Some(sink),
+        (live, _) => live,
+    })
+}
+
+// Datasource that replays the gap since the persisted checkpoint every time this replica takes over the ingest
+// leadership. A standby processes the same events as the leader but doesn't write them, so whatever the previous
+// leader didn't get to before it stepped down is written by the replay.
+pub struct FailoverBackfill {
+    pub rpc_url: String,
+    pub db: Arc<PgPool>,
+    // Whether the event sink is enabled, its checkpoint is resumed from too
+    pub publishing: bool,
+    pub tracker: CheckpointTracker,
+    pub leadership: Leadership,
+}
+
+#[async_trait]
+impl Datasource for FailoverBackfill {
+    async fn consume(
+        &self,
+        sender: &UnboundedSender<Update>,
+        cancellation_token: CancellationToken,
+        metrics: Arc<MetricsCollection>,
+    ) -> CarbonResult<()> {
+        let mut leadership = self.leadership.clone();
+
+        loop {
+            let leader = tokio::select! {
+                _ = cancellation_token.cancelled() => return Ok(()),
+                leader = leadership.changed() => leader,
+            };
+
+            if !leader {
+                continue;
+            }
+
+            // Held from the moment the leadership is acquired, the progress of the standby must not be persisted
+            self.tracker.begin_replay();
+
+            let mut attempt = 0;
+
+            let checkpoint = loop {
+                match resume_checkpoint(self.db.clone(), self.publishing).await {
+                    Ok(checkpoint) => break checkpoint,
+                    Err(err) => {
+                        tracing::error!(
+                            attempt,
+                            "Failover failed to load the checkpoint: {:?}",
+                            err
+                        );
+                    }
+                }
+
+                if !retry_backoff(attempt, &cancellation_token).await {
+                    return Ok(());
+                }
+
+                attempt += 1;
+            };
+
+            let Some(checkpoint) = checkpoint else {
+                tracing::info!("No checkpoint yet, nothing to replay after the takeover");
+                self.tracker.set_backfilling(false);
+                continue;
+            };
+
+            tracing::info!(
+                "Replaying from checkpoint at slot {} ({}) after the takeover",
+                checkpoint.slot,
+                checkpoint.signature
+            );
+
+            CheckpointBackfill {
+                rpc_url: self.rpc_url.clone(),
+                checkpoint,
+                tracker: self.tracker.clone(),
+                done: None,
+            }
+            .consume(sender, cancellation_token.clone(), metrics.clone())
+            .await?;
+        }
+    }
+
+    fn update_types(&self) -> Vec<UpdateType> {
+        vec![UpdateType::Transaction]
+    }
+}
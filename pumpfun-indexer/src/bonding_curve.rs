@@ -0,0 +1,57 @@
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+
+// Pump.fun tokens all use 6 decimals
+pub const TOKEN_DECIMALS: u32 = 6;
+
+// Every Pump.fun token is minted with a fixed supply of one billion tokens
+pub const TOTAL_SUPPLY: u64 = 1_000_000_000;
+
+// Virtual token reserves of a new curve and the tokens sold by the time it completes, in base units
+const INITIAL_VIRTUAL_TOKEN_RESERVES: i128 = 1_073_000_000 * 10i128.pow(TOKEN_DECIMALS);
+const CURVE_TOKEN_SUPPLY: i128 = 793_100_000 * 10i128.pow(TOKEN_DECIMALS);
+
+// Function to calculate the bonding curve progress in percent based on the virtual token reserve
+pub fn bonding_curve_progress(virtual_token_reserve: i128) -> i128 {
+    (INITIAL_VIRTUAL_TOKEN_RESERVES - virtual_token_reserve) * 100 / CURVE_TOKEN_SUPPLY
+}
+
+// Function to calculate the token price in SOL from the virtual reserves
+pub fn price_sol(virtual_sol_reserves: u64, virtual_token_reserves: u64) -> f64 {
+    let sol_reserves = virtual_sol_reserves as f64 / LAMPORTS_PER_SOL as f64;
+    let token_reserves = virtual_token_reserves as f64 / 10f64.powi(TOKEN_DECIMALS as i32);
+
+    if token_reserves > 0.0 {
+        sol_reserves / token_reserves
+    } else {
+        0.0
+    }
+}
+
+// Function to calculate the market cap based on the virtual reserves, total supply, and SOL price in USD. The
+// reserves are truncated to whole SOL and tokens, which is what the stored market caps have always been based on.
+pub fn market_cap_usd(
+    virtual_sol_reserves: u64,
+    virtual_token_reserve: u64,
+    decimals: u32,
+    total_supply: u64,
+    sol_price_usd: f64,
+) -> i64 {
+    let sol_reserve = virtual_sol_reserves / LAMPORTS_PER_SOL;
+    let token_reserve = virtual_token_reserve / 10u64.pow(decimals);
+
+    let token_price_sol: f64 = sol_reserve as f64 / token_reserve as f64;
+
+    let token_price_usd = token_price_sol * sol_price_usd;
+
+    tracing::debug!(
+        sol_price_usd,
+        sol_reserve,
+        token_reserve,
+        token_price_usd,
+        "computed token price"
+    );
+
+    let mc = token_price_usd * total_supply as f64;
+
+    mc as i64
+}
@@ -0,0 +1,427 @@
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    num::NonZeroUsize,
+    sync::{Arc, Mutex as StdMutex},
+    time::{Duration, Instant},
+};
+
+use actix_web::{
+    http::header::{CACHE_CONTROL, ETAG, IF_NONE_MATCH},
+    web, HttpRequest, HttpResponse,
+};
+use lru::LruCache;
+use redis::{aio::MultiplexedConnection, AsyncCommands};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tokio::sync::{
+    mpsc::{UnboundedReceiver, UnboundedSender},
+    Mutex, RwLock,
+};
+use tokio_util::sync::CancellationToken;
+
+use crate::{config::CacheConfig, errors::ApiError};
+
+// Tags the cached responses depend on, invalidating a tag makes every response carrying it stale. The token list has
+// none, it changes with every trade and is only refreshed when it expires.
+pub const TRENDING_TAG: &str = "trending";
+
+pub fn token_tag(mint: &str) -> String {
+    format!("token:{}", mint)
+}
+
+pub fn candles_tag(mint: &str) -> String {
+    format!("candles:{}", mint)
+}
+
+struct CacheEntry {
+    body: web::Bytes,
+    etag: String,
+    expires_at: Instant,
+}
+
+// Read-through cache of serialized API responses, an in-process LRU in front of an optional shared Redis.
+//
+// Instead of deleting entries, invalidation bumps a generation counter per tag and the generations of its tags are
+// part of every cache key. Stale entries are simply never read again and age out, which works the same for the
+// local LRU of every API instance and for Redis. The generations live in Redis when it is configured so that
+// invalidations made by the indexer reach every instance.
+pub struct ResponseCache {
+    config: CacheConfig,
+    local: Mutex<LruCache<String, CacheEntry>>,
+    generations: RwLock<HashMap<String, u64>>,
+    // Keys being computed, the concurrent misses of a key wait for the first one instead of computing it again
+    computing: StdMutex<HashMap<String, Arc<Mutex<()>>>>,
+    redis: Option<MultiplexedConnection>,
+}
+
+// Removes a key from the computing map once its request is done. actix drops the handler future of a disconnected
+// client, so this runs whether the computation finished or was aborted.
+struct ComputingGuard<'a> {
+    computing: &'a StdMutex<HashMap<String, Arc<Mutex<()>>>>,
+    key: String,
+    lock: Arc<Mutex<()>>,
+}
+
+impl Drop for ComputingGuard<'_> {
+    fn drop(&mut self) {
+        let mut computing = self.computing.lock().unwrap();
+
+        // A later miss may have started over with its own lock
+        if matches!(computing.get(&self.key), Some(lock) if Arc::ptr_eq(lock, &self.lock)) {
+            computing.remove(&self.key);
+        }
+    }
+}
+
+fn etag(body: &[u8]) -> String {
+    format!("\"{}\"", &hex::encode(Sha256::digest(body))[..32])
+}
+
+// True when one of the tags of If-None-Match is the current ETag
+fn etag_matches(req: &HttpRequest, etag: &str) -> bool {
+    req.headers()
+        .get_all(IF_NONE_MATCH)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|tag| tag.trim().trim_start_matches("W/"))
+        .any(|tag| tag == etag || tag == "*")
+}
+
+impl ResponseCache {
+    pub fn new(config: CacheConfig, redis: Option<MultiplexedConnection>) -> Self {
+        let capacity = NonZeroUsize::new(config.max_entries).unwrap_or(NonZeroUsize::MIN);
+
+        Self {
+            config,
+            local: Mutex::new(LruCache::new(capacity)),
+            generations: RwLock::new(HashMap::new()),
+            computing: StdMutex::new(HashMap::new()),
+            redis,
+        }
+    }
+
+    pub fn config(&self) -> &CacheConfig {
+        &self.config
+    }
+
+    // True when the entries and generations live in Redis and are shared with the other processes
+    pub fn is_shared(&self) -> bool {
+        self.redis.is_some()
+    }
+
+    async fn generations(&self, tags: &[String]) -> Vec<u64> {
+        if tags.is_empty() {
+            return Vec::new();
+        }
+
+        if let Some(redis) = &self.redis {
+            let keys: Vec<String> = tags
+                .iter()
+                .map(|tag| format!("cache:gen:{}", tag))
+                .collect();
+            let mut redis = redis.clone();
+
+            // MGET rather than mget(), which sends a plain GET for a single key
+            match redis::cmd("MGET")
+                .arg(&keys)
+                .query_async::<Vec<Option<u64>>>(&mut redis)
+                .await
+            {
+                Ok(generations) => {
+                    return generations
+                        .into_iter()
+                        .map(|generation| generation.unwrap_or(0))
+                        .collect()
+                }
+                Err(err) => {
+                    tracing::warn!(error = ?err, "Failed to read cache generations from Redis")
+                }
+            }
+        }
+
+        let generations = self.generations.read().await;
+
+        tags.iter()
+            .map(|tag| generations.get(tag).copied().unwrap_or(0))
+            .collect()
+    }
+
+    // Makes every cached response carrying one of the tags stale
+    pub async fn invalidate(&self, tags: &[String]) {
+        if tags.is_empty() {
+            return;
+        }
+
+        {
+            let mut generations = self.generations.write().await;
+
+            for tag in tags {
+                *generations.entry(tag.clone()).or_insert(0) += 1;
+            }
+        }
+
+        if let Some(redis) = &self.redis {
+            let mut pipe = redis::pipe();
+
+            for tag in tags {
+                pipe.incr(format!("cache:gen:{}", tag), 1).ignore();
+            }
+
+            let mut redis = redis.clone();
+
+            if let Err(err) = pipe.query_async::<()>(&mut redis).await {
+                tracing::warn!(error = ?err, "Failed to invalidate cached responses in Redis");
+            }
+        }
+    }
+
+    async fn get(&self, key: &str) -> Option<(web::Bytes, String)> {
+        {
+            let mut local = self.local.lock().await;
+
+            match local.get(key) {
+                Some(entry) if entry.expires_at > Instant::now() => {
+                    return Some((entry.body.clone(), entry.etag.clone()))
+                }
+                Some(_) => {
+                    local.pop(key);
+                }
+                None => {}
+            }
+        }
+
+        let mut redis = self.redis.clone()?;
+
+        let (body, ttl_ms) = match redis::pipe()
+            .get(format!("cache:{}", key))
+            .pttl(format!("cache:{}", key))
+            .query_async::<(Option<Vec<u8>>, i64)>(&mut redis)
+            .await
+        {
+            Ok((Some(body), ttl_ms)) if ttl_ms > 0 => (web::Bytes::from(body), ttl_ms as u64),
+            Ok(_) => return None,
+            Err(err) => {
+                tracing::warn!(error = ?err, "Failed to read cached response from Redis");
+                return None;
+            }
+        };
+
+        let etag = etag(&body);
+
+        self.local.lock().await.put(
+            key.to_string(),
+            CacheEntry {
+                body: body.clone(),
+                etag: etag.clone(),
+                expires_at: Instant::now() + Duration::from_millis(ttl_ms),
+            },
+        );
+
+        Some((body, etag))
+    }
+
+    async fn put(&self, key: &str, body: web::Bytes, etag: String, ttl: Duration) {
+        if let Some(redis) = &self.redis {
+            let mut redis = redis.clone();
+
+            if let Err(err) = redis
+                .set_ex::<_, _, ()>(
+                    format!("cache:{}", key),
+                    body.as_ref(),
+                    ttl.as_secs().max(1),
+                )
+                .await
+            {
+                tracing::warn!(error = ?err, "Failed to store cached response in Redis");
+            }
+        }
+
+        self.local.lock().await.put(
+            key.to_string(),
+            CacheEntry {
+                body,
+                etag,
+                expires_at: Instant::now() + ttl,
+            },
+        );
+    }
+
+    // Serves the JSON response of `key` from the cache, computing and storing it on a miss. Concurrent misses of a key
+    // in this process compute it once. The response carries an ETag and a client sending it back in If-None-Match
+    // gets a 304 without the body.
+    pub async fn json<T, F>(
+        &self,
+        req: &HttpRequest,
+        key: &str,
+        tags: &[String],
+        ttl: Duration,
+        compute: F,
+    ) -> Result<HttpResponse, ApiError>
+    where
+        T: Serialize,
+        F: Future<Output = Result<T, ApiError>>,
+    {
+        if !self.config.enabled {
+            let (body, etag) = Self::serialize(compute.await?)?;
+
+            return Ok(Self::respond(req, body, etag, ttl, false));
+        }
+
+        let generations = self.generations(tags).await;
+
+        let key = format!(
+            "{}@{}",
+            key,
+            generations
+                .iter()
+                .map(|generation| generation.to_string())
+                .collect::<Vec<_>>()
+                .join(".")
+        );
+
+        if let Some((body, etag)) = self.get(&key).await {
+            return Ok(Self::respond(req, body, etag, ttl, true));
+        }
+
+        let lock = self
+            .computing
+            .lock()
+            .unwrap()
+            .entry(key.clone())
+            .or_default()
+            .clone();
+        let computing = ComputingGuard {
+            computing: &self.computing,
+            key: key.clone(),
+            lock,
+        };
+        let _computing = computing.lock.lock().await;
+
+        // Computed by the request we waited on
+        if let Some((body, etag)) = self.get(&key).await {
+            return Ok(Self::respond(req, body, etag, ttl, true));
+        }
+
+        let result = match compute.await {
+            Ok(result) => Self::serialize(result),
+            Err(err) => Err(err),
+        };
+
+        // The waiters still hold the lock and find the stored response, later requests read it directly
+        if let Ok((body, etag)) = &result {
+            self.put(&key, body.clone(), etag.clone(), ttl).await;
+        }
+
+        let (body, etag) = result?;
+
+        Ok(Self::respond(req, body, etag, ttl, false))
+    }
+
+    fn serialize<T: Serialize>(result: T) -> Result<(web::Bytes, String), ApiError> {
+        let body = web::Bytes::from(
+            serde_json::to_vec(&result).map_err(|err| ApiError::Internal(err.into()))?,
+        );
+        let etag = etag(&body);
+
+        Ok((body, etag))
+    }
+
+    fn respond(
+        req: &HttpRequest,
+        body: web::Bytes,
+        etag: String,
+        ttl: Duration,
+        hit: bool,
+    ) -> HttpResponse {
+        let cache_control = format!("private, max-age={}", ttl.as_secs());
+        let cache_status = if hit { "hit" } else { "miss" };
+
+        if etag_matches(req, &etag) {
+            return HttpResponse::NotModified()
+                .insert_header((ETAG, etag))
+                .insert_header((CACHE_CONTROL, cache_control))
+                .insert_header(("X-Cache", cache_status))
+                .finish();
+        }
+
+        HttpResponse::Ok()
+            .content_type("application/json")
+            .insert_header((ETAG, etag))
+            .insert_header((CACHE_CONTROL, cache_control))
+            .insert_header(("X-Cache", cache_status))
+            .body(body)
+    }
+}
+
+// Hands the cache tags touched by the processed events to the invalidation task. Owned by the instruction processor,
+// does nothing when the cache is disabled.
+#[derive(Debug, Clone, Default)]
+pub struct CacheInvalidator {
+    sender: Option<UnboundedSender<String>>,
+}
+
+impl CacheInvalidator {
+    pub fn new(sender: Option<UnboundedSender<String>>) -> Self {
+        Self { sender }
+    }
+
+    fn send(&self, tag: String) {
+        if let Some(sender) = &self.sender {
+            let _ = sender.send(tag);
+        }
+    }
+
+    // A token was created or its trades, status or market cap changed
+    pub fn token_changed(&self, mint: &str) {
+        self.send(token_tag(mint));
+        self.send(candles_tag(mint));
+    }
+
+    pub fn rankings_changed(&self) {
+        self.send(TRENDING_TAG.to_string());
+    }
+}
+
+// Applies the invalidations of the processor, the tags received within the debounce window are bumped together.
+// Once the flush token is cancelled the final flushes still invalidate what they write, so the tags are applied
+// right away until every invalidator has been dropped.
+pub async fn run_cache_invalidator(
+    cache: Arc<ResponseCache>,
+    mut rx: UnboundedReceiver<String>,
+    shutdown: CancellationToken,
+) {
+    let debounce = Duration::from_millis(cache.config().invalidation_debounce_ms);
+
+    let mut stopping = false;
+
+    loop {
+        let first = tokio::select! {
+            tag = rx.recv() => match tag {
+                Some(tag) => tag,
+                None => break,
+            },
+            _ = shutdown.cancelled(), if !stopping => {
+                stopping = true;
+                continue;
+            }
+        };
+
+        let mut tags = HashSet::from([first]);
+
+        if !stopping {
+            stopping = tokio::select! {
+                _ = shutdown.cancelled() => true,
+                _ = tokio::time::sleep(debounce) => false,
+            };
+        }
+
+        while let Ok(tag) = rx.try_recv() {
+            tags.insert(tag);
+        }
+
+        cache
+            .invalidate(&tags.into_iter().collect::<Vec<_>>())
+            .await;
+    }
+}
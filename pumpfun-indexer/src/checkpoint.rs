@@ -0,0 +1,265 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use sqlx::PgPool;
+use tokio::sync::RwLock;
+use tokio_util::sync::CancellationToken;
+
+use crate::{db::checkpoint::save_checkpoint, types::Checkpoint};
+
+// Name under which the live websocket progress is checkpointed
+pub const LIVE_DATASOURCE: &str = "helius_websocket";
+
+// Number of recently processed events remembered to de-duplicate the backfill/live overlap
+const SEEN_EVENTS_CAPACITY: usize = 100_000;
+
+#[derive(Debug, Default)]
+struct CheckpointState {
+    // Slot and signature of the latest event seen, the slot may still receive more events
+    current: Option<(u64, String)>,
+    // Highest slot for which every event has been processed
+    completed: Option<(u64, String)>,
+}
+
+// Progress of the transactions a backfill replays through the processor
+#[derive(Debug, Default)]
+struct ReplayState {
+    // Replayed transactions whose events haven't all been dispatched yet
+    pending: HashSet<String>,
+    // Number of transactions sent to the pipeline
+    replayed: usize,
+    // Transaction and slot of the last dispatched event. The events of a transaction are dispatched one after the
+    // other, so it is complete once an event of another transaction shows up.
+    current: Option<(String, u64)>,
+    // Highest slot of the fully dispatched replayed transactions
+    last_slot: u64,
+    // Every missed transaction has been sent to the pipeline
+    sent: bool,
+    // Every event of the replayed transactions has been processed
+    processed: bool,
+}
+
+// Tracks the highest fully-processed slot of a datasource and periodically persists it.
+#[derive(Debug, Clone)]
+pub struct CheckpointTracker {
+    datasource: String,
+    state: Arc<RwLock<CheckpointState>>,
+    // While a catch-up backfill is running the live stream is ahead of it, so persisting would skip the gap
+    backfilling: Arc<AtomicBool>,
+    replay: Arc<Mutex<ReplayState>>,
+}
+
+impl CheckpointTracker {
+    pub fn new(datasource: &str) -> Self {
+        Self {
+            datasource: datasource.to_string(),
+            state: Arc::new(RwLock::new(CheckpointState::default())),
+            backfilling: Arc::new(AtomicBool::new(false)),
+            replay: Arc::new(Mutex::new(ReplayState::default())),
+        }
+    }
+
+    // Tracker for another consumer of the same event stream, it shares the backfill state of this one
+    pub fn follower(&self, datasource: &str) -> Self {
+        Self {
+            datasource: datasource.to_string(),
+            state: Arc::new(RwLock::new(CheckpointState::default())),
+            backfilling: self.backfilling.clone(),
+            replay: self.replay.clone(),
+        }
+    }
+
+    pub fn set_backfilling(&self, backfilling: bool) {
+        self.backfilling.store(backfilling, Ordering::SeqCst);
+    }
+
+    pub fn is_backfilling(&self) -> bool {
+        self.backfilling.load(Ordering::SeqCst)
+    }
+
+    // Starts tracking a new replay, the checkpoint is held back until it has been processed
+    pub fn begin_replay(&self) {
+        *self.replay.lock().unwrap() = ReplayState::default();
+
+        self.set_backfilling(true);
+    }
+
+    // Records a transaction before the backfill sends it to the pipeline
+    pub fn expect_replayed(&self, signature: String) {
+        let mut replay = self.replay.lock().unwrap();
+
+        replay.pending.insert(signature);
+        replay.replayed += 1;
+    }
+
+    // Every missed transaction has been sent, what is left is for the pipeline to process them
+    pub fn replay_sent(&self) {
+        let mut replay = self.replay.lock().unwrap();
+
+        replay.sent = true;
+        replay.processed = replay.replayed == 0;
+    }
+
+    pub fn is_replay_sent(&self) -> bool {
+        self.replay.lock().unwrap().sent
+    }
+
+    // Called by the processor before an event is processed, in the order the pipeline delivers them
+    pub fn dispatched(&self, slot: u64, signature: &str) {
+        if !self.is_backfilling() {
+            return;
+        }
+
+        let mut replay = self.replay.lock().unwrap();
+
+        if matches!(&replay.current, Some((current, _)) if current == signature) {
+            return;
+        }
+
+        if let Some((previous, previous_slot)) = replay.current.take() {
+            if replay.pending.remove(&previous) {
+                replay.last_slot = replay.last_slot.max(previous_slot);
+            }
+        }
+
+        replay.current = Some((signature.to_string(), slot));
+    }
+
+    // Called once every event dispatched up to `slot` has been processed
+    pub fn completed(&self, slot: u64) {
+        if !self.is_backfilling() {
+            return;
+        }
+
+        let mut replay = self.replay.lock().unwrap();
+
+        if replay.sent && replay.pending.is_empty() && slot >= replay.last_slot {
+            replay.processed = true;
+        }
+    }
+
+    // Waits until the processor went through every replayed transaction. Transactions that made no progress for
+    // `stall_timeout` had nothing the processor handles and are no longer waited for. False if cancelled first.
+    pub async fn wait_replayed(
+        &self,
+        stall_timeout: Duration,
+        cancellation_token: &CancellationToken,
+    ) -> bool {
+        let mut remaining = usize::MAX;
+        let mut progressed_at = Instant::now();
+
+        loop {
+            {
+                let mut replay = self.replay.lock().unwrap();
+
+                if replay.processed {
+                    return true;
+                }
+
+                if replay.pending.len() != remaining {
+                    remaining = replay.pending.len();
+                    progressed_at = Instant::now();
+                } else if progressed_at.elapsed() >= stall_timeout && remaining > 0 {
+                    tracing::warn!(
+                        transactions = remaining,
+                        "Replayed transactions never reached the processor, no longer waiting for them"
+                    );
+
+                    replay.pending.clear();
+                    replay.processed = true;
+                }
+            }
+
+            tokio::select! {
+                _ = cancellation_token.cancelled() => return false,
+                _ = tokio::time::sleep(Duration::from_millis(500)) => {}
+            }
+        }
+    }
+
+    // Records an event for the given slot. Once a higher slot shows up the previous one is considered complete.
+    pub async fn observe(&self, slot: u64, signature: String) {
+        let mut state = self.state.write().await;
+
+        match state.current.take() {
+            Some((current_slot, current_signature)) if slot > current_slot => {
+                state.completed = Some((current_slot, current_signature));
+                state.current = Some((slot, signature));
+            }
+            Some((current_slot, current_signature)) => {
+                // Same slot (or an older slot delivered by the backfill), keep the current head
+                state.current = Some((current_slot, current_signature));
+            }
+            None => state.current = Some((slot, signature)),
+        }
+    }
+
+    // Persists the highest fully-processed slot. On shutdown the current slot is flushed too since the pipeline has drained.
+    pub async fn flush(&self, db: Arc<PgPool>, include_current: bool) {
+        if self.backfilling.load(Ordering::SeqCst) {
+            tracing::info!("Backfill still running, not advancing the checkpoint");
+            return;
+        }
+
+        let checkpoint = {
+            let state = self.state.read().await;
+
+            if include_current {
+                state.current.clone().or_else(|| state.completed.clone())
+            } else {
+                state.completed.clone()
+            }
+        };
+
+        let Some((slot, signature)) = checkpoint else {
+            return;
+        };
+
+        save_checkpoint(
+            db,
+            &Checkpoint {
+                datasource: self.datasource.clone(),
+                slot: slot as i64,
+                signature,
+            },
+        )
+        .await;
+    }
+}
+
+// Bounded set of recently processed event keys, the oldest keys are forgotten first.
+#[derive(Debug, Default)]
+pub struct SeenEvents {
+    keys: HashSet<String>,
+    order: VecDeque<String>,
+}
+
+impl SeenEvents {
+    pub fn contains(&self, key: &str) -> bool {
+        self.keys.contains(key)
+    }
+
+    // Returns false if the event was already processed
+    pub fn insert(&mut self, key: String) -> bool {
+        if self.keys.contains(&key) {
+            return false;
+        }
+
+        if self.order.len() >= SEEN_EVENTS_CAPACITY {
+            if let Some(oldest) = self.order.pop_front() {
+                self.keys.remove(&oldest);
+            }
+        }
+
+        self.keys.insert(key.clone());
+        self.order.push_back(key);
+
+        true
+    }
+}
@@ -0,0 +1,664 @@
+use std::{collections::HashMap, env, str::FromStr};
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::state::{DEFAULT_MAX_TOKENS, DEFAULT_SHARDS};
+
+#[derive(Debug, Default)]
+pub struct IndexerConfig {
+    pub api_key: String,
+    pub database_url: String,
+    pub redis_url: String,
+    pub coingecko_api: String,
+    pub shutdown_timeout_secs: u64,
+    pub rpc_url: String,
+    pub max_event_lag_secs: i64,
+    pub max_sol_price_age_secs: i64,
+    pub max_backlog_depth: i64,
+    pub log_level: String,
+    pub log_format: String,
+    // Port of the REST/GraphQL server of the api role
+    pub http_port: u16,
+    // Port of the /metrics, /healthz and /readyz server of the ingest and worker roles
+    pub ops_port: u16,
+    pub ranking: RankingConfig,
+    pub sniper: SniperConfig,
+    pub funding: FundingConfig,
+    pub alerts: AlertConfig,
+    pub webhooks: WebhookConfig,
+    pub event_sink: EventSinkConfig,
+    pub event_encoding: EventEncoding,
+    pub api: ApiConfig,
+    pub cache: CacheConfig,
+    pub leader_election: LeaderElectionConfig,
+    pub datasources: DatasourceConfig,
+    pub state: StateConfig,
+    pub processor: ProcessorConfig,
+    pub trade_writer: TradeWriterConfig,
+}
+
+// A single alerting rule, ALERT_RULES holds a JSON array of these
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AlertRule {
+    // The creator sold more than `percent` of the tokens they bought
+    CreatorSell { percent: f64 },
+    // One of the `top_n` holders sold more than `percent` of their balance within `minutes` of launch
+    TopHolderDump {
+        top_n: usize,
+        minutes: i64,
+        percent: f64,
+    },
+    // Market cap fell more than `percent` from its peak within the last `window_secs`
+    MarketCapDrop { percent: f64, window_secs: i64 },
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct AlertConfig {
+    pub rules: Vec<AlertRule>,
+    pub webhook_url: Option<String>,
+    // How long a post to the alert webhook may take before it is abandoned
+    pub webhook_timeout_secs: u64,
+}
+
+fn default_alert_rules() -> Vec<AlertRule> {
+    vec![
+        AlertRule::CreatorSell { percent: 50.0 },
+        AlertRule::TopHolderDump {
+            top_n: 10,
+            minutes: 10,
+            percent: 50.0,
+        },
+        AlertRule::MarketCapDrop {
+            percent: 50.0,
+            window_secs: 300,
+        },
+    ]
+}
+
+// Wire format of the published event envelopes
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum EventEncoding {
+    #[default]
+    Json,
+    MessagePack,
+}
+
+impl FromStr for EventEncoding {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "" | "json" => Ok(Self::Json),
+            "msgpack" | "messagepack" => Ok(Self::MessagePack),
+            other => Err(format!("Unknown event encoding {}", other)),
+        }
+    }
+}
+
+impl EventEncoding {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Json => "application/json",
+            Self::MessagePack => "application/msgpack",
+        }
+    }
+}
+
+// Token bucket of a rate limit tier, API_RATE_LIMIT_TIERS holds a JSON object of these keyed by tier name
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct RateLimitTier {
+    // Burst size
+    pub capacity: f64,
+    // Sustained requests per second
+    pub refill_per_sec: f64,
+}
+
+fn default_rate_limit_tiers() -> HashMap<String, RateLimitTier> {
+    HashMap::from([
+        (
+            "anonymous".to_string(),
+            RateLimitTier {
+                capacity: 20.0,
+                refill_per_sec: 1.0,
+            },
+        ),
+        (
+            "free".to_string(),
+            RateLimitTier {
+                capacity: 60.0,
+                refill_per_sec: 5.0,
+            },
+        ),
+        (
+            "pro".to_string(),
+            RateLimitTier {
+                capacity: 600.0,
+                refill_per_sec: 50.0,
+            },
+        ),
+    ])
+}
+
+// Access control of the HTTP API
+#[derive(Debug, Default, Clone)]
+pub struct ApiConfig {
+    // When false, requests without a key are let through and rate limited per IP with the anonymous tier
+    pub auth_required: bool,
+    // Bootstrap admin key that is not stored in the database, used to create the first keys
+    pub admin_key: Option<String>,
+    pub tiers: HashMap<String, RateLimitTier>,
+    pub anonymous_tier: String,
+    // Tier given to new keys when none is requested
+    pub default_tier: String,
+    // Bucket tokens taken by one /tokens request, it scans every trade
+    pub tokens_request_cost: f64,
+    // Deepest selection and highest complexity a GraphQL query may have, rejected before it runs
+    pub graphql_max_depth: usize,
+    pub graphql_max_complexity: usize,
+    // Bucket tokens taken per unit of complexity of a GraphQL query, on top of the cost of the request
+    pub graphql_complexity_cost: f64,
+    // How long a looked up key is trusted before it is read again, bounds how long a revoked key keeps working
+    pub key_cache_ttl_secs: u64,
+    pub usage_flush_interval_secs: u64,
+    // Allowed CORS origins, any origin when it contains "*"
+    pub cors_origins: Vec<String>,
+}
+
+// Settings of the API response cache
+#[derive(Debug, Default, Clone)]
+pub struct CacheConfig {
+    pub enabled: bool,
+    // Entries of the in-process LRU
+    pub max_entries: usize,
+    // Shared cache for multiple API instances, only the in-process cache is used when unset
+    pub redis_url: Option<String>,
+    pub tokens_ttl_secs: u64,
+    pub token_ttl_secs: u64,
+    pub candles_ttl_secs: u64,
+    pub trending_ttl_secs: u64,
+    // Invalidations from the processor are batched over this window, so a busy token doesn't evict on every trade
+    pub invalidation_debounce_ms: u64,
+}
+
+// Broker the decoded events are published to
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum EventSinkKind {
+    #[default]
+    None,
+    RedisStreams,
+    Kafka,
+    Nats,
+}
+
+impl FromStr for EventSinkKind {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "" | "none" => Ok(Self::None),
+            "redis_streams" | "redis" => Ok(Self::RedisStreams),
+            "kafka" => Ok(Self::Kafka),
+            "nats" => Ok(Self::Nats),
+            other => Err(format!("Unknown event sink {}", other)),
+        }
+    }
+}
+
+// Settings of the external event sink
+#[derive(Debug, Default, Clone)]
+pub struct EventSinkConfig {
+    pub kind: EventSinkKind,
+    pub encoding: EventEncoding,
+    // Upper bound of the delay between two publish attempts of the same event
+    pub max_retry_backoff_ms: u64,
+    // Events waiting to be published, the processing waits once it is full
+    pub queue_size: usize,
+    // Defaults to REDIS_URL. The indexer flushes its own Redis on start, so point this elsewhere to keep the stream.
+    pub redis_url: String,
+    pub redis_stream: String,
+    pub redis_stream_max_len: usize,
+    pub kafka_brokers: String,
+    pub kafka_topic: String,
+    pub nats_url: String,
+    pub nats_stream: String,
+    pub nats_subject_prefix: String,
+}
+
+// How the ingest replicas agree on the one that writes
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LeaderElectionKind {
+    // A single replica, it is always the leader
+    #[default]
+    None,
+    // Session advisory lock, released as soon as the leader's connection closes
+    Postgres,
+    // Lease key renewed by the leader, taken over once it expires
+    Redis,
+}
+
+impl FromStr for LeaderElectionKind {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "" | "none" => Ok(Self::None),
+            "postgres" | "pg" => Ok(Self::Postgres),
+            "redis" => Ok(Self::Redis),
+            other => Err(format!("Unknown leader election {}", other)),
+        }
+    }
+}
+
+// Settings of the ingest leader election
+#[derive(Debug, Default, Clone)]
+pub struct LeaderElectionConfig {
+    pub kind: LeaderElectionKind,
+    // Name of the Redis lease key, hashed into the Postgres advisory lock id
+    pub key: String,
+    // A leader that can't renew within this window steps down, the lock is checked three times per lease
+    pub lease_ms: u64,
+}
+
+// Settings of the in-memory bonding curve and market cap state
+#[derive(Debug, Default, Clone)]
+pub struct StateConfig {
+    // How often the tokens changed since the last flush are written to the DB
+    pub flush_interval_secs: u64,
+    // Tokens written per UPDATE statement
+    pub flush_batch_size: usize,
+    // Tokens kept in memory, the least recently traded are evicted and reloaded from the DB on their next trade
+    pub max_tokens: usize,
+    // Tokens without a trade for this long are evicted on the next flush
+    pub idle_ttl_secs: u64,
+    // Independently locked shards of the state map, trades of tokens in different shards don't contend
+    pub shards: usize,
+}
+
+// Worker pool the decoded events are processed by, the events of a token always go to the same worker
+#[derive(Debug, Default, Clone)]
+pub struct ProcessorConfig {
+    pub workers: usize,
+    // Events queued per worker before the pipeline waits for it
+    pub queue_size: usize,
+}
+
+// Batching of the trades stored from the Redis "trade" channel
+#[derive(Debug, Default, Clone)]
+pub struct TradeWriterConfig {
+    // Bounds of the batch size, it starts at the minimum and adapts to the write latency
+    pub min_batch_size: usize,
+    pub max_batch_size: usize,
+    // Trades that don't fill a batch are written after this long
+    pub flush_interval_ms: u64,
+    // Batches grow while the writes are well under this latency and shrink above it
+    pub target_latency_ms: u64,
+    // Batches written at the same time, the consumer waits beyond that
+    pub max_in_flight: usize,
+    // Attempts of a failed write before its trades are given up on
+    pub max_write_attempts: u32,
+}
+
+// Datasources the ingest role consumes at the same time, the first delivery of each transaction is processed
+#[derive(Debug, Default, Clone)]
+pub struct DatasourceConfig {
+    // One Helius websocket per API key, defaults to API_KEY
+    pub websocket_api_keys: Vec<String>,
+    // RPC endpoints polled for new Pump.fun signatures, covers the websocket reconnects
+    pub rpc_poll_urls: Vec<String>,
+    pub rpc_poll_interval_ms: u64,
+    // Transactions a poller fetches at the same time
+    pub rpc_poll_max_concurrent_fetches: usize,
+    // How long a delivered signature is remembered to drop the copies from the other datasources
+    pub dedup_window_secs: u64,
+    // Upper bound of the remembered signatures, the oldest are forgotten first
+    pub dedup_max_signatures: usize,
+}
+
+// Splits a comma separated list, dropping the empty entries
+fn env_list(key: &str) -> Vec<String> {
+    env::var(key)
+        .unwrap_or_default()
+        .split(',')
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+        .collect()
+}
+
+// Settings of the outbound webhook delivery worker
+#[derive(Debug, Default, Clone)]
+pub struct WebhookConfig {
+    // Trades moving at least this much SOL are published as `large_trade` events
+    pub large_trade_sol: f64,
+    pub poll_interval_ms: u64,
+    pub batch_size: i64,
+    pub timeout_secs: u64,
+    // A delivery is given up after this many attempts
+    pub max_attempts: i32,
+    pub backoff_base_secs: i64,
+    pub backoff_max_secs: i64,
+    // A webhook is disabled after this many failed attempts in a row
+    pub disable_after_failures: i32,
+    // Delivered and given up deliveries are deleted after this many days
+    pub delivery_retention_days: i64,
+    // Lets webhooks point to loopback and private addresses, only for receivers inside a trusted network
+    pub allow_private_urls: bool,
+}
+
+// Settings for resolving where trader wallets got their SOL from
+#[derive(Debug, Default, Clone)]
+pub struct FundingConfig {
+    pub lookups_enabled: bool,
+    pub max_concurrent_lookups: usize,
+    // Wallets waiting for a lookup, the ones traded while the queue is full are looked up on a later trade
+    pub lookup_queue_size: usize,
+    // Wallets with more history than this many signature pages are treated as unresolvable
+    pub max_signature_pages: usize,
+    // Funders linked to more wallets than this (exchanges, faucets) don't merge clusters
+    pub max_funder_fanout: usize,
+}
+
+// Thresholds used to flag snipers and bundled buys around a token launch
+#[derive(Debug, Default, Clone)]
+pub struct SniperConfig {
+    // Buys landing within this many slots after the create transaction count as snipes
+    pub slot_window: i64,
+    // Minimum number of distinct wallets buying in the same launch slot to flag a bundle
+    pub bundle_min_wallets: usize,
+}
+
+// Weights and windows used by the trending ranking engine
+#[derive(Debug, Default, Clone)]
+pub struct RankingConfig {
+    pub interval_secs: u64,
+    pub window_secs: i64,
+    pub half_life_secs: f64,
+    pub volume_weight: f64,
+    pub buyers_weight: f64,
+    pub holder_growth_weight: f64,
+    pub curve_velocity_weight: f64,
+    pub market_cap_momentum_weight: f64,
+}
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Error: Invalid API Key")]
+    InvalidAPIKey,
+    #[error("Error: Invalid Database URL")]
+    InvalidDatabseURL,
+    #[error("Error: Invalid Redis URL")]
+    InvalidRedisURL,
+    #[error("Error: Invalid Coingecko API")]
+    InvalidCoingeckoAPI,
+}
+
+// Default number of seconds to wait for buffers to drain on shutdown
+const DEFAULT_SHUTDOWN_TIMEOUT_SECS: u64 = 30;
+
+// Default readiness thresholds, Pump.fun emits events every few seconds so a minute of silence means we are stalled
+const DEFAULT_MAX_EVENT_LAG_SECS: i64 = 60;
+const DEFAULT_MAX_SOL_PRICE_AGE_SECS: i64 = 120;
+const DEFAULT_MAX_BACKLOG_DEPTH: i64 = 2_000;
+
+const DEFAULT_HTTP_PORT: u16 = 8000;
+const DEFAULT_OPS_PORT: u16 = 9100;
+
+// Reads a numeric environment variable, falling back to the default when it is missing or invalid
+fn env_or<T: FromStr>(key: &str, default: T) -> T {
+    env::var(key)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+// Environment variable configuration for the indexer
+impl IndexerConfig {
+    pub fn get_config() -> Self {
+        let api_key =
+            env::var("API_KEY").unwrap_or_else(|_| ConfigError::InvalidAPIKey.to_string());
+
+        let database_url =
+            env::var("DATABASE_URL").unwrap_or_else(|_| ConfigError::InvalidDatabseURL.to_string());
+
+        let redis_url =
+            env::var("REDIS_URL").unwrap_or_else(|_| ConfigError::InvalidRedisURL.to_string());
+
+        let coingecko_api = env::var("COINGECKO_API")
+            .unwrap_or_else(|_| ConfigError::InvalidCoingeckoAPI.to_string());
+
+        let shutdown_timeout_secs = env_or("SHUTDOWN_TIMEOUT_SECS", DEFAULT_SHUTDOWN_TIMEOUT_SECS);
+
+        // Falls back to the Helius RPC endpoint for the same API key, used for the catch-up backfill
+        let rpc_url = env::var("RPC_URL").unwrap_or_else(|_| {
+            format!("https://mainnet.helius-rpc.com/?api-key={}", api_key)
+        });
+
+        let max_event_lag_secs = env_or("MAX_EVENT_LAG_SECS", DEFAULT_MAX_EVENT_LAG_SECS);
+
+        let max_sol_price_age_secs =
+            env_or("MAX_SOL_PRICE_AGE_SECS", DEFAULT_MAX_SOL_PRICE_AGE_SECS);
+
+        let max_backlog_depth = env_or("MAX_BACKLOG_DEPTH", DEFAULT_MAX_BACKLOG_DEPTH);
+
+        let log_level = env::var("LOG_LEVEL").unwrap_or_else(|_| "info".to_string());
+
+        let log_format = env::var("LOG_FORMAT").unwrap_or_else(|_| "pretty".to_string());
+
+        let http_port = env_or("HTTP_PORT", DEFAULT_HTTP_PORT);
+
+        let ops_port = env_or("OPS_PORT", DEFAULT_OPS_PORT);
+
+        let ranking = RankingConfig {
+            interval_secs: env_or("RANK_INTERVAL_SECS", 30),
+            window_secs: env_or("RANK_WINDOW_SECS", 3600),
+            half_life_secs: env_or("RANK_HALF_LIFE_SECS", 900.0),
+            volume_weight: env_or("RANK_VOLUME_WEIGHT", 0.35),
+            buyers_weight: env_or("RANK_BUYERS_WEIGHT", 0.25),
+            holder_growth_weight: env_or("RANK_HOLDER_GROWTH_WEIGHT", 0.15),
+            curve_velocity_weight: env_or("RANK_CURVE_VELOCITY_WEIGHT", 0.15),
+            market_cap_momentum_weight: env_or("RANK_MARKET_CAP_MOMENTUM_WEIGHT", 0.10),
+        };
+
+        let sniper = SniperConfig {
+            slot_window: env_or("SNIPER_SLOT_WINDOW", 2),
+            bundle_min_wallets: env_or("BUNDLE_MIN_WALLETS", 3),
+        };
+
+        let funding = FundingConfig {
+            lookups_enabled: env_or("FUNDING_LOOKUPS_ENABLED", false),
+            max_concurrent_lookups: env_or("FUNDING_MAX_CONCURRENT_LOOKUPS", 4),
+            lookup_queue_size: env_or("FUNDING_LOOKUP_QUEUE_SIZE", 1000),
+            max_signature_pages: env_or("FUNDING_MAX_SIGNATURE_PAGES", 5),
+            max_funder_fanout: env_or("FUNDING_MAX_FUNDER_FANOUT", 50),
+        };
+
+        let alert_rules = match env::var("ALERT_RULES") {
+            Ok(rules) => serde_json::from_str(&rules).unwrap_or_else(|err| {
+                eprintln!("Invalid ALERT_RULES: {}, using the default rules", err);
+                default_alert_rules()
+            }),
+            Err(_) => default_alert_rules(),
+        };
+
+        let alerts = AlertConfig {
+            rules: alert_rules,
+            webhook_url: env::var("ALERT_WEBHOOK_URL").ok(),
+            webhook_timeout_secs: env_or("ALERT_WEBHOOK_TIMEOUT_SECS", 5),
+        };
+
+        let webhooks = WebhookConfig {
+            large_trade_sol: env_or("WEBHOOK_LARGE_TRADE_SOL", 10.0),
+            poll_interval_ms: env_or("WEBHOOK_POLL_INTERVAL_MS", 1000),
+            batch_size: env_or("WEBHOOK_BATCH_SIZE", 50),
+            timeout_secs: env_or("WEBHOOK_TIMEOUT_SECS", 10),
+            max_attempts: env_or("WEBHOOK_MAX_ATTEMPTS", 8),
+            backoff_base_secs: env_or("WEBHOOK_BACKOFF_BASE_SECS", 5),
+            backoff_max_secs: env_or("WEBHOOK_BACKOFF_MAX_SECS", 3600),
+            disable_after_failures: env_or("WEBHOOK_DISABLE_AFTER_FAILURES", 20),
+            delivery_retention_days: env_or("WEBHOOK_DELIVERY_RETENTION_DAYS", 7),
+            allow_private_urls: env_or("WEBHOOK_ALLOW_PRIVATE_URLS", false),
+        };
+
+        let event_encoding = env::var("EVENT_ENCODING")
+            .ok()
+            .map(|encoding| {
+                encoding.parse().unwrap_or_else(|err| {
+                    eprintln!("Invalid EVENT_ENCODING: {}, using JSON", err);
+                    EventEncoding::Json
+                })
+            })
+            .unwrap_or_default();
+
+        let event_sink = EventSinkConfig {
+            encoding: event_encoding,
+            kind: env::var("EVENT_SINK")
+                .ok()
+                .map(|kind| {
+                    kind.parse().unwrap_or_else(|err| {
+                        eprintln!("Invalid EVENT_SINK: {}, publishing is disabled", err);
+                        EventSinkKind::None
+                    })
+                })
+                .unwrap_or_default(),
+            max_retry_backoff_ms: env_or("EVENT_SINK_MAX_RETRY_BACKOFF_MS", 30_000),
+            queue_size: env_or("EVENT_SINK_QUEUE_SIZE", 10_000),
+            redis_url: env::var("EVENT_SINK_REDIS_URL").unwrap_or_else(|_| redis_url.clone()),
+            redis_stream: env::var("EVENT_SINK_REDIS_STREAM")
+                .unwrap_or_else(|_| "pumpfun:events".to_string()),
+            redis_stream_max_len: env_or("EVENT_SINK_REDIS_STREAM_MAX_LEN", 1_000_000),
+            kafka_brokers: env::var("EVENT_SINK_KAFKA_BROKERS")
+                .unwrap_or_else(|_| "localhost:9092".to_string()),
+            kafka_topic: env::var("EVENT_SINK_KAFKA_TOPIC")
+                .unwrap_or_else(|_| "pumpfun.events".to_string()),
+            nats_url: env::var("EVENT_SINK_NATS_URL")
+                .unwrap_or_else(|_| "nats://localhost:4222".to_string()),
+            nats_stream: env::var("EVENT_SINK_NATS_STREAM")
+                .unwrap_or_else(|_| "PUMPFUN".to_string()),
+            nats_subject_prefix: env::var("EVENT_SINK_NATS_SUBJECT_PREFIX")
+                .unwrap_or_else(|_| "pumpfun".to_string()),
+        };
+
+        let rate_limit_tiers = match env::var("API_RATE_LIMIT_TIERS") {
+            Ok(tiers) => serde_json::from_str(&tiers).unwrap_or_else(|err| {
+                eprintln!("Invalid API_RATE_LIMIT_TIERS: {}, using the default tiers", err);
+                default_rate_limit_tiers()
+            }),
+            Err(_) => default_rate_limit_tiers(),
+        };
+
+        let api = ApiConfig {
+            auth_required: env_or("API_AUTH_REQUIRED", true),
+            admin_key: env::var("API_ADMIN_KEY").ok().filter(|key| !key.is_empty()),
+            tiers: rate_limit_tiers,
+            anonymous_tier: env::var("API_ANONYMOUS_TIER")
+                .unwrap_or_else(|_| "anonymous".to_string()),
+            default_tier: env::var("API_DEFAULT_TIER").unwrap_or_else(|_| "free".to_string()),
+            tokens_request_cost: env_or("API_TOKENS_REQUEST_COST", 10.0),
+            graphql_max_depth: env_or("API_GRAPHQL_MAX_DEPTH", 8),
+            graphql_max_complexity: env_or("API_GRAPHQL_MAX_COMPLEXITY", 500),
+            graphql_complexity_cost: env_or("API_GRAPHQL_COMPLEXITY_COST", 0.1),
+            key_cache_ttl_secs: env_or("API_KEY_CACHE_TTL_SECS", 30),
+            usage_flush_interval_secs: env_or("API_USAGE_FLUSH_INTERVAL_SECS", 10),
+            cors_origins: env::var("API_CORS_ORIGINS")
+                .unwrap_or_else(|_| "*".to_string())
+                .split(',')
+                .map(|origin| origin.trim().to_string())
+                .filter(|origin| !origin.is_empty())
+                .collect(),
+        };
+
+        let cache = CacheConfig {
+            enabled: env_or("CACHE_ENABLED", true),
+            max_entries: env_or("CACHE_MAX_ENTRIES", 1000),
+            redis_url: env::var("CACHE_REDIS_URL").ok().filter(|url| !url.is_empty()),
+            tokens_ttl_secs: env_or("CACHE_TOKENS_TTL_SECS", 30),
+            token_ttl_secs: env_or("CACHE_TOKEN_TTL_SECS", 10),
+            candles_ttl_secs: env_or("CACHE_CANDLES_TTL_SECS", 10),
+            trending_ttl_secs: env_or("CACHE_TRENDING_TTL_SECS", 30),
+            invalidation_debounce_ms: env_or("CACHE_INVALIDATION_DEBOUNCE_MS", 1000),
+        };
+
+        let leader_election = LeaderElectionConfig {
+            kind: env::var("LEADER_ELECTION")
+                .ok()
+                .map(|kind| {
+                    kind.parse().unwrap_or_else(|err| {
+                        eprintln!("Invalid LEADER_ELECTION: {}, the election is disabled", err);
+                        LeaderElectionKind::None
+                    })
+                })
+                .unwrap_or_default(),
+            key: env::var("LEADER_ELECTION_KEY")
+                .unwrap_or_else(|_| "pumpfun-indexer:ingest-leader".to_string()),
+            lease_ms: env_or("LEADER_LEASE_MS", 5000),
+        };
+
+        let mut websocket_api_keys = env_list("HELIUS_WS_API_KEYS");
+
+        if websocket_api_keys.is_empty() {
+            websocket_api_keys.push(api_key.clone());
+        }
+
+        let datasources = DatasourceConfig {
+            websocket_api_keys,
+            rpc_poll_urls: env_list("RPC_POLL_URLS"),
+            rpc_poll_interval_ms: env_or("RPC_POLL_INTERVAL_MS", 1000),
+            rpc_poll_max_concurrent_fetches: env_or("RPC_POLL_MAX_CONCURRENT_FETCHES", 8),
+            dedup_window_secs: env_or("DEDUP_WINDOW_SECS", 120),
+            dedup_max_signatures: env_or("DEDUP_MAX_SIGNATURES", 500_000),
+        };
+
+        let state = StateConfig {
+            flush_interval_secs: env_or("MARKET_CAP_FLUSH_INTERVAL_SECS", 10),
+            flush_batch_size: env_or("MARKET_CAP_FLUSH_BATCH_SIZE", 5000),
+            max_tokens: env_or("STATE_MAX_TOKENS", DEFAULT_MAX_TOKENS),
+            idle_ttl_secs: env_or("STATE_IDLE_TTL_SECS", 3600),
+            shards: env_or("STATE_SHARDS", DEFAULT_SHARDS),
+        };
+
+        let processor = ProcessorConfig {
+            workers: env_or("PROCESSOR_WORKERS", 4),
+            queue_size: env_or("PROCESSOR_QUEUE_SIZE", 1024),
+        };
+
+        let trade_writer = TradeWriterConfig {
+            min_batch_size: env_or("TRADE_BATCH_MIN_SIZE", 100),
+            max_batch_size: env_or("TRADE_BATCH_MAX_SIZE", 10_000),
+            flush_interval_ms: env_or("TRADE_FLUSH_INTERVAL_MS", 1000),
+            target_latency_ms: env_or("TRADE_WRITE_TARGET_MS", 250),
+            max_in_flight: env_or("TRADE_MAX_IN_FLIGHT_WRITES", 4),
+            max_write_attempts: env_or("TRADE_WRITE_MAX_ATTEMPTS", 10),
+        };
+
+        Self {
+            api_key,
+            database_url,
+            redis_url,
+            coingecko_api,
+            shutdown_timeout_secs,
+            rpc_url,
+            max_event_lag_secs,
+            max_sol_price_age_secs,
+            max_backlog_depth,
+            log_level,
+            log_format,
+            http_port,
+            ops_port,
+            ranking,
+            sniper,
+            funding,
+            alerts,
+            webhooks,
+            event_sink,
+            event_encoding,
+            api,
+            cache,
+            leader_election,
+            datasources,
+            state,
+            processor,
+            trade_writer,
+        }
+    }
+}
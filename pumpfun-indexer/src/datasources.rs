@@ -0,0 +1,311 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use carbon_core::{
+    datasource::{Datasource, Update, UpdateType},
+    error::CarbonResult,
+    metrics::MetricsCollection,
+};
+use futures::future::join_all;
+use solana_sdk::signature::Signature;
+use sqlx::types::chrono::Utc;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    config::DatasourceConfig, helius_websocket::get_helius_websocket, metrics::metrics,
+    rpc_poller::RpcPoller,
+};
+
+// Delay before a datasource that stopped on its own is started again
+const RESTART_DELAY: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Default)]
+struct WindowState {
+    first_seen: HashMap<Signature, Instant>,
+    order: VecDeque<(Instant, Signature)>,
+}
+
+// Signatures delivered within the last window, bounded in size. Shared by the datasources of a RedundantDatasource.
+#[derive(Debug, Clone)]
+pub struct SignatureWindow {
+    state: Arc<Mutex<WindowState>>,
+    window: Duration,
+    capacity: usize,
+}
+
+impl SignatureWindow {
+    pub fn new(window: Duration, capacity: usize) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(WindowState::default())),
+            window,
+            capacity,
+        }
+    }
+
+    // Records the arrival of a signature. Returns how long after its first arrival it came, None if this is the first.
+    pub fn arrive(&self, signature: Signature) -> Option<Duration> {
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap();
+
+        while let Some((at, oldest)) = state.order.front().copied() {
+            if now.duration_since(at) <= self.window && state.order.len() < self.capacity {
+                break;
+            }
+
+            state.order.pop_front();
+            state.first_seen.remove(&oldest);
+        }
+
+        if let Some(first) = state.first_seen.get(&signature) {
+            return Some(now.duration_since(*first));
+        }
+
+        state.first_seen.insert(signature, now);
+        state.order.push_back((now, signature));
+
+        None
+    }
+
+    // How long a signature is remembered
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    pub fn contains(&self, signature: &Signature) -> bool {
+        self.state
+            .lock()
+            .unwrap()
+            .first_seen
+            .contains_key(signature)
+    }
+}
+
+// Consumes several datasources at the same time and forwards the first delivery of every transaction, so a lagging
+// or reconnecting provider doesn't lose events. Records which source delivered first and how far behind the others were.
+pub struct RedundantDatasource {
+    sources: Vec<(String, Box<dyn Datasource>)>,
+    window: SignatureWindow,
+}
+
+impl RedundantDatasource {
+    pub fn new(window: SignatureWindow) -> Self {
+        Self {
+            sources: Vec::new(),
+            window,
+        }
+    }
+
+    // Helius websockets for every configured API key plus the RPC pollers
+    pub fn from_config(config: &DatasourceConfig) -> Self {
+        let mut datasource = Self::new(SignatureWindow::new(
+            Duration::from_secs(config.dedup_window_secs),
+            config.dedup_max_signatures,
+        ));
+
+        for (index, api_key) in config.websocket_api_keys.iter().enumerate() {
+            datasource = datasource.source(
+                format!("helius_ws_{}", index),
+                get_helius_websocket(api_key.clone()),
+            );
+        }
+
+        for (index, rpc_url) in config.rpc_poll_urls.iter().enumerate() {
+            let poller = RpcPoller {
+                rpc_url: rpc_url.clone(),
+                interval: Duration::from_millis(config.rpc_poll_interval_ms),
+                max_concurrent_fetches: config.rpc_poll_max_concurrent_fetches.max(1),
+                seen: datasource.window.clone(),
+            };
+
+            datasource = datasource.source(format!("rpc_poll_{}", index), poller);
+        }
+
+        datasource
+    }
+
+    // Adds a datasource, `name` is the `source` label of its metrics
+    pub fn source<D: Datasource + 'static>(mut self, name: String, source: D) -> Self {
+        self.sources.push((name, Box::new(source)));
+        self
+    }
+
+    fn forward(&self, source: &str, update: Update, sender: &UnboundedSender<Update>) -> bool {
+        metrics()
+            .datasource_updates
+            .with_label_values(&[source])
+            .inc();
+
+        // Only transactions carry a signature, anything else is forwarded as is
+        let Update::Transaction(transaction) = &update else {
+            return sender.send(update).is_ok();
+        };
+
+        if let Some(block_time) = transaction.block_time {
+            let latency = (Utc::now().timestamp_millis() - block_time * 1000).max(0);
+
+            metrics()
+                .datasource_block_latency
+                .with_label_values(&[source])
+                .observe(latency as f64 / 1000.0);
+        }
+
+        match self.window.arrive(transaction.signature) {
+            Some(lag) => {
+                metrics()
+                    .datasource_arrival_lag
+                    .with_label_values(&[source])
+                    .observe(lag.as_secs_f64());
+
+                true
+            }
+            None => {
+                metrics()
+                    .datasource_first_arrivals
+                    .with_label_values(&[source])
+                    .inc();
+                metrics()
+                    .datasource_arrival_lag
+                    .with_label_values(&[source])
+                    .observe(0.0);
+
+                sender.send(update).is_ok()
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Datasource for RedundantDatasource {
+    async fn consume(
+        &self,
+        sender: &UnboundedSender<Update>,
+        cancellation_token: CancellationToken,
+        metrics: Arc<MetricsCollection>,
+    ) -> CarbonResult<()> {
+        let (merged_tx, mut merged_rx) = unbounded_channel::<(usize, Update)>();
+
+        // Stops the datasources when the pipeline goes away as well
+        let stop = cancellation_token.child_token();
+
+        let consumers = self
+            .sources
+            .iter()
+            .enumerate()
+            .map(|(index, (name, source))| {
+                let (source_tx, mut source_rx) = unbounded_channel();
+                let merged_tx = merged_tx.clone();
+                let stop = stop.clone();
+                let metrics = metrics.clone();
+
+                //Spawn a new thread that tags the updates of this datasource before they are merged
+                tokio::spawn(async move {
+                    while let Some(update) = source_rx.recv().await {
+                        if merged_tx.send((index, update)).is_err() {
+                            break;
+                        }
+                    }
+                });
+
+                async move {
+                    loop {
+                        if let Err(err) = source
+                            .consume(&source_tx, stop.clone(), metrics.clone())
+                            .await
+                        {
+                            tracing::error!(source = %name, "Datasource failed: {:?}", err);
+                        }
+
+                        // The other datasources keep delivering while this one is restarted
+                        tokio::select! {
+                            _ = stop.cancelled() => break,
+                            _ = tokio::time::sleep(RESTART_DELAY) => {}
+                        }
+
+                        tracing::warn!(source = %name, "Restarting datasource");
+                        crate::metrics::metrics()
+                            .datasource_restarts
+                            .with_label_values(&[name.as_str()])
+                            .inc();
+                    }
+                }
+            });
+
+        let consumers = join_all(consumers.collect::<Vec<_>>());
+
+        // Only the forwarding tasks hold the merged sender now, the channel closes once every datasource has stopped
+        drop(merged_tx);
+
+        let merge = async {
+            while let Some((index, update)) = merged_rx.recv().await {
+                if !self.forward(&self.sources[index].0, update, sender) {
+                    tracing::error!("Pipeline closed, stopping the datasources");
+                    stop.cancel();
+                    break;
+                }
+            }
+        };
+
+        tokio::join!(consumers, merge);
+
+        Ok(())
+    }
+
+    fn update_types(&self) -> Vec<UpdateType> {
+        vec![UpdateType::Transaction]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signature(byte: u8) -> Signature {
+        Signature::from([byte; 64])
+    }
+
+    #[test]
+    fn reports_the_lag_of_the_copies() {
+        let window = SignatureWindow::new(Duration::from_secs(60), 100);
+
+        assert_eq!(window.arrive(signature(1)), None);
+        assert!(window.contains(&signature(1)));
+        assert!(!window.contains(&signature(2)));
+
+        std::thread::sleep(Duration::from_millis(20));
+
+        let lag = window.arrive(signature(1)).unwrap();
+        assert!(lag >= Duration::from_millis(20));
+    }
+
+    #[test]
+    fn forgets_the_oldest_signatures_beyond_the_capacity() {
+        let window = SignatureWindow::new(Duration::from_secs(60), 2);
+
+        window.arrive(signature(1));
+        window.arrive(signature(2));
+        window.arrive(signature(3));
+
+        assert!(!window.contains(&signature(1)));
+        assert!(window.contains(&signature(2)));
+        assert!(window.contains(&signature(3)));
+    }
+
+    #[test]
+    fn forgets_the_signatures_older_than_the_window() {
+        let window = SignatureWindow::new(Duration::from_millis(10), 100);
+
+        window.arrive(signature(1));
+
+        std::thread::sleep(Duration::from_millis(30));
+
+        // Pruned on the next arrival, a late copy counts as a first delivery again
+        assert_eq!(window.arrive(signature(2)), None);
+        assert!(!window.contains(&signature(1)));
+        assert_eq!(window.arrive(signature(1)), None);
+    }
+}
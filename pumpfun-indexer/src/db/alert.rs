@@ -0,0 +1,60 @@
+use std::sync::Arc;
+
+use sqlx::PgPool;
+
+use crate::{metrics::metrics, types::Alert};
+
+// Persists a fired alert
+#[tracing::instrument(skip_all, fields(rule = %alert.rule, mint = %alert.contract_address))]
+pub async fn save_alert(db: Arc<PgPool>, alert: &Alert) {
+    let insert_sql = r#"
+    INSERT INTO alert(id, rule, contract_address, wallet, message, value, threshold, signature, slot, created_at)
+    VALUES($1::uuid, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+    "#;
+
+    if let Err(err) = sqlx::query(insert_sql)
+        .bind(&alert.id)
+        .bind(&alert.rule)
+        .bind(&alert.contract_address)
+        .bind(&alert.wallet)
+        .bind(&alert.message)
+        .bind(alert.value)
+        .bind(alert.threshold)
+        .bind(&alert.signature)
+        .bind(alert.slot)
+        .bind(alert.created_at)
+        .execute(&*db)
+        .await
+    {
+        metrics().db_errors.with_label_values(&["save_alert"]).inc();
+        tracing::error!(error = ?err, "Failed to save alert");
+    }
+}
+
+// Fetches the most recent alerts, optionally only for one mint
+pub async fn fetch_recent_alerts(
+    db: &PgPool,
+    mint: Option<&str>,
+    limit: i64,
+) -> Result<Vec<Alert>, anyhow::Error> {
+    let query = r#"
+    SELECT id::text AS id, rule, contract_address, wallet, message, value, threshold, signature, slot, created_at
+    FROM alert
+    WHERE $1::text IS NULL OR contract_address = $1
+    ORDER BY created_at DESC
+    LIMIT $2
+    "#;
+
+    match sqlx::query_as::<_, Alert>(query)
+        .bind(mint)
+        .bind(limit)
+        .fetch_all(db)
+        .await
+    {
+        Ok(r) => Ok(r),
+        Err(e) => {
+            tracing::error!("{}", e);
+            Err(anyhow::Error::msg("Error: Fail to fetch alerts"))
+        }
+    }
+}
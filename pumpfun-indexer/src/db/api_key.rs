@@ -0,0 +1,195 @@
+use std::sync::Arc;
+
+use sqlx::{types::chrono::Utc, PgPool};
+use uuid::Uuid;
+
+use crate::{
+    metrics::metrics,
+    types::{ApiKey, ApiKeyUsage},
+};
+
+const API_KEY_COLUMNS: &str =
+    r#"id::text AS id, name, key_prefix, tier, is_admin, created_at, last_used_at, revoked_at"#;
+
+// Stores a new API key, only its hash is kept
+pub async fn create_api_key(
+    db: &PgPool,
+    name: &str,
+    key_prefix: &str,
+    key_hash: &str,
+    tier: &str,
+    is_admin: bool,
+) -> Result<ApiKey, anyhow::Error> {
+    let insert_sql = format!(
+        r#"
+    INSERT INTO api_key(id, name, key_prefix, key_hash, tier, is_admin, created_at)
+    VALUES($1, $2, $3, $4, $5, $6, $7)
+    RETURNING {}
+    "#,
+        API_KEY_COLUMNS
+    );
+
+    match sqlx::query_as::<_, ApiKey>(&insert_sql)
+        .bind(uuid::Uuid::new_v4())
+        .bind(name)
+        .bind(key_prefix)
+        .bind(key_hash)
+        .bind(tier)
+        .bind(is_admin)
+        .bind(Utc::now())
+        .fetch_one(db)
+        .await
+    {
+        Ok(r) => Ok(r),
+        Err(e) => {
+            tracing::error!("{}", e);
+            Err(anyhow::Error::msg("Error: Fail to create API key"))
+        }
+    }
+}
+
+// Looks up a non revoked key by the hash of its secret
+pub async fn fetch_api_key_by_hash(
+    db: &PgPool,
+    key_hash: &str,
+) -> Result<Option<ApiKey>, anyhow::Error> {
+    let query = format!(
+        r#"SELECT {} FROM api_key WHERE key_hash = $1 AND revoked_at IS NULL"#,
+        API_KEY_COLUMNS
+    );
+
+    match sqlx::query_as::<_, ApiKey>(&query)
+        .bind(key_hash)
+        .fetch_optional(db)
+        .await
+    {
+        Ok(r) => Ok(r),
+        Err(e) => {
+            tracing::error!("{}", e);
+            Err(anyhow::Error::msg("Error: Fail to fetch API key"))
+        }
+    }
+}
+
+// Fetches every API key, revoked ones included
+pub async fn fetch_api_keys(db: &PgPool) -> Result<Vec<ApiKey>, anyhow::Error> {
+    let query = format!(
+        r#"SELECT {} FROM api_key ORDER BY created_at DESC"#,
+        API_KEY_COLUMNS
+    );
+
+    match sqlx::query_as::<_, ApiKey>(&query).fetch_all(db).await {
+        Ok(r) => Ok(r),
+        Err(e) => {
+            tracing::error!("{}", e);
+            Err(anyhow::Error::msg("Error: Fail to fetch API keys"))
+        }
+    }
+}
+
+// Revokes a key, returns None when it doesn't exist
+pub async fn revoke_api_key(db: &PgPool, id: Uuid) -> Result<Option<ApiKey>, anyhow::Error> {
+    let update_sql = format!(
+        r#"
+    UPDATE api_key
+    SET revoked_at = COALESCE(revoked_at, $2)
+    WHERE id = $1
+    RETURNING {}
+    "#,
+        API_KEY_COLUMNS
+    );
+
+    match sqlx::query_as::<_, ApiKey>(&update_sql)
+        .bind(id)
+        .bind(Utc::now())
+        .fetch_optional(db)
+        .await
+    {
+        Ok(r) => Ok(r),
+        Err(e) => {
+            tracing::error!("{}", e);
+            Err(anyhow::Error::msg("Error: Fail to revoke API key"))
+        }
+    }
+}
+
+// Daily usage of a key over the last `days` days, newest first
+pub async fn fetch_api_key_usage(
+    db: &PgPool,
+    id: Uuid,
+    days: i64,
+) -> Result<Vec<ApiKeyUsage>, anyhow::Error> {
+    let query = r#"
+    SELECT api_key_id::text AS api_key_id, day, requests, rate_limited
+    FROM api_key_usage
+    WHERE api_key_id = $1 AND day > CURRENT_DATE - $2::int
+    ORDER BY day DESC
+    "#;
+
+    match sqlx::query_as::<_, ApiKeyUsage>(query)
+        .bind(id)
+        .bind(days as i32)
+        .fetch_all(db)
+        .await
+    {
+        Ok(r) => Ok(r),
+        Err(e) => {
+            tracing::error!("{}", e);
+            Err(anyhow::Error::msg("Error: Fail to fetch API key usage"))
+        }
+    }
+}
+
+// Adds the buffered request counts of each key (id, requests, rate limited) to today's usage
+#[tracing::instrument(skip_all, fields(keys = usage.len()))]
+pub async fn save_api_key_usage(db: Arc<PgPool>, usage: Vec<(String, i64, i64)>) {
+    let now = Utc::now();
+
+    let mut ids = Vec::with_capacity(usage.len());
+    let mut requests = Vec::with_capacity(usage.len());
+    let mut rate_limited = Vec::with_capacity(usage.len());
+
+    for (id, served, limited) in usage {
+        ids.push(id);
+        requests.push(served);
+        rate_limited.push(limited);
+    }
+
+    let result = async {
+        let mut tx = db.begin().await?;
+
+        sqlx::query(
+            r#"
+        INSERT INTO api_key_usage(api_key_id, day, requests, rate_limited)
+        SELECT u.id::uuid, $4::date, u.requests, u.rate_limited
+        FROM UNNEST($1::text[], $2::bigint[], $3::bigint[]) AS u(id, requests, rate_limited)
+        ON CONFLICT (api_key_id, day) DO UPDATE
+        SET requests = api_key_usage.requests + EXCLUDED.requests,
+            rate_limited = api_key_usage.rate_limited + EXCLUDED.rate_limited
+        "#,
+        )
+        .bind(&ids)
+        .bind(&requests)
+        .bind(&rate_limited)
+        .bind(now.date_naive())
+        .execute(&mut tx)
+        .await?;
+
+        sqlx::query(r#"UPDATE api_key SET last_used_at = $2 WHERE id = ANY($1::uuid[])"#)
+            .bind(&ids)
+            .bind(now)
+            .execute(&mut tx)
+            .await?;
+
+        tx.commit().await
+    }
+    .await;
+
+    if let Err(err) = result {
+        metrics()
+            .db_errors
+            .with_label_values(&["save_api_key_usage"])
+            .inc();
+        tracing::error!(error = ?err, "Failed to save API key usage");
+    }
+}
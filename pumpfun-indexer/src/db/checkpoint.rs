@@ -0,0 +1,48 @@
+use std::sync::Arc;
+
+use sqlx::{types::chrono::Utc, PgPool};
+
+use crate::types::Checkpoint;
+
+// Fetches the last persisted checkpoint for the given datasource, if the indexer has ever recorded one.
+pub async fn get_checkpoint(
+    db: Arc<PgPool>,
+    datasource: &str,
+) -> Result<Option<Checkpoint>, anyhow::Error> {
+    let query = r#"SELECT datasource, slot, signature FROM indexer_checkpoint WHERE datasource = $1"#;
+
+    match sqlx::query_as::<_, Checkpoint>(query)
+        .bind(datasource)
+        .fetch_optional(&*db)
+        .await
+    {
+        Ok(r) => Ok(r),
+        Err(e) => {
+            tracing::error!("{}", e);
+            Err(anyhow::Error::msg("Error: Fail to fetch indexer checkpoint"))
+        }
+    }
+}
+
+// Upserts the checkpoint for a datasource. The slot only ever moves forward so a late write can't rewind it.
+#[tracing::instrument(skip_all, fields(datasource = %checkpoint.datasource, slot = checkpoint.slot))]
+pub async fn save_checkpoint(db: Arc<PgPool>, checkpoint: &Checkpoint) {
+    let upsert_sql = r#"
+    INSERT INTO indexer_checkpoint(datasource, slot, signature, updated_at)
+    VALUES($1, $2, $3, $4)
+    ON CONFLICT (datasource) DO UPDATE
+    SET slot = EXCLUDED.slot, signature = EXCLUDED.signature, updated_at = EXCLUDED.updated_at
+    WHERE indexer_checkpoint.slot < EXCLUDED.slot
+    "#;
+
+    if let Err(err) = sqlx::query(upsert_sql)
+        .bind(&checkpoint.datasource)
+        .bind(checkpoint.slot)
+        .bind(&checkpoint.signature)
+        .bind(Utc::now())
+        .execute(&*db)
+        .await
+    {
+        tracing::error!(error = ?err, "Failed to save the checkpoint");
+    }
+}
@@ -0,0 +1,87 @@
+use std::sync::Arc;
+
+use sqlx::{types::chrono::Utc, PgPool};
+
+use crate::types::WalletFunding;
+
+// Records the funding source of a wallet. The first observed funder wins, later observations are ignored.
+#[tracing::instrument(skip_all, fields(%wallet))]
+pub async fn save_wallet_funding(
+    db: Arc<PgPool>,
+    wallet: String,
+    funder: Option<String>,
+    signature: Option<String>,
+    slot: Option<i64>,
+) {
+    let insert_sql = r#"
+    INSERT INTO wallet_funding(wallet, funder, funding_signature, funding_slot, resolved_at)
+    VALUES($1, $2, $3, $4, $5)
+    ON CONFLICT (wallet) DO UPDATE
+    SET funder = EXCLUDED.funder, funding_signature = EXCLUDED.funding_signature, funding_slot = EXCLUDED.funding_slot, resolved_at = EXCLUDED.resolved_at
+    WHERE wallet_funding.funder IS NULL AND EXCLUDED.funder IS NOT NULL
+    "#;
+
+    if let Err(err) = sqlx::query(insert_sql)
+        .bind(wallet)
+        .bind(funder)
+        .bind(signature)
+        .bind(slot)
+        .bind(Utc::now())
+        .execute(&*db)
+        .await
+    {
+        tracing::error!(error = ?err, "Failed to save wallet funding");
+    }
+}
+
+// Checks whether the funding of a wallet was already resolved, so the RPC lookup can be skipped
+pub async fn wallet_funding_exists(db: Arc<PgPool>, wallet: &str) -> bool {
+    match sqlx::query_scalar::<_, bool>(
+        r#"SELECT EXISTS(SELECT 1 FROM wallet_funding WHERE wallet = $1)"#,
+    )
+    .bind(wallet)
+    .fetch_one(&*db)
+    .await
+    {
+        Ok(exists) => exists,
+        Err(err) => {
+            tracing::error!(error = ?err, "Failed to check wallet funding");
+            false
+        }
+    }
+}
+
+// Fetches the funding chains of the wallets, each wallet to its funder and up from there. A wallet has a single
+// funder, so the wallets are clustered the same as over the whole graph. The edges to funders of more than
+// `max_funder_fanout` wallets are left out here, `build_clusters` only sees the part of their fanout in the chains.
+pub async fn fetch_wallet_funding(
+    db: &PgPool,
+    wallets: &[String],
+    max_funder_fanout: usize,
+) -> Result<Vec<WalletFunding>, anyhow::Error> {
+    let query = r#"
+    WITH RECURSIVE chain AS (
+        SELECT wallet, funder FROM wallet_funding
+        WHERE wallet = ANY($1) AND funder IS NOT NULL
+        UNION
+        SELECT f.wallet, f.funder FROM wallet_funding f
+        JOIN chain c ON f.wallet = c.funder
+        WHERE f.funder IS NOT NULL
+    )
+    SELECT wallet, funder FROM chain c
+    WHERE (SELECT COUNT(*) FROM wallet_funding f WHERE f.funder = c.funder) <= $2
+    "#;
+
+    match sqlx::query_as::<_, WalletFunding>(query)
+        .bind(wallets)
+        .bind(max_funder_fanout as i64)
+        .fetch_all(db)
+        .await
+    {
+        Ok(r) => Ok(r),
+        Err(e) => {
+            tracing::error!("{}", e);
+            Err(anyhow::Error::msg("Error: Fail to fetch wallet funding"))
+        }
+    }
+}
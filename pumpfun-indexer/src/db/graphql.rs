@@ -0,0 +1,323 @@
+use sqlx::{
+    prelude::FromRow,
+    types::chrono::{DateTime, Utc},
+    PgPool,
+};
+
+use crate::graphql::objects::{CandleObject, HoldingObject, TokenObject, TradeObject};
+
+const TOKEN_COLUMNS: &str = r#"id::text AS id, name, ticker, contract_address, bonding_curve_percentage, bond_status, market_cap, uri,
+    bonding_curve_address, creator_address, created_at, created_slot"#;
+
+// Fetches the tokens with the given contract addresses
+pub async fn fetch_tokens_by_address(
+    db: &PgPool,
+    addresses: &[String],
+) -> Result<Vec<TokenObject>, anyhow::Error> {
+    let query = format!(
+        r#"SELECT {} FROM token WHERE contract_address = ANY($1)"#,
+        TOKEN_COLUMNS
+    );
+
+    match sqlx::query_as::<_, TokenObject>(&query)
+        .bind(addresses)
+        .fetch_all(db)
+        .await
+    {
+        Ok(r) => Ok(r),
+        Err(e) => {
+            tracing::error!("{}", e);
+            Err(anyhow::Error::msg("Error: Fail to fetch tokens"))
+        }
+    }
+}
+
+// Fetches a page of tokens, newest first, optionally filtered by bond status or creator
+pub async fn fetch_tokens_page(
+    db: &PgPool,
+    status: Option<&str>,
+    creator: Option<&str>,
+    offset: i64,
+    limit: i64,
+) -> Result<Vec<TokenObject>, anyhow::Error> {
+    let query = format!(
+        r#"
+    SELECT {}
+    FROM token
+    WHERE ($1::text IS NULL OR bond_status = $1)
+        AND ($2::text IS NULL OR creator_address = $2)
+    ORDER BY created_at DESC, id
+    OFFSET $3
+    LIMIT $4
+    "#,
+        TOKEN_COLUMNS
+    );
+
+    match sqlx::query_as::<_, TokenObject>(&query)
+        .bind(status)
+        .bind(creator)
+        .bind(offset)
+        .bind(limit)
+        .fetch_all(db)
+        .await
+    {
+        Ok(r) => Ok(r),
+        Err(e) => {
+            tracing::error!("{}", e);
+            Err(anyhow::Error::msg("Error: Fail to fetch tokens"))
+        }
+    }
+}
+
+// Counts the tokens launched by each of the creators, creators without tokens are left out
+pub async fn count_creators_tokens(
+    db: &PgPool,
+    creators: &[String],
+) -> Result<Vec<(String, i64)>, anyhow::Error> {
+    let query = r#"
+    SELECT creator_address, COUNT(*)
+    FROM token
+    WHERE creator_address = ANY($1)
+    GROUP BY creator_address
+    "#;
+
+    match sqlx::query_as::<_, (String, i64)>(query)
+        .bind(creators)
+        .fetch_all(db)
+        .await
+    {
+        Ok(r) => Ok(r),
+        Err(e) => {
+            tracing::error!("{}", e);
+            Err(anyhow::Error::msg("Error: Fail to count creator tokens"))
+        }
+    }
+}
+
+// Fetches a page of trades, newest first, of a token and/or a wallet
+pub async fn fetch_trades_page(
+    db: &PgPool,
+    mint: Option<&str>,
+    wallet: Option<&str>,
+    offset: i64,
+    limit: i64,
+) -> Result<Vec<TradeObject>, anyhow::Error> {
+    let query = r#"
+    SELECT t.id::text AS id, t.sol_amount, t.token_amount, t.is_buy, t.user_address, k.contract_address, t.slot,
+        t.signature, t.created_at
+    FROM trade t
+    JOIN token k ON k.id = t.token_id
+    WHERE ($1::text IS NULL OR k.contract_address = $1)
+        AND ($2::text IS NULL OR t.user_address = $2)
+    ORDER BY t.created_at DESC, t.id
+    OFFSET $3
+    LIMIT $4
+    "#;
+
+    match sqlx::query_as::<_, TradeObject>(query)
+        .bind(mint)
+        .bind(wallet)
+        .bind(offset)
+        .bind(limit)
+        .fetch_all(db)
+        .await
+    {
+        Ok(r) => Ok(r),
+        Err(e) => {
+            tracing::error!("{}", e);
+            Err(anyhow::Error::msg("Error: Fail to fetch trades"))
+        }
+    }
+}
+
+// The same page of trades of each of the tokens, newest first
+pub async fn fetch_tokens_trades_page(
+    db: &PgPool,
+    mints: &[String],
+    offset: i64,
+    limit: i64,
+) -> Result<Vec<TradeObject>, anyhow::Error> {
+    let query = r#"
+    SELECT t.id::text AS id, t.sol_amount, t.token_amount, t.is_buy, t.user_address, k.contract_address, t.slot,
+        t.signature, t.created_at
+    FROM token k
+    CROSS JOIN LATERAL (
+        SELECT * FROM trade
+        WHERE token_id = k.id
+        ORDER BY created_at DESC, id
+        OFFSET $2
+        LIMIT $3
+    ) t
+    WHERE k.contract_address = ANY($1)
+    ORDER BY k.contract_address, t.created_at DESC, t.id
+    "#;
+
+    match sqlx::query_as::<_, TradeObject>(query)
+        .bind(mints)
+        .bind(offset)
+        .bind(limit)
+        .fetch_all(db)
+        .await
+    {
+        Ok(r) => Ok(r),
+        Err(e) => {
+            tracing::error!("{}", e);
+            Err(anyhow::Error::msg("Error: Fail to fetch trades"))
+        }
+    }
+}
+
+// Largest `limit` holders of each of the tokens, like `fetch_holdings`
+pub async fn fetch_tokens_holders(
+    db: &PgPool,
+    mints: &[String],
+    limit: i64,
+) -> Result<Vec<HoldingObject>, anyhow::Error> {
+    let query = r#"
+    SELECT wallet_address, contract_address, balance, percentage
+    FROM (
+        SELECT t.user_address AS wallet_address, k.contract_address,
+            SUM(CASE WHEN t.is_buy THEN t.token_amount ELSE -t.token_amount END)::bigint AS balance,
+            SUM(CASE WHEN t.is_buy THEN t.token_amount ELSE -t.token_amount END)::float8 / 1e6 / 1e9 * 100 AS percentage,
+            ROW_NUMBER() OVER (
+                PARTITION BY k.contract_address
+                ORDER BY SUM(CASE WHEN t.is_buy THEN t.token_amount ELSE -t.token_amount END) DESC
+            ) AS rank
+        FROM trade t
+        JOIN token k ON k.id = t.token_id
+        WHERE k.contract_address = ANY($1)
+        GROUP BY t.user_address, k.contract_address
+        HAVING SUM(CASE WHEN t.is_buy THEN t.token_amount ELSE -t.token_amount END) > 0
+    ) holders
+    WHERE rank <= $2
+    ORDER BY contract_address, balance DESC
+    "#;
+
+    match sqlx::query_as::<_, HoldingObject>(query)
+        .bind(mints)
+        .bind(limit)
+        .fetch_all(db)
+        .await
+    {
+        Ok(r) => Ok(r),
+        Err(e) => {
+            tracing::error!("{}", e);
+            Err(anyhow::Error::msg("Error: Fail to fetch holdings"))
+        }
+    }
+}
+
+// Net token balances per wallet and token derived from the trades, largest first. The total supply is 1B tokens.
+pub async fn fetch_holdings(
+    db: &PgPool,
+    mint: Option<&str>,
+    wallet: Option<&str>,
+    limit: i64,
+) -> Result<Vec<HoldingObject>, anyhow::Error> {
+    let query = r#"
+    SELECT t.user_address AS wallet_address, k.contract_address,
+        SUM(CASE WHEN t.is_buy THEN t.token_amount ELSE -t.token_amount END)::bigint AS balance,
+        SUM(CASE WHEN t.is_buy THEN t.token_amount ELSE -t.token_amount END)::float8 / 1e6 / 1e9 * 100 AS percentage
+    FROM trade t
+    JOIN token k ON k.id = t.token_id
+    WHERE ($1::text IS NULL OR k.contract_address = $1)
+        AND ($2::text IS NULL OR t.user_address = $2)
+    GROUP BY t.user_address, k.contract_address
+    HAVING SUM(CASE WHEN t.is_buy THEN t.token_amount ELSE -t.token_amount END) > 0
+    ORDER BY balance DESC
+    LIMIT $3
+    "#;
+
+    match sqlx::query_as::<_, HoldingObject>(query)
+        .bind(mint)
+        .bind(wallet)
+        .bind(limit)
+        .fetch_all(db)
+        .await
+    {
+        Ok(r) => Ok(r),
+        Err(e) => {
+            tracing::error!("{}", e);
+            Err(anyhow::Error::msg("Error: Fail to fetch holdings"))
+        }
+    }
+}
+
+// Candle of one of the tokens given to `fetch_tokens_candles`
+#[derive(FromRow)]
+pub struct TokenCandle {
+    pub contract_address: String,
+    #[sqlx(flatten)]
+    pub candle: CandleObject,
+}
+
+// Buckets the trades of a token into OHLCV candles, the price of a trade is its SOL amount per token. The open and
+// close are the first and last trades in chain order, by slot and position in the transaction.
+pub async fn fetch_candles(
+    db: &PgPool,
+    mint: &str,
+    interval_secs: i64,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    limit: i64,
+) -> Result<Vec<CandleObject>, anyhow::Error> {
+    let candles =
+        fetch_tokens_candles(db, &[mint.to_string()], interval_secs, from, to, limit).await?;
+
+    Ok(candles.into_iter().map(|candle| candle.candle).collect())
+}
+
+// The first `limit` candles of each of the tokens, oldest first
+pub async fn fetch_tokens_candles(
+    db: &PgPool,
+    mints: &[String],
+    interval_secs: i64,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    limit: i64,
+) -> Result<Vec<TokenCandle>, anyhow::Error> {
+    let query = r#"
+    WITH priced AS (
+        SELECT k.contract_address, t.id, t.created_at, t.slot, t.event_index, t.sol_amount,
+            (t.sol_amount::float8 / 1e9) / (t.token_amount::float8 / 1e6) AS price
+        FROM trade t
+        JOIN token k ON k.id = t.token_id
+        WHERE k.contract_address = ANY($1) AND t.token_amount > 0 AND t.created_at >= $3 AND t.created_at < $4
+    ),
+    candles AS (
+        SELECT contract_address,
+            date_bin(make_interval(secs => $2), created_at, TIMESTAMPTZ '2000-01-01') AS start,
+            (array_agg(price ORDER BY slot, event_index, created_at, id))[1] AS open,
+            MAX(price) AS high,
+            MIN(price) AS low,
+            (array_agg(price ORDER BY slot DESC, event_index DESC, created_at DESC, id DESC))[1] AS close,
+            SUM(sol_amount)::float8 / 1e9 AS volume_sol,
+            COUNT(*) AS trades
+        FROM priced
+        GROUP BY contract_address, start
+    )
+    SELECT contract_address, start, open, high, low, close, volume_sol, trades
+    FROM (
+        SELECT *, ROW_NUMBER() OVER (PARTITION BY contract_address ORDER BY start) AS rank
+        FROM candles
+    ) ranked
+    WHERE rank <= $5
+    ORDER BY contract_address, start
+    "#;
+
+    match sqlx::query_as::<_, TokenCandle>(query)
+        .bind(mints)
+        .bind(interval_secs as f64)
+        .bind(from)
+        .bind(to)
+        .bind(limit)
+        .fetch_all(db)
+        .await
+    {
+        Ok(r) => Ok(r),
+        Err(e) => {
+            tracing::error!("{}", e);
+            Err(anyhow::Error::msg("Error: Fail to fetch candles"))
+        }
+    }
+}
@@ -0,0 +1,9 @@
+pub mod alert;
+pub mod checkpoint;
+pub mod funding;
+pub mod graphql;
+pub mod query;
+pub mod rank;
+pub mod token;
+pub mod trade;
+pub mod webhook;
@@ -3,18 +3,61 @@ use sqlx::{Pool, Postgres};
 use std::collections::{HashMap, HashSet};
 use uuid::Uuid;
 
-use crate::types::{BondStatus, Holding, Token, TokenDetails, Trade};
+use crate::{
+    config::{FundingConfig, SniperConfig},
+    db::funding::fetch_wallet_funding,
+    funding::build_clusters,
+    sniper::analyze_launch,
+    types::{BondStatus, Holding, Token, TokenDetails, Trade},
+};
 
 /// Fetches token data from the database and calculates various metrics such as volume, market cap, top_10_holding_percentage, and creator percentage etc.
-pub async fn fetch_token_data(db: &Pool<Postgres>) -> Vec<TokenDetails> {
-    let all_trades = sqlx::query_as::<_, Trade>(r#"SELECT * FROM trade"#)
-        .fetch_all(&*db)
-        .await
-        .unwrap();
-    let all_tokens = sqlx::query_as::<_, Token>(r#"SELECT * FROM token"#)
-        .fetch_all(&*db)
-        .await
-        .unwrap();
+/// Only the token with the given contract address is loaded when `mint` is set.
+pub async fn fetch_token_data(
+    db: &Pool<Postgres>,
+    sniper_config: &SniperConfig,
+    funding_config: &FundingConfig,
+    mint: Option<&str>,
+) -> Result<Vec<TokenDetails>, anyhow::Error> {
+    let all_trades = match sqlx::query_as::<_, Trade>(
+        r#"SELECT * FROM trade WHERE $1::text IS NULL OR token_id IN (SELECT id FROM token WHERE contract_address = $1)"#,
+    )
+    .bind(mint)
+    .fetch_all(&*db)
+    .await
+    {
+        Ok(r) => r,
+        Err(e) => {
+            tracing::error!("{}", e);
+            return Err(anyhow::Error::msg("Error: Fail to fetch trades"));
+        }
+    };
+    let all_tokens = match sqlx::query_as::<_, Token>(
+        r#"SELECT * FROM token WHERE $1::text IS NULL OR contract_address = $1"#,
+    )
+    .bind(mint)
+    .fetch_all(&*db)
+    .await
+    {
+        Ok(r) => r,
+        Err(e) => {
+            tracing::error!("{}", e);
+            return Err(anyhow::Error::msg("Error: Fail to fetch tokens"));
+        }
+    };
+
+    let wallets: Vec<String> = all_trades
+        .iter()
+        .map(|trade| trade.user_address.clone())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+
+    // Wallets funded from the same source are treated as one holder
+    let clusters = build_clusters(
+        &fetch_wallet_funding(db, &wallets, funding_config.max_funder_fanout).await?,
+        funding_config.max_funder_fanout,
+    );
 
     let token_map: HashMap<uuid::Uuid, Token> =
         all_tokens.iter().cloned().map(|t| (t.id, t)).collect();
@@ -23,7 +66,14 @@ pub async fn fetch_token_data(db: &Pool<Postgres>) -> Vec<TokenDetails> {
 
     let mut holdings_map: HashMap<Uuid, Vec<Holding>> = HashMap::new();
 
+    let mut trades_by_token: HashMap<Uuid, Vec<Trade>> = HashMap::new();
+
     for trade in all_trades {
+        trades_by_token
+            .entry(trade.token_id)
+            .or_default()
+            .push(trade.clone());
+
         volume
             .entry(trade.token_id)
             .and_modify(|x| *x += trade.sol_amount as f64 / LAMPORTS_PER_SOL as f64)
@@ -66,7 +116,12 @@ pub async fn fetch_token_data(db: &Pool<Postgres>) -> Vec<TokenDetails> {
 
         let creator_balance = holders
             .iter()
-            .find(|h| h.user == token_map.get(&token_id).unwrap().creator_address)
+            .find(|h| {
+                token_map
+                    .get(&token_id)
+                    .map(|t| h.user == t.creator_address)
+                    .unwrap_or(false)
+            })
             .map(|h| h.net_tokens)
             .unwrap_or(0);
 
@@ -86,9 +141,52 @@ pub async fn fetch_token_data(db: &Pool<Postgres>) -> Vec<TokenDetails> {
             0.0
         };
 
+        let mut cluster_balances: HashMap<&str, i64> = HashMap::new();
+
+        for holder in holders.iter() {
+            let cluster = clusters
+                .get(&holder.user)
+                .map(|c| c.as_str())
+                .unwrap_or(holder.user.as_str());
+
+            *cluster_balances.entry(cluster).or_insert(0) += holder.net_tokens;
+        }
+
+        let cluster_holder_count = cluster_balances.values().filter(|b| **b > 0).count();
+
+        let mut sorted_clusters: Vec<i64> = cluster_balances.into_values().collect();
+
+        sorted_clusters.sort_by(|a, b| b.cmp(a));
+
+        let top_10_clusters_total: i64 = sorted_clusters.iter().take(10).sum();
+
+        let funds_percent_by_top_10_clusters = if total_supply > 0 {
+            ((top_10_clusters_total as f64 / 10f64.powi(6)) / total_supply as f64 * 100.0).max(0.0)
+        } else {
+            0.0
+        };
+
         if let Some(token_details) = all_tokens.iter().find(|x| x.id == token_id) {
             let volume = volume.get(&token_id).cloned();
 
+            let launch = analyze_launch(
+                token_details,
+                trades_by_token
+                    .get(&token_id)
+                    .map(|t| t.as_slice())
+                    .unwrap_or(&[]),
+                &clusters,
+                sniper_config,
+                total_supply as f64,
+            );
+
+            tracing::debug!(
+                %token_id,
+                snipers = launch.sniper_wallets.len(),
+                bundles = launch.bundles.len(),
+                "Analyzed launch"
+            );
+
             let market_cap = token_details.market_cap.unwrap_or_else(|| 0);
 
             token_vec.push(TokenDetails {
@@ -114,10 +212,14 @@ pub async fn fetch_token_data(db: &Pool<Postgres>) -> Vec<TokenDetails> {
                 funds_percent_by_top_10,
                 holder_count,
                 creator_percent,
+                cluster_holder_count,
+                funds_percent_by_top_10_clusters,
+                sniper_percent: launch.sniper_percent,
+                bundled: launch.bundled,
             });
         }
 
-        println!("Token: {token_id}, Top 10: {funds_percent_by_top_10:.2}%, Creator: {creator_percent:.2}%, Holders: {holder_count}");
+        tracing::debug!(%token_id, funds_percent_by_top_10, creator_percent, holder_count, "Computed holder stats");
     }
 
     let b_ids: HashSet<String> = token_vec.iter().map(|t| t.id.to_string()).collect();
@@ -146,8 +248,12 @@ pub async fn fetch_token_data(db: &Pool<Postgres>) -> Vec<TokenDetails> {
             funds_percent_by_top_10: 0.0,
             holder_count: 0,
             creator_percent: 0.0,
+            cluster_holder_count: 0,
+            funds_percent_by_top_10_clusters: 0.0,
+            sniper_percent: 0.0,
+            bundled: false,
         });
     }
 
-    token_vec
+    Ok(token_vec)
 }
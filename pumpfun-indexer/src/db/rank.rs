@@ -0,0 +1,221 @@
+use std::{sync::Arc, time::Instant};
+
+use sqlx::{
+    types::chrono::{DateTime, Utc},
+    PgPool,
+};
+
+use crate::{
+    metrics::metrics,
+    types::{RankedToken, TokenActivity, TokenRank},
+};
+
+// Columns returned for a ranked token, shared by the trending and king of the hill queries
+const RANKED_TOKEN_COLUMNS: &str = r#"
+    t.id::text AS id, t.name, t.ticker, t.contract_address, t.uri, t.bond_status,
+    t.bonding_curve_percentage, t.market_cap, r.rank, r.score, r.volume_sol, r.unique_buyers,
+    r.holder_growth, r.curve_velocity, r.market_cap_momentum, r.updated_at AS ranked_at
+"#;
+
+// Fetches the activity of every token traded since `window_start`. Volume is exponentially decayed with the given half-life
+// and holder counts are compared against the balances at the start of the window.
+pub async fn fetch_token_activity(
+    db: Arc<PgPool>,
+    now: DateTime<Utc>,
+    window_start: DateTime<Utc>,
+    half_life_secs: f64,
+) -> Result<Vec<TokenActivity>, anyhow::Error> {
+    let query = r#"
+    WITH recent AS (
+        SELECT
+        token_id,
+        SUM(sol_amount::double precision / 1e9 * EXP(-LN(2) * EXTRACT(EPOCH FROM ($1 - created_at)) / $3)) AS decayed_volume_sol,
+        COUNT(DISTINCT user_address) FILTER (WHERE is_buy) AS unique_buyers
+        FROM trade
+        WHERE created_at >= $2
+        GROUP BY token_id
+    ),
+    balances AS (
+        SELECT
+        tr.token_id,
+        SUM(CASE WHEN tr.is_buy THEN tr.token_amount ELSE -tr.token_amount END) AS balance,
+        SUM(CASE WHEN tr.created_at < $2 THEN CASE WHEN tr.is_buy THEN tr.token_amount ELSE -tr.token_amount END ELSE 0 END) AS balance_before
+        FROM trade tr
+        JOIN recent ON recent.token_id = tr.token_id
+        GROUP BY tr.token_id, tr.user_address
+    ),
+    holders AS (
+        SELECT
+        token_id,
+        COUNT(*) FILTER (WHERE balance > 0) AS holders,
+        COUNT(*) FILTER (WHERE balance_before > 0) AS holders_before
+        FROM balances
+        GROUP BY token_id
+    )
+    SELECT
+    t.id AS token_id,
+    t.bond_status,
+    t.bonding_curve_percentage,
+    t.market_cap,
+    recent.decayed_volume_sol,
+    recent.unique_buyers,
+    COALESCE(holders.holders, 0) AS holders,
+    COALESCE(holders.holders_before, 0) AS holders_before,
+    r.bonding_curve_percentage AS previous_bonding_curve_percentage,
+    r.market_cap AS previous_market_cap,
+    r.updated_at AS previous_updated_at
+    FROM recent
+    JOIN token t ON t.id = recent.token_id
+    LEFT JOIN holders ON holders.token_id = recent.token_id
+    LEFT JOIN token_rank r ON r.token_id = recent.token_id
+    "#;
+
+    match sqlx::query_as::<_, TokenActivity>(query)
+        .bind(now)
+        .bind(window_start)
+        .bind(half_life_secs)
+        .fetch_all(&*db)
+        .await
+    {
+        Ok(r) => Ok(r),
+        Err(e) => {
+            tracing::error!("{}", e);
+            Err(anyhow::Error::msg("Error: Fail to fetch token activity"))
+        }
+    }
+}
+
+// Replaces the stored ranking with the freshly computed one in a single transaction.
+#[tracing::instrument(skip_all, fields(tokens = ranks.len()))]
+pub async fn save_token_ranks(db: Arc<PgPool>, ranks: Vec<TokenRank>) {
+    let length = ranks.len();
+    let now = Utc::now();
+    let started = Instant::now();
+
+    let mut token_ids = Vec::with_capacity(length);
+    let mut positions = Vec::with_capacity(length);
+    let mut scores = Vec::with_capacity(length);
+    let mut volumes = Vec::with_capacity(length);
+    let mut buyers = Vec::with_capacity(length);
+    let mut holder_growths = Vec::with_capacity(length);
+    let mut velocities = Vec::with_capacity(length);
+    let mut momentums = Vec::with_capacity(length);
+    let mut curve_percentages = Vec::with_capacity(length);
+    let mut market_caps = Vec::with_capacity(length);
+
+    for rank in ranks {
+        token_ids.push(rank.token_id);
+        positions.push(rank.rank);
+        scores.push(rank.score);
+        volumes.push(rank.volume_sol);
+        buyers.push(rank.unique_buyers);
+        holder_growths.push(rank.holder_growth);
+        velocities.push(rank.curve_velocity);
+        momentums.push(rank.market_cap_momentum);
+        curve_percentages.push(rank.bonding_curve_percentage);
+        market_caps.push(rank.market_cap);
+    }
+
+    let result = async {
+        let mut tx = db.begin().await?;
+
+        // Tokens without activity in the window drop out of the ranking
+        sqlx::query(r#"DELETE FROM token_rank WHERE token_id <> ALL($1)"#)
+            .bind(&token_ids)
+            .execute(&mut tx)
+            .await?;
+
+        let upsert_sql = r#"
+        INSERT INTO token_rank (
+        token_id, rank, score, volume_sol, unique_buyers, holder_growth, curve_velocity,
+        market_cap_momentum, bonding_curve_percentage, market_cap, updated_at
+        )
+        SELECT *, $11 FROM UNNEST(
+        $1::uuid[],
+        $2::int[],
+        $3::double precision[],
+        $4::double precision[],
+        $5::bigint[],
+        $6::bigint[],
+        $7::double precision[],
+        $8::double precision[],
+        $9::int[],
+        $10::bigint[]
+        )
+        ON CONFLICT (token_id) DO UPDATE SET
+        rank = EXCLUDED.rank,
+        score = EXCLUDED.score,
+        volume_sol = EXCLUDED.volume_sol,
+        unique_buyers = EXCLUDED.unique_buyers,
+        holder_growth = EXCLUDED.holder_growth,
+        curve_velocity = EXCLUDED.curve_velocity,
+        market_cap_momentum = EXCLUDED.market_cap_momentum,
+        bonding_curve_percentage = EXCLUDED.bonding_curve_percentage,
+        market_cap = EXCLUDED.market_cap,
+        updated_at = EXCLUDED.updated_at
+        "#;
+
+        sqlx::query(upsert_sql)
+            .bind(&token_ids)
+            .bind(&positions)
+            .bind(&scores)
+            .bind(&volumes)
+            .bind(&buyers)
+            .bind(&holder_growths)
+            .bind(&velocities)
+            .bind(&momentums)
+            .bind(&curve_percentages)
+            .bind(&market_caps)
+            .bind(now)
+            .execute(&mut tx)
+            .await?;
+
+        tx.commit().await
+    }
+    .await;
+
+    metrics().observe_db_batch("save_token_ranks", length, started, result.is_err());
+
+    if let Err(err) = result {
+        tracing::error!(error = ?err, "Failed to save token ranks");
+    }
+}
+
+// Fetches the top ranked tokens, best first
+pub async fn fetch_trending(db: &PgPool, limit: i64) -> Result<Vec<RankedToken>, anyhow::Error> {
+    let query = format!(
+        "SELECT {} FROM token_rank r JOIN token t ON t.id = r.token_id ORDER BY r.rank ASC LIMIT $1",
+        RANKED_TOKEN_COLUMNS
+    );
+
+    match sqlx::query_as::<_, RankedToken>(&query)
+        .bind(limit)
+        .fetch_all(db)
+        .await
+    {
+        Ok(r) => Ok(r),
+        Err(e) => {
+            tracing::error!("{}", e);
+            Err(anyhow::Error::msg("Error: Fail to fetch trending tokens"))
+        }
+    }
+}
+
+// King of the hill is the ranked token with the highest market cap that has not graduated yet
+pub async fn fetch_king_of_the_hill(db: &PgPool) -> Result<Option<RankedToken>, anyhow::Error> {
+    let query = format!(
+        "SELECT {} FROM token_rank r JOIN token t ON t.id = r.token_id WHERE t.bond_status IS DISTINCT FROM 'graduated' ORDER BY t.market_cap DESC NULLS LAST, r.rank ASC LIMIT 1",
+        RANKED_TOKEN_COLUMNS
+    );
+
+    match sqlx::query_as::<_, RankedToken>(&query)
+        .fetch_optional(db)
+        .await
+    {
+        Ok(r) => Ok(r),
+        Err(e) => {
+            tracing::error!("{}", e);
+            Err(anyhow::Error::msg("Error: Fail to fetch king of the hill"))
+        }
+    }
+}
@@ -0,0 +1,207 @@
+use std::{sync::Arc, time::Instant};
+
+use carbon_pumpfun_decoder::instructions::create_event::CreateEvent;
+use solana_pubkey::Pubkey;
+use sqlx::{types::chrono::Utc, PgPool};
+
+use crate::{
+    metrics::metrics,
+    types::{BondStatus, BondingCurveAndMcInfo, TokenLaunch},
+};
+
+// This function creates a new token in the database based on the provided CreateEvent data.
+#[tracing::instrument(skip_all, fields(mint = %create_event.mint))]
+pub async fn create_token(
+    db: Arc<PgPool>,
+    create_event: CreateEvent,
+    slot: u64,
+    signature: String,
+) {
+    let id = uuid::Uuid::new_v4();
+    let current_time = Utc::now();
+
+    let insert_sql = r#"
+    INSERT INTO token(
+    id,
+    created_at,
+    updated_at,
+    name,
+    ticker,
+    contract_address,
+    bond_status,
+    uri,
+    bonding_curve_address,
+    creator_address,
+    created_slot,
+    created_signature
+    ) VALUES($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)"#;
+
+    if let Err(err) = sqlx::query(insert_sql)
+        .bind(id)
+        .bind(current_time)
+        .bind(current_time)
+        .bind(create_event.name)
+        .bind(create_event.symbol)
+        .bind(create_event.mint.to_string())
+        .bind(BondStatus::NewlyLaunched)
+        .bind(create_event.uri)
+        .bind(create_event.bonding_curve.to_string())
+        .bind(create_event.user.to_string())
+        .bind(slot as i64)
+        .bind(signature)
+        .execute(&*db)
+        .await
+    {
+        metrics()
+            .db_errors
+            .with_label_values(&["create_token"])
+            .inc();
+        tracing::error!(error = ?err, "Failed to insert new token");
+    }
+}
+
+// This function retrieves bonding curve and market cap information of the most recently created tokens still on the
+// curve, up to `limit`. The older ones are loaded on their next trade with `get_token_bonding_curve_and_mc_info`.
+pub async fn get_bonding_curve_and_mc_info(
+    db: Arc<PgPool>,
+    limit: i64,
+) -> Result<Vec<BondingCurveAndMcInfo>, anyhow::Error> {
+    let query = r#"SELECT contract_address, bonding_curve_address, bonding_curve_percentage, market_cap, market_cap_slot
+    FROM token WHERE bond_status IS DISTINCT FROM 'graduated' ORDER BY created_at DESC LIMIT $1"#;
+
+    let bonding_curve_info = match sqlx::query_as::<_, BondingCurveAndMcInfo>(query)
+        .bind(limit)
+        .fetch_all(&*db)
+        .await
+    {
+        Ok(r) => r,
+        Err(e) => {
+            tracing::error!("{}", e);
+            return Err(anyhow::Error::msg(
+                "Error: Fail to fetch bonding curve info",
+            ));
+        }
+    };
+
+    return Ok(bonding_curve_info);
+}
+
+// Bonding curve and market cap information of a single token, None if it isn't stored or has graduated
+pub async fn get_token_bonding_curve_and_mc_info(
+    db: Arc<PgPool>,
+    mint: &str,
+) -> Result<Option<BondingCurveAndMcInfo>, anyhow::Error> {
+    let query = r#"SELECT contract_address, bonding_curve_address, bonding_curve_percentage, market_cap, market_cap_slot
+    FROM token WHERE contract_address = $1 AND bond_status IS DISTINCT FROM 'graduated'"#;
+
+    let started = Instant::now();
+    let result = sqlx::query_as::<_, BondingCurveAndMcInfo>(query)
+        .bind(mint)
+        .fetch_optional(&*db)
+        .await;
+
+    metrics().observe_db_batch("reload_token_state", 1, started, result.is_err());
+
+    result.map_err(|err| {
+        tracing::error!(error = ?err, "Failed to reload the token state");
+        anyhow::Error::msg("Error: Fail to fetch bonding curve info")
+    })
+}
+
+// Retrieves the creator and creation time of a token, used to start the alert state of a token whose create event
+// wasn't seen by this process
+pub async fn get_token_launch(
+    db: Arc<PgPool>,
+    mint: &str,
+) -> Result<Option<TokenLaunch>, anyhow::Error> {
+    let query = r#"SELECT creator_address, created_at FROM token WHERE contract_address = $1"#;
+
+    match sqlx::query_as::<_, TokenLaunch>(query)
+        .bind(mint)
+        .fetch_optional(&*db)
+        .await
+    {
+        Ok(launch) => Ok(launch),
+        Err(e) => {
+            tracing::error!("{}", e);
+            Err(anyhow::Error::msg("Error: Fail to fetch token launch"))
+        }
+    }
+}
+
+// Updates the bonding curve percentage and market cap of a batch of tokens with a single UNNEST update, the bind
+// parameters don't grow with the batch. Rows that already hold the same values or come from a later trade are skipped.
+#[tracing::instrument(skip_all, fields(batch_size = updates.len()))]
+pub async fn update_bonding_curve_and_market_cap(
+    db: Arc<PgPool>,
+    updates: &[BondingCurveAndMcInfo],
+) -> Result<(), anyhow::Error> {
+    if updates.is_empty() {
+        return Ok(());
+    }
+
+    let mut contract_addresses = Vec::with_capacity(updates.len());
+    let mut market_caps = Vec::with_capacity(updates.len());
+    let mut bonding_curve_percentages = Vec::with_capacity(updates.len());
+    let mut market_cap_slots = Vec::with_capacity(updates.len());
+
+    for update in updates {
+        contract_addresses.push(update.contract_address.clone());
+        market_caps.push(update.market_cap);
+        bonding_curve_percentages.push(update.bonding_curve_percentage);
+        market_cap_slots.push(update.market_cap_slot);
+    }
+
+    let query = r#"
+    UPDATE token AS t
+    SET market_cap = u.market_cap, bonding_curve_percentage = u.bonding_curve_percentage,
+    market_cap_slot = COALESCE(u.market_cap_slot, t.market_cap_slot)
+    FROM UNNEST($1::text[], $2::bigint[], $3::int[], $4::bigint[])
+    AS u(contract_address, market_cap, bonding_curve_percentage, market_cap_slot)
+    WHERE u.contract_address = t.contract_address
+    AND (t.market_cap, t.bonding_curve_percentage) IS DISTINCT FROM (u.market_cap, u.bonding_curve_percentage)
+    AND (t.market_cap_slot IS NULL OR u.market_cap_slot IS NULL OR t.market_cap_slot <= u.market_cap_slot)
+    "#;
+
+    let started = Instant::now();
+    let result = sqlx::query(query)
+        .bind(&contract_addresses)
+        .bind(&market_caps)
+        .bind(&bonding_curve_percentages)
+        .bind(&market_cap_slots)
+        .execute(&*db)
+        .await;
+
+    metrics().observe_db_batch("update_market_cap", updates.len(), started, result.is_err());
+
+    if let Err(err) = result {
+        tracing::error!(error = ?err, "Failed to save the data");
+        return Err(anyhow::Error::msg(
+            "Error: Fail to update bonding curve and market cap",
+        ));
+    }
+
+    tracing::debug!("Updated bonding curve and market cap");
+
+    Ok(())
+}
+
+#[tracing::instrument(skip_all, fields(mint = %mint, ?bond_status))]
+pub async fn change_status(bond_status: BondStatus, mint: Pubkey, db: Arc<PgPool>) {
+    let update_sql = r#"
+    UPDATE token SET bond_status = $1 WHERE contract_address = $2
+    "#;
+
+    if let Err(err) = sqlx::query(update_sql)
+        .bind(bond_status)
+        .bind(mint.to_string())
+        .execute(&*db)
+        .await
+    {
+        metrics()
+            .db_errors
+            .with_label_values(&["change_status"])
+            .inc();
+        tracing::error!(error = ?err, "Failed to update the bond status");
+    }
+}
@@ -0,0 +1,368 @@
+use std::sync::Arc;
+
+use redis::{aio::MultiplexedConnection, PushInfo, Value};
+use sqlx::{
+    types::chrono::{DateTime, Utc},
+    PgPool,
+};
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+use crate::{
+    cache::CacheInvalidator,
+    config::TradeWriterConfig,
+    events::{EventEnvelope, EventPayload},
+    leader::Leadership,
+    metrics::metrics,
+    trade_writer::TradeWriter,
+};
+
+// Binary timestamps count microseconds from 2000-01-01, this is that date in microseconds since the Unix epoch
+const POSTGRES_EPOCH_MICROS: i64 = 946_684_800_000_000;
+
+// Staging table of the COPY, one per session. Its rows only live until the merge commits.
+const CREATE_TRADE_STAGING: &str = r#"
+    CREATE TEMP TABLE IF NOT EXISTS trade_staging (
+        id uuid,
+        sol_amount bigint,
+        token_amount bigint,
+        is_buy boolean,
+        user_address text,
+        created_at timestamptz,
+        updated_at timestamptz,
+        contract_address text,
+        slot bigint,
+        signature text,
+        event_index bigint
+    ) ON COMMIT DELETE ROWS
+"#;
+
+// Merges the staged trades, skipping those already stored. Returns the number of stored trades, of trades whose token
+// isn't stored and of duplicates.
+const MERGE_TRADE_STAGING: &str = r#"
+    WITH staged AS (
+        SELECT DISTINCT ON (s.signature, s.event_index) s.*, tok.id AS token_id
+        FROM trade_staging s
+        LEFT JOIN token tok ON tok.contract_address = s.contract_address
+    ),
+    inserted AS (
+        INSERT INTO trade (id, sol_amount, token_amount, is_buy, user_address, created_at, updated_at, token_id, slot, signature, event_index)
+        SELECT id, sol_amount, token_amount, is_buy, user_address, created_at, updated_at, token_id, slot, signature, event_index
+        FROM staged
+        WHERE token_id IS NOT NULL
+        ON CONFLICT (signature, event_index) DO NOTHING
+        RETURNING 1
+    )
+    SELECT
+    (SELECT COUNT(*) FROM inserted) AS stored,
+    (SELECT COUNT(*) FROM staged WHERE token_id IS NULL) AS unknown_token,
+    (SELECT COUNT(*) FROM trade_staging) AS staged
+"#;
+
+// Consumes messages from the Redis channel and hands them to a TradeWriter, which stores them in batches sized by
+// count and time. When the shutdown token is cancelled, the remaining messages are drained and the writer is flushed
+// before returning. Every ingest replica is subscribed, only the leader stores what it receives.
+pub async fn consume_and_store(
+    redis: &mut MultiplexedConnection,
+    db: Arc<PgPool>,
+    rx: &mut UnboundedReceiver<PushInfo>,
+    config: &TradeWriterConfig,
+    leadership: &Leadership,
+    cache: CacheInvalidator,
+    shutdown: CancellationToken,
+) {
+    let _ = redis
+        .psubscribe("trade")
+        .await
+        .expect("Failed to subscribe to trade channel");
+
+    let mut writer = TradeWriter::new(db, config.clone()).cache(cache);
+
+    // Writes the trades of a quiet period that never fill a batch
+    let mut flush_interval = tokio::time::interval(writer.flush_interval());
+
+    loop {
+        let msg = tokio::select! {
+            _ = shutdown.cancelled() => break,
+            _ = flush_interval.tick() => {
+                if writer.is_due() {
+                    writer.flush().await;
+                }
+                continue;
+            }
+            msg = rx.recv() => match msg {
+                Some(msg) => msg,
+                None => break,
+            },
+        };
+
+        if !leadership.is_leader() {
+            continue;
+        }
+
+        let Some(parsed) = parse_trade_message(msg) else {
+            continue;
+        };
+
+        writer.push(parsed).await;
+    }
+
+    // Drain whatever is still sitting in the channel so that no published trade is lost, a standby stores none of it
+    while let Ok(msg) = rx.try_recv() {
+        if !leadership.is_leader() {
+            continue;
+        }
+
+        if let Some(parsed) = parse_trade_message(msg) {
+            writer.push(parsed).await;
+        }
+    }
+
+    tracing::info!("Flushing {} buffered trades", writer.buffered());
+
+    writer.close().await;
+}
+
+// Parses a Redis push message into the trade envelope published on the "trade" channel, in either encoding.
+pub fn parse_trade_message(msg: PushInfo) -> Option<EventEnvelope> {
+    let message = msg.data;
+
+    if message.len() < 3 {
+        tracing::error!("Received message with insufficient data: {:?}", message);
+        return None;
+    }
+
+    let Value::BulkString(ref data) = message[2] else {
+        tracing::error!("Unexpected message format: {:?}", message);
+        return None;
+    };
+
+    let parsed = match EventEnvelope::decode(data) {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            tracing::error!(error = ?err, "Failed to decode trade envelope");
+            return None;
+        }
+    };
+
+    let EventPayload::Trade(trade) = &parsed.payload else {
+        tracing::error!(
+            event = parsed.event_type(),
+            "Unexpected event on the trade channel"
+        );
+        return None;
+    };
+
+    tracing::debug!(mint = %parsed.mint, user = %trade.user, version = parsed.version, "Parsed trade envelope");
+
+    Some(parsed)
+}
+
+// Stores a batch of trades with a binary COPY into the staging table, merged into `trade` within the same
+// transaction. Trades of tokens that aren't stored and trades stored before are dropped by the merge and counted in
+// `indexer_trades_dropped_total`. Returns the number of stored trades.
+pub async fn copy_trades(db: &PgPool, trades: &[EventEnvelope]) -> Result<u64, sqlx::Error> {
+    let mut tx = db.begin().await?;
+
+    sqlx::query(CREATE_TRADE_STAGING).execute(&mut tx).await?;
+
+    let mut copy = tx
+        .copy_in_raw("COPY trade_staging FROM STDIN (FORMAT binary)")
+        .await?;
+
+    copy.send(encode_trade_rows(trades, Utc::now())).await?;
+    copy.finish().await?;
+
+    let (stored, unknown_token, staged): (i64, i64, i64) = sqlx::query_as(MERGE_TRADE_STAGING)
+        .fetch_one(&mut tx)
+        .await?;
+
+    tx.commit().await?;
+
+    metrics()
+        .trades_dropped
+        .with_label_values(&["unknown_token"])
+        .inc_by(unknown_token as u64);
+    metrics()
+        .trades_dropped
+        .with_label_values(&["duplicate"])
+        .inc_by((staged - stored - unknown_token).max(0) as u64);
+
+    if unknown_token > 0 {
+        tracing::warn!(
+            trades = unknown_token,
+            "Dropped trades of tokens that aren't stored"
+        );
+    }
+
+    Ok(stored as u64)
+}
+
+// The trades as the rows of a binary COPY into the staging table, header and trailer included
+fn encode_trade_rows(trades: &[EventEnvelope], now: DateTime<Utc>) -> Vec<u8> {
+    let now = now.timestamp_micros() - POSTGRES_EPOCH_MICROS;
+
+    let mut buffer = Vec::with_capacity(19 + trades.len() * 256);
+
+    buffer.extend_from_slice(b"PGCOPY\n\xff\r\n\0");
+    // Flags and the length of the header extension
+    buffer.extend_from_slice(&0i32.to_be_bytes());
+    buffer.extend_from_slice(&0i32.to_be_bytes());
+
+    for envelope in trades {
+        let EventPayload::Trade(trade) = &envelope.payload else {
+            continue;
+        };
+
+        buffer.extend_from_slice(&11i16.to_be_bytes());
+
+        push_field(&mut buffer, Uuid::new_v4().as_bytes());
+        push_field(&mut buffer, &(trade.sol_amount as i64).to_be_bytes());
+        push_field(&mut buffer, &(trade.token_amount as i64).to_be_bytes());
+        push_field(&mut buffer, &[trade.is_buy as u8]);
+        push_field(&mut buffer, trade.user.as_bytes());
+        push_field(&mut buffer, &now.to_be_bytes());
+        push_field(&mut buffer, &now.to_be_bytes());
+        push_field(&mut buffer, envelope.mint.as_bytes());
+        push_field(&mut buffer, &(envelope.slot as i64).to_be_bytes());
+        push_field(&mut buffer, envelope.signature.as_bytes());
+
+        match envelope.event_index() {
+            Some(event_index) => push_field(&mut buffer, &event_index.to_be_bytes()),
+            // A NULL field is a length of -1 without a value
+            None => buffer.extend_from_slice(&(-1i32).to_be_bytes()),
+        }
+    }
+
+    buffer.extend_from_slice(&(-1i16).to_be_bytes());
+
+    buffer
+}
+
+fn push_field(buffer: &mut Vec<u8>, value: &[u8]) {
+    buffer.extend_from_slice(&(value.len() as i32).to_be_bytes());
+    buffer.extend_from_slice(value);
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::types::chrono::TimeZone;
+
+    use super::*;
+    use crate::events::TradeData;
+
+    fn trade(id: &str, is_buy: bool) -> EventEnvelope {
+        EventEnvelope::new(
+            id.to_string(),
+            42,
+            "signature",
+            "mint".to_string(),
+            EventPayload::Trade(TradeData {
+                user: "wallet".to_string(),
+                is_buy,
+                sol_amount: 1_500_000_000,
+                token_amount: 35_000_000_000,
+                timestamp: 0,
+                virtual_sol_reserves: 30_000_000_000,
+                virtual_token_reserves: 1_073_000_000_000_000,
+                real_sol_reserves: 0,
+                real_token_reserves: 793_100_000_000_000,
+                fee_recipient: "fee".to_string(),
+                fee_basis_points: 100,
+                fee: 10_000,
+                price_sol: 0.0,
+                price_usd: 0.0,
+                sol_amount_usd: 0.0,
+                market_cap_usd: 0,
+            }),
+        )
+    }
+
+    // Splits a binary COPY into its rows of fields, None for a NULL field
+    fn decode_rows(mut buffer: &[u8]) -> Vec<Vec<Option<Vec<u8>>>> {
+        let mut take = |len: usize| {
+            let current = buffer;
+            let (head, rest) = current.split_at(len);
+            buffer = rest;
+            head.to_vec()
+        };
+
+        assert_eq!(take(11), b"PGCOPY\n\xff\r\n\0");
+        assert_eq!(take(4), 0i32.to_be_bytes());
+        assert_eq!(take(4), 0i32.to_be_bytes());
+
+        let mut rows = Vec::new();
+
+        loop {
+            let fields = i16::from_be_bytes(take(2).try_into().unwrap());
+
+            if fields == -1 {
+                break;
+            }
+
+            let row = (0..fields)
+                .map(|_| {
+                    let len = i32::from_be_bytes(take(4).try_into().unwrap());
+
+                    (len >= 0).then(|| take(len as usize))
+                })
+                .collect();
+
+            rows.push(row);
+        }
+
+        assert!(buffer.is_empty(), "Bytes left after the trailer");
+
+        rows
+    }
+
+    fn int8(field: &Option<Vec<u8>>) -> i64 {
+        i64::from_be_bytes(field.as_deref().unwrap().try_into().unwrap())
+    }
+
+    #[test]
+    fn encodes_trades_as_binary_copy_rows() {
+        let now = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        let trades = [
+            trade("signature:2:5", true),
+            trade("not-an-event-id", false),
+        ];
+
+        let rows = decode_rows(&encode_trade_rows(&trades, now));
+
+        assert_eq!(rows.len(), 2);
+
+        let row = &rows[0];
+        assert_eq!(row.len(), 11);
+        assert_eq!(row[0].as_ref().unwrap().len(), 16);
+        assert_eq!(int8(&row[1]), 1_500_000_000);
+        assert_eq!(int8(&row[2]), 35_000_000_000);
+        assert_eq!(row[3].as_deref(), Some(&[1u8][..]));
+        assert_eq!(row[4].as_deref(), Some(&b"wallet"[..]));
+        // 2025-01-01 is 9132 days after the 2000-01-01 epoch of binary timestamps
+        assert_eq!(int8(&row[5]), 9_132 * 86_400 * 1_000_000);
+        assert_eq!(int8(&row[6]), int8(&row[5]));
+        assert_eq!(row[7].as_deref(), Some(&b"mint"[..]));
+        assert_eq!(int8(&row[8]), 42);
+        assert_eq!(row[9].as_deref(), Some(&b"signature"[..]));
+        assert_eq!(int8(&row[10]), (5 << 8) | 2);
+
+        assert_eq!(rows[1][3].as_deref(), Some(&[0u8][..]));
+        assert_eq!(rows[1][10], None);
+    }
+
+    #[test]
+    fn skips_envelopes_that_are_not_trades() {
+        let mut create = trade("signature:1:0", true);
+        create.payload = EventPayload::Complete {
+            user: "wallet".to_string(),
+            bonding_curve: "curve".to_string(),
+            timestamp: 0,
+        };
+
+        let rows = decode_rows(&encode_trade_rows(&[create], Utc::now()));
+
+        assert!(rows.is_empty());
+    }
+}
@@ -0,0 +1,385 @@
+use std::sync::Arc;
+
+use sqlx::{
+    types::chrono::{DateTime, Utc},
+    PgPool,
+};
+use uuid::Uuid;
+
+use crate::{
+    metrics::metrics,
+    types::{DueDelivery, OwnerScope, Webhook, WebhookDelivery},
+};
+
+const WEBHOOK_COLUMNS: &str = r#"id::text AS id, url, secret, event_types, mints, creators, enabled, consecutive_failures, created_at, disabled_at"#;
+
+// Registers a new webhook, owned by the API key that registered it
+pub async fn create_webhook(
+    db: &PgPool,
+    api_key_id: Option<uuid::Uuid>,
+    url: &str,
+    secret: &str,
+    event_types: &[String],
+    mints: Option<&[String]>,
+    creators: Option<&[String]>,
+) -> Result<Webhook, anyhow::Error> {
+    let insert_sql = format!(
+        r#"
+    INSERT INTO webhook(id, url, secret, event_types, mints, creators, enabled, consecutive_failures, created_at, api_key_id)
+    VALUES($1, $2, $3, $4, $5, $6, true, 0, $7, $8)
+    RETURNING {}
+    "#,
+        WEBHOOK_COLUMNS
+    );
+
+    match sqlx::query_as::<_, Webhook>(&insert_sql)
+        .bind(uuid::Uuid::new_v4())
+        .bind(url)
+        .bind(secret)
+        .bind(event_types)
+        .bind(mints)
+        .bind(creators)
+        .bind(Utc::now())
+        .bind(api_key_id)
+        .fetch_one(db)
+        .await
+    {
+        Ok(r) => Ok(r),
+        Err(e) => {
+            tracing::error!("{}", e);
+            Err(anyhow::Error::msg("Error: Fail to create webhook"))
+        }
+    }
+}
+
+// Fetches the registered webhooks within the scope
+pub async fn fetch_webhooks(db: &PgPool, scope: OwnerScope) -> Result<Vec<Webhook>, anyhow::Error> {
+    let query = format!(
+        r#"SELECT {} FROM webhook WHERE ($1 OR api_key_id IS NOT DISTINCT FROM $2) ORDER BY created_at DESC"#,
+        WEBHOOK_COLUMNS
+    );
+
+    match sqlx::query_as::<_, Webhook>(&query)
+        .bind(scope.is_all())
+        .bind(scope.api_key_id())
+        .fetch_all(db)
+        .await
+    {
+        Ok(r) => Ok(r),
+        Err(e) => {
+            tracing::error!("{}", e);
+            Err(anyhow::Error::msg("Error: Fail to fetch webhooks"))
+        }
+    }
+}
+
+// Deletes a webhook and its delivery log, returns false when it doesn't exist within the scope
+pub async fn delete_webhook(
+    db: &PgPool,
+    id: Uuid,
+    scope: OwnerScope,
+) -> Result<bool, anyhow::Error> {
+    match sqlx::query(
+        r#"DELETE FROM webhook WHERE id = $1 AND ($2 OR api_key_id IS NOT DISTINCT FROM $3)"#,
+    )
+    .bind(id)
+    .bind(scope.is_all())
+    .bind(scope.api_key_id())
+    .execute(db)
+    .await
+    {
+        Ok(r) => Ok(r.rows_affected() > 0),
+        Err(e) => {
+            tracing::error!("{}", e);
+            Err(anyhow::Error::msg("Error: Fail to delete webhook"))
+        }
+    }
+}
+
+// Enables or disables a webhook by hand, enabling also resets its failure streak
+pub async fn set_webhook_enabled(
+    db: &PgPool,
+    id: Uuid,
+    enabled: bool,
+    scope: OwnerScope,
+) -> Result<Option<Webhook>, anyhow::Error> {
+    let update_sql = format!(
+        r#"
+    UPDATE webhook
+    SET enabled = $2,
+        consecutive_failures = CASE WHEN $2 THEN 0 ELSE consecutive_failures END,
+        disabled_at = CASE WHEN $2 THEN NULL ELSE $3 END
+    WHERE id = $1 AND ($4 OR api_key_id IS NOT DISTINCT FROM $5)
+    RETURNING {}
+    "#,
+        WEBHOOK_COLUMNS
+    );
+
+    match sqlx::query_as::<_, Webhook>(&update_sql)
+        .bind(id)
+        .bind(enabled)
+        .bind(Utc::now())
+        .bind(scope.is_all())
+        .bind(scope.api_key_id())
+        .fetch_optional(db)
+        .await
+    {
+        Ok(r) => Ok(r),
+        Err(e) => {
+            tracing::error!("{}", e);
+            Err(anyhow::Error::msg("Error: Fail to update webhook"))
+        }
+    }
+}
+
+// Fetches the delivery log of a webhook within the scope, newest first
+pub async fn fetch_webhook_deliveries(
+    db: &PgPool,
+    webhook_id: Uuid,
+    limit: i64,
+    scope: OwnerScope,
+) -> Result<Vec<WebhookDelivery>, anyhow::Error> {
+    let query = r#"
+    SELECT d.id::text AS id, d.webhook_id::text AS webhook_id, d.event_type, d.payload, d.status, d.attempts,
+        d.next_attempt_at, d.last_status_code, d.last_error, d.created_at, d.delivered_at
+    FROM webhook_delivery d
+    JOIN webhook w ON w.id = d.webhook_id
+    WHERE d.webhook_id = $1 AND ($3 OR w.api_key_id IS NOT DISTINCT FROM $4)
+    ORDER BY d.created_at DESC
+    LIMIT $2
+    "#;
+
+    match sqlx::query_as::<_, WebhookDelivery>(query)
+        .bind(webhook_id)
+        .bind(limit)
+        .bind(scope.is_all())
+        .bind(scope.api_key_id())
+        .fetch_all(db)
+        .await
+    {
+        Ok(r) => Ok(r),
+        Err(e) => {
+            tracing::error!("{}", e);
+            Err(anyhow::Error::msg(
+                "Error: Fail to fetch webhook deliveries",
+            ))
+        }
+    }
+}
+
+// Queues one delivery per enabled webhook subscribed to the event. Creator filters fall back to the creator stored
+// on the token when the event itself doesn't carry it.
+#[tracing::instrument(skip_all, fields(%event_type, %mint))]
+pub async fn enqueue_webhook_deliveries(
+    db: Arc<PgPool>,
+    event_type: &str,
+    mint: &str,
+    creator: Option<&str>,
+    payload: &str,
+) {
+    let insert_sql = r#"
+    INSERT INTO webhook_delivery(id, webhook_id, event_type, payload, status, attempts, next_attempt_at, created_at)
+    SELECT gen_random_uuid(), w.id, $1, $4, 'pending', 0, $5, $5
+    FROM webhook w
+    LEFT JOIN token t ON t.contract_address = $2
+    WHERE w.enabled
+        AND $1 = ANY(w.event_types)
+        AND (w.mints IS NULL OR $2 = ANY(w.mints))
+        AND (w.creators IS NULL OR COALESCE($3, t.creator_address) = ANY(w.creators))
+    "#;
+
+    if let Err(err) = sqlx::query(insert_sql)
+        .bind(event_type)
+        .bind(mint)
+        .bind(creator)
+        .bind(payload)
+        .bind(Utc::now())
+        .execute(&*db)
+        .await
+    {
+        metrics()
+            .db_errors
+            .with_label_values(&["enqueue_webhook_deliveries"])
+            .inc();
+        tracing::error!(error = ?err, "Failed to enqueue webhook deliveries");
+    }
+}
+
+// Claims the due deliveries of enabled webhooks. Instead of a status flag the claim pushes `next_attempt_at` out by
+// the lease, so deliveries held by a worker that died are picked up again once the lease runs out.
+pub async fn claim_due_deliveries(
+    db: &PgPool,
+    limit: i64,
+    lease_secs: i64,
+) -> Result<Vec<DueDelivery>, anyhow::Error> {
+    let query = r#"
+    UPDATE webhook_delivery d
+    SET next_attempt_at = $2 + make_interval(secs => $3)
+    FROM webhook w
+    WHERE w.id = d.webhook_id
+        AND d.id IN (
+            SELECT pending.id
+            FROM webhook_delivery pending
+            JOIN webhook hook ON hook.id = pending.webhook_id
+            WHERE pending.status = 'pending' AND pending.next_attempt_at <= $2 AND hook.enabled
+            ORDER BY pending.next_attempt_at
+            LIMIT $1
+            FOR UPDATE OF pending SKIP LOCKED
+        )
+    RETURNING d.id, d.webhook_id, d.event_type, d.payload, d.attempts, w.url, w.secret
+    "#;
+
+    match sqlx::query_as::<_, DueDelivery>(query)
+        .bind(limit)
+        .bind(Utc::now())
+        .bind(lease_secs as f64)
+        .fetch_all(db)
+        .await
+    {
+        Ok(r) => Ok(r),
+        Err(e) => {
+            tracing::error!("{}", e);
+            Err(anyhow::Error::msg(
+                "Error: Fail to claim webhook deliveries",
+            ))
+        }
+    }
+}
+
+// Marks a delivery as delivered and resets the failure streak of its webhook
+pub async fn mark_delivery_succeeded(db: Arc<PgPool>, delivery: &DueDelivery, status_code: i32) {
+    let result = async {
+        let mut tx = db.begin().await?;
+
+        sqlx::query(
+            r#"
+        UPDATE webhook_delivery
+        SET status = 'delivered', attempts = attempts + 1, last_status_code = $2, last_error = NULL, delivered_at = $3
+        WHERE id = $1
+        "#,
+        )
+        .bind(delivery.id)
+        .bind(status_code)
+        .bind(Utc::now())
+        .execute(&mut tx)
+        .await?;
+
+        sqlx::query(r#"UPDATE webhook SET consecutive_failures = 0 WHERE id = $1"#)
+            .bind(delivery.webhook_id)
+            .execute(&mut tx)
+            .await?;
+
+        tx.commit().await
+    }
+    .await;
+
+    if let Err(err) = result {
+        metrics()
+            .db_errors
+            .with_label_values(&["mark_delivery_succeeded"])
+            .inc();
+        tracing::error!(error = ?err, "Failed to mark webhook delivery as delivered");
+    }
+}
+
+// Records a failed attempt. The delivery is retried at `retry_at` or given up when there is none, and the webhook
+// is disabled once its failure streak reaches `disable_after_failures`.
+pub async fn mark_delivery_failed(
+    db: Arc<PgPool>,
+    delivery: &DueDelivery,
+    status_code: Option<i32>,
+    error: &str,
+    retry_at: Option<DateTime<Utc>>,
+    disable_after_failures: i32,
+) {
+    let now = Utc::now();
+
+    let result = async {
+        let mut tx = db.begin().await?;
+
+        sqlx::query(
+            r#"
+        UPDATE webhook_delivery
+        SET status = CASE WHEN $4::timestamptz IS NULL THEN 'failed' ELSE 'pending' END,
+            attempts = attempts + 1,
+            last_status_code = $2,
+            last_error = $3,
+            next_attempt_at = COALESCE($4, next_attempt_at)
+        WHERE id = $1
+        "#,
+        )
+        .bind(delivery.id)
+        .bind(status_code)
+        .bind(error)
+        .bind(retry_at)
+        .execute(&mut tx)
+        .await?;
+
+        let disabled = sqlx::query_scalar::<_, bool>(
+            r#"
+        UPDATE webhook
+        SET consecutive_failures = consecutive_failures + 1,
+            enabled = enabled AND consecutive_failures + 1 < $2,
+            disabled_at = CASE WHEN enabled AND consecutive_failures + 1 >= $2 THEN $3 ELSE disabled_at END
+        WHERE id = $1
+        RETURNING NOT enabled
+        "#,
+        )
+        .bind(delivery.webhook_id)
+        .bind(disable_after_failures)
+        .bind(now)
+        .fetch_optional(&mut tx)
+        .await?
+        .unwrap_or(false);
+
+        tx.commit().await?;
+
+        Ok::<bool, sqlx::Error>(disabled)
+    }
+    .await;
+
+    match result {
+        Ok(true) => {
+            tracing::warn!(webhook_id = %delivery.webhook_id, "Webhook disabled after repeated failures")
+        }
+        Ok(false) => {}
+        Err(err) => {
+            metrics()
+                .db_errors
+                .with_label_values(&["mark_delivery_failed"])
+                .inc();
+            tracing::error!(error = ?err, "Failed to record webhook delivery failure");
+        }
+    }
+}
+
+// Deletes up to `limit` delivered or given up deliveries created before `before`, returns how many were deleted
+pub async fn prune_webhook_deliveries(
+    db: &PgPool,
+    before: DateTime<Utc>,
+    limit: i64,
+) -> Result<u64, anyhow::Error> {
+    let delete_sql = r#"
+    DELETE FROM webhook_delivery
+    WHERE id IN (
+        SELECT id FROM webhook_delivery
+        WHERE status <> 'pending' AND created_at < $1
+        LIMIT $2
+    )
+    "#;
+
+    match sqlx::query(delete_sql)
+        .bind(before)
+        .bind(limit)
+        .execute(db)
+        .await
+    {
+        Ok(r) => Ok(r.rows_affected()),
+        Err(e) => {
+            tracing::error!("{}", e);
+            Err(anyhow::Error::msg(
+                "Error: Fail to prune webhook deliveries",
+            ))
+        }
+    }
+}
@@ -0,0 +1,190 @@
+use std::{
+    collections::BTreeMap,
+    hash::{BuildHasher, RandomState},
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use carbon_core::{
+    error::{CarbonResult, Error as CarbonError},
+    instruction::InstructionProcessorInputType,
+    metrics::MetricsCollection,
+    processor::Processor,
+};
+use carbon_pumpfun_decoder::instructions::PumpfunInstruction;
+use prometheus::IntGauge;
+use tokio::{
+    sync::mpsc::{channel, Sender},
+    task::JoinHandle,
+};
+
+use crate::{
+    checkpoint::CheckpointTracker, metrics::metrics,
+    pumpfun_processor::PumpfunInstructionProcessor, sinks::SinkMessage,
+};
+
+struct Job {
+    data: InstructionProcessorInputType<PumpfunInstruction>,
+    metrics: Arc<MetricsCollection>,
+    slot: u64,
+}
+
+// Events dispatched but not processed yet, counted per slot with the first signature of the slot
+#[derive(Debug, Clone, Default)]
+struct InFlightSlots {
+    slots: Arc<Mutex<BTreeMap<u64, (usize, String)>>>,
+}
+
+impl InFlightSlots {
+    // The replay progress of the checkpoint is updated under the same lock, so it sees the events in the order they
+    // were dispatched and completed
+    fn start(&self, slot: u64, signature: String, checkpoint: &CheckpointTracker) {
+        let mut slots = self.slots.lock().unwrap();
+
+        checkpoint.dispatched(slot, &signature);
+
+        slots.entry(slot).or_insert((0, signature)).0 += 1;
+    }
+
+    // Marks an event of the slot as processed. Returns the slots no earlier event is waiting on anymore, lowest first.
+    fn finish(&self, slot: u64, checkpoint: &CheckpointTracker) -> Vec<(u64, String)> {
+        let mut slots = self.slots.lock().unwrap();
+
+        if let Some((pending, _)) = slots.get_mut(&slot) {
+            *pending -= 1;
+        }
+
+        let mut done = Vec::new();
+
+        while let Some(entry) = slots.first_entry() {
+            if entry.get().0 > 0 {
+                break;
+            }
+
+            let (slot, (_, signature)) = entry.remove_entry();
+            done.push((slot, signature));
+        }
+
+        if let Some((slot, _)) = done.last() {
+            checkpoint.completed(*slot);
+        }
+
+        done
+    }
+}
+
+// Fans the decoded events out to a pool of processors partitioned by mint, so unrelated tokens are processed in
+// parallel while the events of a token stay in order. Every worker owns its processor, the state map is shared.
+// The checkpoint only moves past a slot once every event up to it has been processed, whichever worker it went to.
+pub struct PartitionedProcessor {
+    workers: Vec<Sender<Job>>,
+    queue_depths: Vec<IntGauge>,
+    hasher: RandomState,
+    in_flight: InFlightSlots,
+    checkpoint: CheckpointTracker,
+}
+
+impl PartitionedProcessor {
+    // Starts `workers` processors made by `build`, which are given no checkpoint of their own. The completed slots are
+    // also sent to the event sink, after the events the processors emitted for them. The returned handles finish once
+    // the pipeline has dropped the dispatcher and the queued events are processed.
+    pub fn spawn<F: FnMut(usize) -> PumpfunInstructionProcessor>(
+        workers: usize,
+        queue_size: usize,
+        checkpoint: CheckpointTracker,
+        sink: Option<Sender<SinkMessage>>,
+        mut build: F,
+    ) -> (Self, Vec<JoinHandle<()>>) {
+        let in_flight = InFlightSlots::default();
+
+        let mut senders = Vec::new();
+        let mut queue_depths = Vec::new();
+        let mut handles = Vec::new();
+
+        for worker in 0..workers.max(1) {
+            let (tx, mut rx) = channel::<Job>(queue_size.max(1));
+            let mut processor = build(worker);
+            let queue_depth = metrics()
+                .processor_queue_depth
+                .with_label_values(&[&worker.to_string()]);
+            let in_flight = in_flight.clone();
+            let checkpoint = checkpoint.clone();
+            let sink = sink.clone();
+
+            senders.push(tx);
+            queue_depths.push(queue_depth.clone());
+
+            //Spawn a new thread that processes the events of the mints hashed to this worker, in the order they came
+            handles.push(tokio::spawn(async move {
+                while let Some(job) = rx.recv().await {
+                    queue_depth.dec();
+
+                    if let Err(err) = processor.process(job.data, job.metrics).await {
+                        tracing::error!(worker, "Failed to process event: {:?}", err);
+                    }
+
+                    for (slot, signature) in in_flight.finish(job.slot, &checkpoint) {
+                        if let Some(sink) = &sink {
+                            let _ = sink
+                                .send(SinkMessage::SlotCompleted(slot, signature.clone()))
+                                .await;
+                        }
+
+                        checkpoint.observe(slot, signature).await;
+                    }
+                }
+            }));
+        }
+
+        let dispatcher = Self {
+            workers: senders,
+            queue_depths,
+            hasher: RandomState::new(),
+            in_flight,
+            checkpoint,
+        };
+
+        (dispatcher, handles)
+    }
+
+    // Events of the same mint always go to the same worker, those without one are spread by signature
+    fn partition(&self, instruction: &PumpfunInstruction, signature: &str) -> usize {
+        let hash = match instruction {
+            PumpfunInstruction::CreateEvent(event) => self.hasher.hash_one(event.mint),
+            PumpfunInstruction::TradeEvent(event) => self.hasher.hash_one(event.mint),
+            PumpfunInstruction::CompleteEvent(event) => self.hasher.hash_one(event.mint),
+            _ => self.hasher.hash_one(signature),
+        };
+
+        hash as usize % self.workers.len()
+    }
+}
+
+#[async_trait]
+impl Processor for PartitionedProcessor {
+    type InputType = InstructionProcessorInputType<PumpfunInstruction>;
+
+    async fn process(
+        &mut self,
+        data: Self::InputType,
+        metrics: Arc<MetricsCollection>,
+    ) -> CarbonResult<()> {
+        let slot = data.0.transaction_metadata.slot;
+        let signature = data.0.transaction_metadata.signature.to_string();
+
+        let worker = self.partition(&data.1.data, &signature);
+
+        self.in_flight.start(slot, signature, &self.checkpoint);
+        self.queue_depths[worker].inc();
+
+        // Waits while the queue of the worker is full, a busy token slows the pipeline down instead of piling up
+        self.workers[worker]
+            .send(Job {
+                data,
+                metrics,
+                slot,
+            })
+            .await
+            .map_err(|_| CarbonError::Custom(format!("Processor worker {} stopped", worker)))
+    }
+}
@@ -0,0 +1,386 @@
+use carbon_pumpfun_decoder::instructions::{
+    complete_event::CompleteEvent, create_event::CreateEvent, trade_event::TradeEvent,
+};
+use serde::{Deserialize, Serialize};
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use sqlx::types::chrono::{DateTime, Utc};
+use tokio::sync::{broadcast, mpsc::Sender};
+
+use crate::{
+    bonding_curve::price_sol, config::EventEncoding, hooks::Hooks, sinks::SinkMessage,
+    types::BondStatus,
+};
+
+// Bumped whenever a field is removed or changes meaning. Adding optional fields keeps the version.
+pub const EVENT_SCHEMA_VERSION: u16 = 1;
+
+// Envelope of every decoded Pump.fun event published to the external sinks.
+//
+// {
+//   "version": 1,
+//   "id": "<signature>:<stack_height>:<index>",   unique per event, consumers de-duplicate on it
+//   "slot": 312345678,
+//   "signature": "<transaction signature>",
+//   "mint": "<token mint>",
+//   "emitted_at": "2025-01-01T00:00:00Z",         when the indexer processed the event
+//   "type": "create" | "trade" | "complete" | "status_change",
+//   "data": { ...fields of the event type below... }
+// }
+//
+// The envelope is serialized as JSON, or as MessagePack with the same field names when EVENT_ENCODING=msgpack.
+// Delivery is at-least-once, the same `id` can be published more than once after a restart.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventEnvelope {
+    pub version: u16,
+    pub id: String,
+    pub slot: u64,
+    pub signature: String,
+    pub mint: String,
+    pub emitted_at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub payload: EventPayload,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum EventPayload {
+    // A new token was launched
+    Create {
+        name: String,
+        symbol: String,
+        uri: String,
+        creator: String,
+        bonding_curve: String,
+    },
+    // A buy or sell on the bonding curve
+    Trade(TradeData),
+    // The bonding curve completed and the token graduates
+    Complete {
+        user: String,
+        bonding_curve: String,
+        timestamp: i64,
+    },
+    // The bond status stored for the token changed
+    StatusChange {
+        status: BondStatus,
+    },
+}
+
+// Every field of the decoded TradeEvent, amounts are raw (lamports and token base units), plus the values derived
+// from the reserves and the SOL price at the time the trade was processed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TradeData {
+    pub user: String,
+    pub is_buy: bool,
+    pub sol_amount: u64,
+    pub token_amount: u64,
+    pub timestamp: i64,
+    pub virtual_sol_reserves: u64,
+    pub virtual_token_reserves: u64,
+    pub real_sol_reserves: u64,
+    pub real_token_reserves: u64,
+    pub fee_recipient: String,
+    pub fee_basis_points: u64,
+    pub fee: u64,
+    // Token price in SOL and USD from the virtual reserves after the trade
+    pub price_sol: f64,
+    pub price_usd: f64,
+    pub sol_amount_usd: f64,
+    pub market_cap_usd: i64,
+}
+
+impl EventEnvelope {
+    pub fn new(
+        id: String,
+        slot: u64,
+        signature: &str,
+        mint: String,
+        payload: EventPayload,
+    ) -> Self {
+        Self {
+            version: EVENT_SCHEMA_VERSION,
+            id,
+            slot,
+            signature: signature.to_string(),
+            mint,
+            emitted_at: Utc::now(),
+            payload,
+        }
+    }
+
+    pub fn trade(
+        id: String,
+        event: &TradeEvent,
+        slot: u64,
+        signature: &str,
+        sol_price_usd: f64,
+        market_cap_usd: i64,
+    ) -> Self {
+        let price_sol = price_sol(event.virtual_sol_reserves, event.virtual_token_reserves);
+
+        Self::new(
+            id,
+            slot,
+            signature,
+            event.mint.to_string(),
+            EventPayload::Trade(TradeData {
+                user: event.user.to_string(),
+                is_buy: event.is_buy,
+                sol_amount: event.sol_amount,
+                token_amount: event.token_amount,
+                timestamp: event.timestamp,
+                virtual_sol_reserves: event.virtual_sol_reserves,
+                virtual_token_reserves: event.virtual_token_reserves,
+                real_sol_reserves: event.real_sol_reserves,
+                real_token_reserves: event.real_token_reserves,
+                fee_recipient: event.fee_recipient.to_string(),
+                fee_basis_points: event.fee_basis_points,
+                fee: event.fee,
+                price_sol,
+                price_usd: price_sol * sol_price_usd,
+                sol_amount_usd: event.sol_amount as f64 / LAMPORTS_PER_SOL as f64 * sol_price_usd,
+                market_cap_usd,
+            }),
+        )
+    }
+
+    pub fn encode(&self, encoding: EventEncoding) -> Result<Vec<u8>, anyhow::Error> {
+        Ok(match encoding {
+            EventEncoding::Json => serde_json::to_vec(self)?,
+            EventEncoding::MessagePack => rmp_serde::to_vec_named(self)?,
+        })
+    }
+
+    // Accepts both encodings, a JSON envelope always starts with '{' which is never the first byte of a MessagePack map
+    pub fn decode(bytes: &[u8]) -> Result<Self, anyhow::Error> {
+        Ok(match bytes.first() {
+            Some(b'{') => serde_json::from_slice(bytes)?,
+            _ => rmp_serde::from_slice(bytes)?,
+        })
+    }
+
+    // Position of the event within its transaction, packed from the stack height and instruction index of the id. None
+    // for an id that doesn't have them.
+    pub fn event_index(&self) -> Option<i64> {
+        let mut parts = self.id.rsplitn(3, ':');
+
+        let index: i64 = parts.next()?.parse().ok()?;
+        let stack_height: i64 = parts.next()?.parse().ok()?;
+
+        Some((index << 8) | (stack_height & 0xff))
+    }
+
+    pub fn event_type(&self) -> &'static str {
+        match self.payload {
+            EventPayload::Create { .. } => "create",
+            EventPayload::Trade { .. } => "trade",
+            EventPayload::Complete { .. } => "complete",
+            EventPayload::StatusChange { .. } => "status_change",
+        }
+    }
+}
+
+// Builds the envelopes of the decoded events and hands them to the sink worker, the live subscribers (GraphQL
+// subscriptions) and the hooks of an embedding service, does nothing when none of them is listening
+#[derive(Debug, Clone, Default)]
+pub struct EventEmitter {
+    sender: Option<Sender<SinkMessage>>,
+    live: Option<broadcast::Sender<EventEnvelope>>,
+    hooks: Hooks,
+}
+
+impl EventEmitter {
+    pub(crate) fn new(
+        sender: Option<Sender<SinkMessage>>,
+        live: Option<broadcast::Sender<EventEnvelope>>,
+    ) -> Self {
+        Self {
+            sender,
+            live,
+            hooks: Hooks::default(),
+        }
+    }
+
+    pub fn with_hooks(mut self, hooks: Hooks) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
+    fn has_live_subscribers(&self) -> bool {
+        self.live
+            .as_ref()
+            .map(|live| live.receiver_count() > 0)
+            .unwrap_or(false)
+    }
+
+    fn is_active(&self) -> bool {
+        self.sender.is_some() || self.has_live_subscribers() || !self.hooks.is_empty()
+    }
+
+    // Waits while the sink queue is full, a slow broker slows the processing down instead of piling up events
+    pub async fn emit(&self, envelope: EventEnvelope) {
+        self.hooks.call(&envelope);
+
+        if self.has_live_subscribers() {
+            if let Some(live) = &self.live {
+                let _ = live.send(envelope.clone());
+            }
+        }
+
+        if let Some(sender) = &self.sender {
+            let _ = sender.send(SinkMessage::Event(envelope)).await;
+        }
+    }
+
+    pub async fn create(&self, id: &str, event: &CreateEvent, slot: u64, signature: &str) {
+        if !self.is_active() {
+            return;
+        }
+
+        self.emit(EventEnvelope::new(
+            id.to_string(),
+            slot,
+            signature,
+            event.mint.to_string(),
+            EventPayload::Create {
+                name: event.name.clone(),
+                symbol: event.symbol.clone(),
+                uri: event.uri.clone(),
+                creator: event.user.to_string(),
+                bonding_curve: event.bonding_curve.to_string(),
+            },
+        ))
+        .await;
+    }
+
+    pub async fn complete(&self, id: &str, event: &CompleteEvent, slot: u64, signature: &str) {
+        if !self.is_active() {
+            return;
+        }
+
+        self.emit(EventEnvelope::new(
+            id.to_string(),
+            slot,
+            signature,
+            event.mint.to_string(),
+            EventPayload::Complete {
+                user: event.user.to_string(),
+                bonding_curve: event.bonding_curve.to_string(),
+                timestamp: event.timestamp,
+            },
+        ))
+        .await;
+    }
+
+    pub async fn status_change(
+        &self,
+        id: &str,
+        mint: String,
+        status: BondStatus,
+        slot: u64,
+        signature: &str,
+    ) {
+        self.emit(EventEnvelope::new(
+            format!("{}:status", id),
+            slot,
+            signature,
+            mint,
+            EventPayload::StatusChange { status },
+        ))
+        .await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trade() -> EventEnvelope {
+        EventEnvelope::new(
+            "signature:2:5".to_string(),
+            42,
+            "signature",
+            "mint".to_string(),
+            EventPayload::Trade(TradeData {
+                user: "wallet".to_string(),
+                is_buy: true,
+                sol_amount: 1_500_000_000,
+                token_amount: 35_000_000_000,
+                timestamp: 1_700_000_000,
+                virtual_sol_reserves: 30_000_000_000,
+                virtual_token_reserves: 1_073_000_000_000_000,
+                real_sol_reserves: 0,
+                real_token_reserves: 793_100_000_000_000,
+                fee_recipient: "fee".to_string(),
+                fee_basis_points: 100,
+                fee: 10_000,
+                price_sol: 0.000000028,
+                price_usd: 0.0000042,
+                sol_amount_usd: 225.0,
+                market_cap_usd: 4_200,
+            }),
+        )
+    }
+
+    fn status_change() -> EventEnvelope {
+        EventEnvelope::new(
+            "signature:1:0:status".to_string(),
+            43,
+            "signature",
+            "mint".to_string(),
+            EventPayload::StatusChange {
+                status: BondStatus::Graduated,
+            },
+        )
+    }
+
+    #[test]
+    fn round_trips_both_encodings() {
+        for envelope in [trade(), status_change()] {
+            for encoding in [EventEncoding::Json, EventEncoding::MessagePack] {
+                let bytes = envelope.encode(encoding).unwrap();
+                let decoded = EventEnvelope::decode(&bytes).unwrap();
+
+                assert_eq!(
+                    serde_json::to_value(&decoded).unwrap(),
+                    serde_json::to_value(&envelope).unwrap(),
+                    "{:?}",
+                    encoding
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn encodes_the_documented_json_layout() {
+        let bytes = trade().encode(EventEncoding::Json).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+
+        assert_eq!(json["version"], EVENT_SCHEMA_VERSION);
+        assert_eq!(json["type"], "trade");
+        assert_eq!(json["mint"], "mint");
+        assert_eq!(json["slot"], 42);
+        assert_eq!(json["data"]["user"], "wallet");
+        assert_eq!(json["data"]["market_cap_usd"], 4_200);
+
+        let status = status_change().encode(EventEncoding::Json).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&status).unwrap();
+
+        assert_eq!(json["type"], "status_change");
+        assert_eq!(json["data"]["status"], "Graduated");
+    }
+
+    #[test]
+    fn rejects_bytes_that_are_not_an_envelope() {
+        assert!(EventEnvelope::decode(b"{\"version\":1}").is_err());
+        assert!(EventEnvelope::decode(&[0xc1]).is_err());
+        assert!(EventEnvelope::decode(&[]).is_err());
+    }
+
+    #[test]
+    fn packs_the_event_index_from_the_id() {
+        assert_eq!(trade().event_index(), Some((5 << 8) | 2));
+        assert_eq!(trade().event_type(), "trade");
+        assert_eq!(status_change().event_index(), None);
+    }
+}
@@ -0,0 +1,412 @@
+use std::{collections::HashMap, str::FromStr, sync::Arc};
+
+use solana_client::{
+    nonblocking::rpc_client::RpcClient, rpc_client::GetConfirmedSignaturesForAddress2Config,
+    rpc_config::RpcTransactionConfig,
+};
+use solana_pubkey::Pubkey;
+use solana_sdk::{
+    commitment_config::CommitmentConfig, message::VersionedMessage, signature::Signature,
+    system_program,
+};
+use solana_transaction_status::{
+    option_serializer::OptionSerializer, TransactionStatusMeta, UiTransactionEncoding,
+};
+use sqlx::PgPool;
+use tokio::sync::{
+    mpsc::{self, error::TrySendError, Receiver, UnboundedReceiver},
+    Mutex,
+};
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
+
+use crate::{
+    checkpoint::SeenEvents,
+    config::FundingConfig,
+    db::funding::{save_wallet_funding, wallet_funding_exists},
+    types::WalletFunding,
+};
+
+const SIGNATURES_PAGE_LIMIT: usize = 1000;
+
+// Discriminant of SystemInstruction::Transfer in its bincode encoding
+const SYSTEM_TRANSFER_TAG: [u8; 4] = [2, 0, 0, 0];
+
+// What the instruction processor tells the funding resolver about a trader
+#[derive(Debug, Clone)]
+pub enum FundingObservation {
+    // A system transfer into the wallet seen inside an indexed transaction
+    Transfer {
+        funder: String,
+        wallet: String,
+        slot: u64,
+        signature: String,
+    },
+    // A wallet that traded, its funding source is looked up over RPC if unknown
+    Wallet(String),
+}
+
+// Returns the (from, to, lamports) of every top level system transfer in a message
+pub fn extract_system_transfers(
+    message: &VersionedMessage,
+    meta: Option<&TransactionStatusMeta>,
+) -> Vec<(Pubkey, Pubkey, u64)> {
+    // Address lookup table accounts come after the static keys, writable first
+    let mut account_keys = message.static_account_keys().to_vec();
+
+    if let Some(meta) = meta {
+        account_keys.extend(meta.loaded_addresses.writable.iter().cloned());
+        account_keys.extend(meta.loaded_addresses.readonly.iter().cloned());
+    }
+
+    message
+        .instructions()
+        .iter()
+        .filter_map(|instruction| {
+            let program_id = account_keys.get(instruction.program_id_index as usize)?;
+
+            if *program_id != system_program::ID
+                || instruction.data.len() != 12
+                || instruction.data[..4] != SYSTEM_TRANSFER_TAG
+            {
+                return None;
+            }
+
+            let from = account_keys.get(*instruction.accounts.first()? as usize)?;
+            let to = account_keys.get(*instruction.accounts.get(1)? as usize)?;
+            let lamports = u64::from_le_bytes(instruction.data[4..12].try_into().ok()?);
+
+            Some((*from, *to, lamports))
+        })
+        .collect()
+}
+
+// Finds the first system transfer into the wallet by walking its history back to the oldest transaction
+async fn lookup_first_funding(
+    rpc: &RpcClient,
+    wallet: &str,
+    max_pages: usize,
+) -> Option<(String, String, u64)> {
+    let address = Pubkey::from_str(wallet).ok()?;
+
+    let mut before = None;
+    let mut oldest = None;
+
+    for _ in 0..max_pages {
+        let page = rpc
+            .get_signatures_for_address_with_config(
+                &address,
+                GetConfirmedSignaturesForAddress2Config {
+                    before,
+                    until: None,
+                    limit: Some(SIGNATURES_PAGE_LIMIT),
+                    commitment: Some(CommitmentConfig::confirmed()),
+                },
+            )
+            .await
+            .map_err(|err| tracing::error!(error = ?err, %wallet, "Failed to fetch wallet signatures"))
+            .ok()?;
+
+        let Some(last) = page.last() else {
+            break;
+        };
+
+        oldest = Signature::from_str(&last.signature).ok();
+        before = oldest;
+
+        if page.len() < SIGNATURES_PAGE_LIMIT {
+            break;
+        }
+
+        // Still more history after the last allowed page, this is not a fresh wallet
+        oldest = None;
+    }
+
+    let signature = oldest?;
+
+    let transaction = rpc
+        .get_transaction_with_config(
+            &signature,
+            RpcTransactionConfig {
+                encoding: Some(UiTransactionEncoding::Base64),
+                commitment: Some(CommitmentConfig::confirmed()),
+                max_supported_transaction_version: Some(0),
+            },
+        )
+        .await
+        .map_err(|err| tracing::error!(error = ?err, %signature, "Failed to fetch funding transaction"))
+        .ok()?;
+
+    let decoded = transaction.transaction.transaction.decode()?;
+
+    let mut account_keys = decoded.message.static_account_keys().to_vec();
+
+    if let Some(OptionSerializer::Some(loaded)) = transaction
+        .transaction
+        .meta
+        .as_ref()
+        .map(|meta| meta.loaded_addresses.clone())
+    {
+        for key in loaded.writable.iter().chain(loaded.readonly.iter()) {
+            if let Ok(key) = Pubkey::from_str(key) {
+                account_keys.push(key);
+            }
+        }
+    }
+
+    decoded.message.instructions().iter().find_map(|instruction| {
+        let program_id = account_keys.get(instruction.program_id_index as usize)?;
+        let from = account_keys.get(*instruction.accounts.first()? as usize)?;
+        let to = account_keys.get(*instruction.accounts.get(1)? as usize)?;
+
+        (*program_id == system_program::ID
+            && instruction.data.len() == 12
+            && instruction.data[..4] == SYSTEM_TRANSFER_TAG
+            && *to == address)
+            .then(|| (from.to_string(), signature.to_string(), transaction.slot))
+    })
+}
+
+// Resolves the funding source of the queued wallets one at a time until the queue closes or shutdown
+async fn run_lookup_worker(
+    db: Arc<PgPool>,
+    rpc: Arc<RpcClient>,
+    queue: Arc<Mutex<Receiver<String>>>,
+    max_pages: usize,
+    shutdown: CancellationToken,
+) {
+    loop {
+        let wallet = tokio::select! {
+            _ = shutdown.cancelled() => break,
+            wallet = async { queue.lock().await.recv().await } => match wallet {
+                Some(wallet) => wallet,
+                None => break,
+            },
+        };
+
+        if wallet_funding_exists(db.clone(), &wallet).await {
+            continue;
+        }
+
+        match lookup_first_funding(&rpc, &wallet, max_pages).await {
+            Some((funder, signature, slot)) => {
+                save_wallet_funding(
+                    db.clone(),
+                    wallet,
+                    Some(funder),
+                    Some(signature),
+                    Some(slot as i64),
+                )
+                .await
+            }
+            // Remember that the lookup came back empty so it isn't retried on every trade
+            None => save_wallet_funding(db.clone(), wallet, None, None, None).await,
+        }
+    }
+}
+
+// Consumes funding observations from the processor. Transfers seen in indexed transactions are stored directly,
+// unknown wallets are queued for a fixed number of workers that resolve them over RPC. The queue is bounded and the
+// wallets already handled are remembered in a bounded set.
+pub async fn run_funding_resolver(
+    db: Arc<PgPool>,
+    rpc_url: String,
+    config: FundingConfig,
+    mut rx: UnboundedReceiver<FundingObservation>,
+    shutdown: CancellationToken,
+) {
+    let rpc = Arc::new(RpcClient::new_with_commitment(
+        rpc_url,
+        CommitmentConfig::confirmed(),
+    ));
+    let (lookup_tx, lookup_rx) = mpsc::channel(config.lookup_queue_size.max(1));
+    let lookup_rx = Arc::new(Mutex::new(lookup_rx));
+    let tracker = TaskTracker::new();
+    let mut known = SeenEvents::default();
+
+    if config.lookups_enabled {
+        for _ in 0..config.max_concurrent_lookups.max(1) {
+            tracker.spawn(run_lookup_worker(
+                db.clone(),
+                rpc.clone(),
+                lookup_rx.clone(),
+                config.max_signature_pages,
+                shutdown.clone(),
+            ));
+        }
+    }
+
+    loop {
+        let observation = tokio::select! {
+            _ = shutdown.cancelled() => break,
+            observation = rx.recv() => match observation {
+                Some(observation) => observation,
+                None => break,
+            },
+        };
+
+        match observation {
+            FundingObservation::Transfer {
+                funder,
+                wallet,
+                slot,
+                signature,
+            } => {
+                known.insert(wallet.clone());
+
+                save_wallet_funding(
+                    db.clone(),
+                    wallet,
+                    Some(funder),
+                    Some(signature),
+                    Some(slot as i64),
+                )
+                .await;
+            }
+            FundingObservation::Wallet(wallet) => {
+                if !config.lookups_enabled || known.contains(&wallet) {
+                    continue;
+                }
+
+                match lookup_tx.try_send(wallet.clone()) {
+                    Ok(()) => {
+                        known.insert(wallet);
+                    }
+                    // Not remembered, so a later trade of the wallet queues it again
+                    Err(TrySendError::Full(_)) => {
+                        tracing::debug!(%wallet, "Funding lookup queue is full, skipping the wallet");
+                    }
+                    Err(TrySendError::Closed(_)) => break,
+                }
+            }
+        }
+    }
+
+    // Pending lookups are best effort, the workers stop on shutdown without draining the queue
+    drop(lookup_tx);
+    tracker.close();
+}
+
+fn find_root(parents: &mut HashMap<String, String>, wallet: &str) -> String {
+    let mut root = wallet.to_string();
+
+    while let Some(parent) = parents.get(&root) {
+        if *parent == root {
+            break;
+        }
+        root = parent.clone();
+    }
+
+    // Path compression
+    let mut current = wallet.to_string();
+    while current != root {
+        let next = parents.insert(current.clone(), root.clone()).unwrap_or(root.clone());
+        current = next;
+    }
+
+    root
+}
+
+// Links every wallet to its funder and returns a wallet -> cluster id map (union-find over the funding graph).
+// Funders above the fanout limit are treated as unrelated sources, otherwise every exchange withdrawal would
+// end up in one giant cluster.
+pub fn build_clusters(funding: &[WalletFunding], max_funder_fanout: usize) -> HashMap<String, String> {
+    let mut fanout: HashMap<&str, usize> = HashMap::new();
+
+    for edge in funding {
+        if let Some(funder) = &edge.funder {
+            *fanout.entry(funder.as_str()).or_insert(0) += 1;
+        }
+    }
+
+    let mut parents: HashMap<String, String> = HashMap::new();
+
+    for edge in funding {
+        let Some(funder) = &edge.funder else {
+            continue;
+        };
+
+        if fanout.get(funder.as_str()).copied().unwrap_or(0) > max_funder_fanout {
+            continue;
+        }
+
+        parents
+            .entry(edge.wallet.clone())
+            .or_insert_with(|| edge.wallet.clone());
+        parents
+            .entry(funder.clone())
+            .or_insert_with(|| funder.clone());
+
+        let wallet_root = find_root(&mut parents, &edge.wallet);
+        let funder_root = find_root(&mut parents, funder);
+
+        if wallet_root != funder_root {
+            parents.insert(wallet_root, funder_root);
+        }
+    }
+
+    let wallets: Vec<String> = parents.keys().cloned().collect();
+
+    wallets
+        .into_iter()
+        .map(|wallet| {
+            let root = find_root(&mut parents, &wallet);
+            (wallet, root)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edge(wallet: &str, funder: Option<&str>) -> WalletFunding {
+        WalletFunding {
+            wallet: wallet.to_string(),
+            funder: funder.map(String::from),
+        }
+    }
+
+    #[test]
+    fn links_wallets_funded_along_a_chain() {
+        let funding = [
+            edge("a", Some("root")),
+            edge("b", Some("a")),
+            edge("c", Some("b")),
+            edge("d", Some("other")),
+        ];
+
+        let clusters = build_clusters(&funding, 10);
+
+        assert_eq!(clusters["a"], clusters["root"]);
+        assert_eq!(clusters["b"], clusters["root"]);
+        assert_eq!(clusters["c"], clusters["root"]);
+        assert_eq!(clusters["d"], clusters["other"]);
+        assert_ne!(clusters["d"], clusters["a"]);
+    }
+
+    #[test]
+    fn skips_funders_above_the_fanout_limit() {
+        let funding = [
+            edge("a", Some("exchange")),
+            edge("b", Some("exchange")),
+            edge("c", Some("exchange")),
+            edge("d", Some("friend")),
+            edge("e", Some("friend")),
+        ];
+
+        let clusters = build_clusters(&funding, 2);
+
+        assert!(!clusters.contains_key("exchange"));
+        assert!(!clusters.contains_key("a"));
+        assert_eq!(clusters["d"], clusters["e"]);
+        assert_eq!(clusters["d"], clusters["friend"]);
+    }
+
+    #[test]
+    fn ignores_wallets_without_a_known_funder() {
+        let clusters = build_clusters(&[edge("a", None), edge("b", Some("a"))], 10);
+
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(clusters["a"], clusters["b"]);
+    }
+}
@@ -0,0 +1,216 @@
+use std::{collections::HashMap, hash::Hash, sync::Arc};
+
+use async_graphql::dataloader::Loader;
+use sqlx::{
+    types::chrono::{DateTime, Utc},
+    PgPool,
+};
+
+use crate::{
+    db::graphql::{
+        count_creators_tokens, fetch_tokens_by_address, fetch_tokens_candles, fetch_tokens_holders,
+        fetch_tokens_trades_page,
+    },
+    graphql::objects::{CandleObject, HoldingObject, TokenObject, TradeObject},
+};
+
+// Splits the keys of a batch by their arguments, the mints of every group are loaded with one query
+fn group_mints<K, G: Hash + Eq>(
+    keys: &[K],
+    mint: impl Fn(&K) -> &String,
+    group: impl Fn(&K) -> G,
+) -> HashMap<G, Vec<String>> {
+    let mut groups: HashMap<G, Vec<String>> = HashMap::new();
+
+    for key in keys {
+        groups
+            .entry(group(key))
+            .or_default()
+            .push(mint(key).clone());
+    }
+
+    groups
+}
+
+// Batches the token lookups of every trade/holding in a response into a single query
+pub struct TokenLoader {
+    pub db: Arc<PgPool>,
+}
+
+impl Loader<String> for TokenLoader {
+    type Value = TokenObject;
+    type Error = Arc<anyhow::Error>;
+
+    async fn load(&self, keys: &[String]) -> Result<HashMap<String, Self::Value>, Self::Error> {
+        let tokens = fetch_tokens_by_address(&self.db, keys)
+            .await
+            .map_err(Arc::new)?;
+
+        Ok(tokens
+            .into_iter()
+            .map(|token| (token.contract_address.clone(), token))
+            .collect())
+    }
+}
+
+// Batches the token counts of every creator in a response
+pub struct CreatorTokenCountLoader {
+    pub db: Arc<PgPool>,
+}
+
+impl Loader<String> for CreatorTokenCountLoader {
+    type Value = i64;
+    type Error = Arc<anyhow::Error>;
+
+    async fn load(&self, keys: &[String]) -> Result<HashMap<String, Self::Value>, Self::Error> {
+        let mut counts: HashMap<String, i64> = keys.iter().map(|key| (key.clone(), 0)).collect();
+
+        for (creator, count) in count_creators_tokens(&self.db, keys)
+            .await
+            .map_err(Arc::new)?
+        {
+            counts.insert(creator, count);
+        }
+
+        Ok(counts)
+    }
+}
+
+// A page of the trades of a token
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TradesPage {
+    pub mint: String,
+    pub offset: i64,
+    pub limit: i64,
+}
+
+// Batches the trade pages of every token in a response, one query per page
+pub struct TokenTradesLoader {
+    pub db: Arc<PgPool>,
+}
+
+impl Loader<TradesPage> for TokenTradesLoader {
+    type Value = Vec<TradeObject>;
+    type Error = Arc<anyhow::Error>;
+
+    async fn load(
+        &self,
+        keys: &[TradesPage],
+    ) -> Result<HashMap<TradesPage, Self::Value>, Self::Error> {
+        let mut pages: HashMap<TradesPage, Self::Value> =
+            keys.iter().map(|key| (key.clone(), Vec::new())).collect();
+
+        for ((offset, limit), mints) in
+            group_mints(keys, |key| &key.mint, |key| (key.offset, key.limit))
+        {
+            for trade in fetch_tokens_trades_page(&self.db, &mints, offset, limit)
+                .await
+                .map_err(Arc::new)?
+            {
+                let page = TradesPage {
+                    mint: trade.contract_address.clone(),
+                    offset,
+                    limit,
+                };
+
+                pages.entry(page).or_default().push(trade);
+            }
+        }
+
+        Ok(pages)
+    }
+}
+
+// The largest holders of a token
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TopHolders {
+    pub mint: String,
+    pub limit: i64,
+}
+
+// Batches the holders of every token in a response, one query per limit
+pub struct TokenHoldersLoader {
+    pub db: Arc<PgPool>,
+}
+
+impl Loader<TopHolders> for TokenHoldersLoader {
+    type Value = Vec<HoldingObject>;
+    type Error = Arc<anyhow::Error>;
+
+    async fn load(
+        &self,
+        keys: &[TopHolders],
+    ) -> Result<HashMap<TopHolders, Self::Value>, Self::Error> {
+        let mut holders: HashMap<TopHolders, Self::Value> =
+            keys.iter().map(|key| (key.clone(), Vec::new())).collect();
+
+        for (limit, mints) in group_mints(keys, |key| &key.mint, |key| key.limit) {
+            for holding in fetch_tokens_holders(&self.db, &mints, limit)
+                .await
+                .map_err(Arc::new)?
+            {
+                let key = TopHolders {
+                    mint: holding.contract_address.clone(),
+                    limit,
+                };
+
+                holders.entry(key).or_default().push(holding);
+            }
+        }
+
+        Ok(holders)
+    }
+}
+
+// The candles of a token over a time range
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CandleRange {
+    pub mint: String,
+    pub interval_secs: i64,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub limit: i64,
+}
+
+// Batches the candles of every token in a response, one query per range
+pub struct TokenCandlesLoader {
+    pub db: Arc<PgPool>,
+}
+
+impl Loader<CandleRange> for TokenCandlesLoader {
+    type Value = Vec<CandleObject>;
+    type Error = Arc<anyhow::Error>;
+
+    async fn load(
+        &self,
+        keys: &[CandleRange],
+    ) -> Result<HashMap<CandleRange, Self::Value>, Self::Error> {
+        let mut candles: HashMap<CandleRange, Self::Value> =
+            keys.iter().map(|key| (key.clone(), Vec::new())).collect();
+
+        let groups = group_mints(
+            keys,
+            |key| &key.mint,
+            |key| (key.interval_secs, key.from, key.to, key.limit),
+        );
+
+        for ((interval_secs, from, to, limit), mints) in groups {
+            for candle in fetch_tokens_candles(&self.db, &mints, interval_secs, from, to, limit)
+                .await
+                .map_err(Arc::new)?
+            {
+                let key = CandleRange {
+                    mint: candle.contract_address,
+                    interval_secs,
+                    from,
+                    to,
+                    limit,
+                };
+
+                candles.entry(key).or_default().push(candle.candle);
+            }
+        }
+
+        Ok(candles)
+    }
+}
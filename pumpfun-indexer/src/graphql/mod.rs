@@ -0,0 +1,277 @@
+pub mod loaders;
+pub mod objects;
+
+use std::{future::Future, sync::Arc};
+
+use actix_web::{get, post, web, HttpMessage, HttpRequest, HttpResponse};
+use async_graphql::{
+    connection::{query, Connection, Edge},
+    dataloader::DataLoader,
+    extensions::{Extension, ExtensionContext, ExtensionFactory, NextValidation},
+    http::GraphiQLSource,
+    Context, EmptyMutation, Object, Result, Schema, ServerError, Subscription, ValidationResult,
+};
+use async_graphql_actix_web::{GraphQLRequest, GraphQLResponse, GraphQLSubscription};
+use async_trait::async_trait;
+use futures::Stream;
+use sqlx::PgPool;
+use tokio::sync::broadcast;
+
+use crate::{
+    auth::{ApiAccess, Caller},
+    config::ApiConfig,
+    db::graphql::fetch_tokens_page,
+    events::EventEnvelope,
+    graphql::{
+        loaders::{
+            CreatorTokenCountLoader, TokenCandlesLoader, TokenHoldersLoader, TokenLoader,
+            TokenTradesLoader,
+        },
+        objects::{CreatorObject, TokenObject, TradeObject, WalletObject},
+    },
+};
+
+const DEFAULT_PAGE_SIZE: usize = 20;
+const MAX_PAGE_SIZE: usize = 100;
+
+// Offset based connection, the cursor is the position of the edge. One extra row is fetched to know whether
+// another page follows.
+pub async fn paginate<T, F, R>(
+    after: Option<String>,
+    first: Option<i32>,
+    fetch: F,
+) -> Result<Connection<usize, T>>
+where
+    T: async_graphql::OutputType,
+    F: FnOnce(i64, i64) -> R,
+    R: Future<Output = Result<Vec<T>, anyhow::Error>>,
+{
+    query(
+        after,
+        None,
+        first,
+        None,
+        |after: Option<usize>, _: Option<usize>, first: Option<usize>, _: Option<usize>| async move {
+            let offset = after.map(|after| after + 1).unwrap_or(0);
+            let limit = first.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE);
+
+            let mut rows = fetch(offset as i64, limit as i64 + 1).await?;
+            let has_next_page = rows.len() > limit;
+            rows.truncate(limit);
+
+            let mut connection = Connection::new(offset > 0, has_next_page);
+            connection.edges.extend(
+                rows.into_iter()
+                    .enumerate()
+                    .map(|(index, row)| Edge::new(offset + index, row)),
+            );
+
+            Ok::<_, async_graphql::Error>(connection)
+        },
+    )
+    .await
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    async fn token(&self, ctx: &Context<'_>, address: String) -> Result<Option<TokenObject>> {
+        let loader = ctx.data::<DataLoader<TokenLoader>>()?;
+
+        Ok(loader.load_one(address).await?)
+    }
+
+    // Tokens newest first, `status` filters on the bond status (newly_launched, graduating, graduated)
+    async fn tokens(
+        &self,
+        ctx: &Context<'_>,
+        after: Option<String>,
+        first: Option<i32>,
+        status: Option<String>,
+    ) -> Result<Connection<usize, TokenObject>> {
+        let db = ctx.data::<Arc<PgPool>>()?;
+
+        paginate(after, first, |offset, limit| async move {
+            fetch_tokens_page(db, status.as_deref(), None, offset, limit).await
+        })
+        .await
+    }
+
+    async fn wallet(&self, address: String) -> WalletObject {
+        WalletObject { address }
+    }
+
+    async fn creator(&self, address: String) -> CreatorObject {
+        CreatorObject { address }
+    }
+}
+
+pub struct SubscriptionRoot;
+
+#[Subscription]
+impl SubscriptionRoot {
+    // Trades as they are processed, optionally of a single token. Subscribers too slow to keep up skip trades.
+    async fn trades(
+        &self,
+        ctx: &Context<'_>,
+        mint: Option<String>,
+    ) -> Result<impl Stream<Item = TradeObject>> {
+        let rx = ctx.data::<broadcast::Sender<EventEnvelope>>()?.subscribe();
+
+        Ok(futures::stream::unfold(rx, move |mut rx| {
+            let mint = mint.clone();
+
+            async move {
+                loop {
+                    match rx.recv().await {
+                        Ok(envelope) => {
+                            if mint
+                                .as_ref()
+                                .map(|mint| *mint != envelope.mint)
+                                .unwrap_or(false)
+                            {
+                                continue;
+                            }
+
+                            if let Some(trade) = TradeObject::from_envelope(&envelope) {
+                                return Some((trade, rx));
+                            }
+                        }
+                        Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(broadcast::error::RecvError::Closed) => return None,
+                    }
+                }
+            }
+        }))
+    }
+}
+
+pub type IndexerSchema = Schema<QueryRoot, EmptyMutation, SubscriptionRoot>;
+
+// Takes the complexity of a validated query from the caller's token bucket before it runs, so a query walking many
+// tokens and trades costs more than a single lookup. Requests without a caller, like the subscriptions, are not charged.
+struct ComplexityCost {
+    cost: f64,
+}
+
+impl ExtensionFactory for ComplexityCost {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(ComplexityCostExtension { cost: self.cost })
+    }
+}
+
+struct ComplexityCostExtension {
+    cost: f64,
+}
+
+#[async_trait]
+impl Extension for ComplexityCostExtension {
+    async fn validation(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        next: NextValidation<'_>,
+    ) -> Result<ValidationResult, Vec<ServerError>> {
+        let result = next.run(ctx).await?;
+
+        if let (Some(access), Some(caller)) = (
+            ctx.data_opt::<web::Data<ApiAccess>>(),
+            ctx.data_opt::<Caller>(),
+        ) {
+            if !access
+                .charge(caller, result.complexity as f64 * self.cost)
+                .await
+            {
+                return Err(vec![ServerError::new(
+                    format!(
+                        "Rate limit exceeded, the query has a complexity of {}",
+                        result.complexity
+                    ),
+                    None,
+                )]);
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+pub fn build_schema(
+    db: Arc<PgPool>,
+    live: broadcast::Sender<EventEnvelope>,
+    config: &ApiConfig,
+) -> IndexerSchema {
+    Schema::build(QueryRoot, EmptyMutation, SubscriptionRoot)
+        .limit_depth(config.graphql_max_depth)
+        .limit_complexity(config.graphql_max_complexity)
+        .extension(ComplexityCost {
+            cost: config.graphql_complexity_cost,
+        })
+        .data(DataLoader::new(
+            TokenLoader { db: db.clone() },
+            tokio::spawn,
+        ))
+        .data(DataLoader::new(
+            TokenTradesLoader { db: db.clone() },
+            tokio::spawn,
+        ))
+        .data(DataLoader::new(
+            TokenHoldersLoader { db: db.clone() },
+            tokio::spawn,
+        ))
+        .data(DataLoader::new(
+            TokenCandlesLoader { db: db.clone() },
+            tokio::spawn,
+        ))
+        .data(DataLoader::new(
+            CreatorTokenCountLoader { db: db.clone() },
+            tokio::spawn,
+        ))
+        .data(db)
+        .data(live)
+        .finish()
+}
+
+//* GraphQL endpoint over tokens, trades, holders and creators */
+#[post("/graphql")]
+pub async fn post_graphql(
+    schema: web::Data<IndexerSchema>,
+    req: HttpRequest,
+    request: GraphQLRequest,
+) -> GraphQLResponse {
+    let mut request = request.into_inner();
+
+    // The caller and the buckets are handed to the query, to charge it by its complexity
+    if let Some(caller) = req.extensions().get::<Caller>().cloned() {
+        request = request.data(caller);
+    }
+
+    if let Some(access) = req.app_data::<web::Data<ApiAccess>>().cloned() {
+        request = request.data(access);
+    }
+
+    schema.execute(request).await.into()
+}
+
+//* GraphiQL playground */
+#[get("/graphql")]
+pub async fn get_graphiql() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(
+            GraphiQLSource::build()
+                .endpoint("/graphql")
+                .subscription_endpoint("/graphql/ws")
+                .finish(),
+        )
+}
+
+//* Websocket endpoint of the GraphQL subscriptions */
+#[get("/graphql/ws")]
+pub async fn get_graphql_ws(
+    schema: web::Data<IndexerSchema>,
+    req: HttpRequest,
+    payload: web::Payload,
+) -> actix_web::Result<HttpResponse> {
+    GraphQLSubscription::new(IndexerSchema::clone(&schema)).start(&req, payload)
+}
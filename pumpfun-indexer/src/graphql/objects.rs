@@ -0,0 +1,292 @@
+use std::sync::Arc;
+
+use async_graphql::{
+    connection::Connection, dataloader::DataLoader, ComplexObject, Context, Result, SimpleObject,
+};
+use serde::Serialize;
+use sqlx::{
+    prelude::FromRow,
+    types::chrono::{DateTime, Utc},
+    PgPool,
+};
+use utoipa::ToSchema;
+
+use crate::{
+    db::graphql::{fetch_holdings, fetch_tokens_page, fetch_trades_page},
+    events::{EventEnvelope, EventPayload},
+    graphql::{
+        loaders::{
+            CandleRange, CreatorTokenCountLoader, TokenCandlesLoader, TokenHoldersLoader,
+            TokenLoader, TokenTradesLoader, TopHolders, TradesPage,
+        },
+        paginate,
+    },
+};
+
+// Candle widths outside of this range are clamped
+const MIN_CANDLE_INTERVAL_SECS: i64 = 1;
+const MAX_CANDLE_INTERVAL_SECS: i64 = 86_400;
+
+// Maximum number of candles returned for one query
+const MAX_CANDLES: i64 = 1_000;
+
+const DEFAULT_HOLDERS_LIMIT: i32 = 20;
+const MAX_HOLDERS_LIMIT: i32 = 100;
+
+fn holders_limit(first: Option<i32>) -> i64 {
+    first
+        .unwrap_or(DEFAULT_HOLDERS_LIMIT)
+        .clamp(1, MAX_HOLDERS_LIMIT) as i64
+}
+
+#[derive(SimpleObject, FromRow, Clone, Debug)]
+#[graphql(name = "Token", complex)]
+pub struct TokenObject {
+    pub id: String,
+    pub name: String,
+    pub ticker: String,
+    pub contract_address: String,
+    pub bonding_curve_percentage: i32,
+    pub bond_status: String,
+    // Market cap in USD
+    pub market_cap: Option<i64>,
+    pub uri: String,
+    pub bonding_curve_address: String,
+    #[graphql(skip)]
+    pub creator_address: String,
+    pub created_at: DateTime<Utc>,
+    pub created_slot: Option<i64>,
+}
+
+#[ComplexObject]
+impl TokenObject {
+    async fn creator(&self) -> CreatorObject {
+        CreatorObject {
+            address: self.creator_address.clone(),
+        }
+    }
+
+    // Trades of the token, newest first
+    async fn trades(
+        &self,
+        ctx: &Context<'_>,
+        after: Option<String>,
+        first: Option<i32>,
+    ) -> Result<Connection<usize, TradeObject>> {
+        let loader = ctx.data::<DataLoader<TokenTradesLoader>>()?;
+        let mint = self.contract_address.clone();
+
+        paginate(after, first, |offset, limit| async move {
+            let page = TradesPage {
+                mint,
+                offset,
+                limit,
+            };
+
+            match loader.load_one(page).await {
+                Ok(trades) => Ok(trades.unwrap_or_default()),
+                Err(err) => Err(anyhow::Error::msg(err.to_string())),
+            }
+        })
+        .await
+    }
+
+    // Largest current holders derived from the trades
+    async fn holders(&self, ctx: &Context<'_>, first: Option<i32>) -> Result<Vec<HoldingObject>> {
+        let loader = ctx.data::<DataLoader<TokenHoldersLoader>>()?;
+
+        let key = TopHolders {
+            mint: self.contract_address.clone(),
+            limit: holders_limit(first),
+        };
+
+        Ok(loader.load_one(key).await?.unwrap_or_default())
+    }
+
+    // OHLCV candles of the price in SOL, `interval_secs` wide, between `from` and `to` (default: the last day)
+    async fn candles(
+        &self,
+        ctx: &Context<'_>,
+        interval_secs: i64,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> Result<Vec<CandleObject>> {
+        let loader = ctx.data::<DataLoader<TokenCandlesLoader>>()?;
+
+        let interval_secs = interval_secs.clamp(MIN_CANDLE_INTERVAL_SECS, MAX_CANDLE_INTERVAL_SECS);
+        let to = to.unwrap_or_else(Utc::now);
+        let from = from.unwrap_or_else(|| to - sqlx::types::chrono::Duration::days(1));
+
+        let range = CandleRange {
+            mint: self.contract_address.clone(),
+            interval_secs,
+            from,
+            to,
+            limit: MAX_CANDLES,
+        };
+
+        Ok(loader.load_one(range).await?.unwrap_or_default())
+    }
+}
+
+#[derive(SimpleObject, FromRow, Clone, Debug)]
+#[graphql(name = "Trade", complex)]
+pub struct TradeObject {
+    pub id: String,
+    // Lamports
+    pub sol_amount: i64,
+    // Token base units (6 decimals)
+    pub token_amount: i64,
+    pub is_buy: bool,
+    #[graphql(skip)]
+    pub user_address: String,
+    #[graphql(skip)]
+    pub contract_address: String,
+    pub slot: Option<i64>,
+    pub signature: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl TradeObject {
+    // Live trades are served straight from the processed events, before they reach the trade table
+    pub fn from_envelope(envelope: &EventEnvelope) -> Option<Self> {
+        let EventPayload::Trade(trade) = &envelope.payload else {
+            return None;
+        };
+
+        Some(Self {
+            id: envelope.id.clone(),
+            sol_amount: trade.sol_amount as i64,
+            token_amount: trade.token_amount as i64,
+            is_buy: trade.is_buy,
+            user_address: trade.user.clone(),
+            contract_address: envelope.mint.clone(),
+            slot: Some(envelope.slot as i64),
+            signature: Some(envelope.signature.clone()),
+            created_at: envelope.emitted_at,
+        })
+    }
+}
+
+#[ComplexObject]
+impl TradeObject {
+    async fn token(&self, ctx: &Context<'_>) -> Result<Option<TokenObject>> {
+        let loader = ctx.data::<DataLoader<TokenLoader>>()?;
+
+        Ok(loader.load_one(self.contract_address.clone()).await?)
+    }
+
+    async fn wallet(&self) -> WalletObject {
+        WalletObject {
+            address: self.user_address.clone(),
+        }
+    }
+}
+
+#[derive(SimpleObject, FromRow, Clone, Debug)]
+#[graphql(name = "Holding", complex)]
+pub struct HoldingObject {
+    #[graphql(skip)]
+    pub wallet_address: String,
+    #[graphql(skip)]
+    pub contract_address: String,
+    // Token base units (6 decimals)
+    pub balance: i64,
+    // Share of the total supply
+    pub percentage: f64,
+}
+
+#[ComplexObject]
+impl HoldingObject {
+    async fn wallet(&self) -> WalletObject {
+        WalletObject {
+            address: self.wallet_address.clone(),
+        }
+    }
+
+    async fn token(&self, ctx: &Context<'_>) -> Result<Option<TokenObject>> {
+        let loader = ctx.data::<DataLoader<TokenLoader>>()?;
+
+        Ok(loader.load_one(self.contract_address.clone()).await?)
+    }
+}
+
+#[derive(SimpleObject, Clone, Debug)]
+#[graphql(name = "Creator", complex)]
+pub struct CreatorObject {
+    pub address: String,
+}
+
+#[ComplexObject]
+impl CreatorObject {
+    // Tokens launched by the creator, newest first
+    async fn tokens(
+        &self,
+        ctx: &Context<'_>,
+        after: Option<String>,
+        first: Option<i32>,
+    ) -> Result<Connection<usize, TokenObject>> {
+        let db = ctx.data::<Arc<PgPool>>()?;
+        let creator = self.address.clone();
+
+        paginate(after, first, |offset, limit| async move {
+            fetch_tokens_page(db, None, Some(&creator), offset, limit).await
+        })
+        .await
+    }
+
+    async fn token_count(&self, ctx: &Context<'_>) -> Result<i64> {
+        let loader = ctx.data::<DataLoader<CreatorTokenCountLoader>>()?;
+
+        Ok(loader
+            .load_one(self.address.clone())
+            .await?
+            .unwrap_or_default())
+    }
+}
+
+#[derive(SimpleObject, Clone, Debug)]
+#[graphql(name = "Wallet", complex)]
+pub struct WalletObject {
+    pub address: String,
+}
+
+#[ComplexObject]
+impl WalletObject {
+    // Trades of the wallet across all tokens, newest first
+    async fn trades(
+        &self,
+        ctx: &Context<'_>,
+        after: Option<String>,
+        first: Option<i32>,
+    ) -> Result<Connection<usize, TradeObject>> {
+        let db = ctx.data::<Arc<PgPool>>()?;
+        let wallet = self.address.clone();
+
+        paginate(after, first, |offset, limit| async move {
+            fetch_trades_page(db, None, Some(&wallet), offset, limit).await
+        })
+        .await
+    }
+
+    // Tokens currently held by the wallet, largest balance first
+    async fn holdings(&self, ctx: &Context<'_>, first: Option<i32>) -> Result<Vec<HoldingObject>> {
+        let db = ctx.data::<Arc<PgPool>>()?;
+
+        Ok(fetch_holdings(db, None, Some(&self.address), holders_limit(first)).await?)
+    }
+}
+
+#[derive(SimpleObject, FromRow, Serialize, ToSchema, Clone, Debug)]
+#[graphql(name = "Candle")]
+#[schema(as = Candle)]
+pub struct CandleObject {
+    pub start: DateTime<Utc>,
+    // Prices in SOL per token
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume_sol: f64,
+    pub trades: i64,
+}
@@ -0,0 +1,159 @@
+use std::sync::Arc;
+
+use actix_web::{get, web, HttpResponse};
+use redis::aio::MultiplexedConnection;
+use serde::Serialize;
+use sqlx::PgPool;
+use utoipa::ToSchema;
+
+use crate::{config::IndexerConfig, metrics::metrics};
+
+// Readiness thresholds taken from the IndexerConfig at startup
+#[derive(Debug, Clone)]
+pub struct HealthThresholds {
+    pub max_event_lag_secs: i64,
+    pub max_sol_price_age_secs: i64,
+    pub max_backlog_depth: i64,
+    // Only the ingest role processes events, the other roles just check their connections
+    pub check_ingestion: bool,
+}
+
+impl From<&IndexerConfig> for HealthThresholds {
+    fn from(config: &IndexerConfig) -> Self {
+        Self {
+            max_event_lag_secs: config.max_event_lag_secs,
+            max_sol_price_age_secs: config.max_sol_price_age_secs,
+            max_backlog_depth: config.max_backlog_depth,
+            check_ingestion: true,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct HealthCheck {
+    pub name: &'static str,
+    pub ok: bool,
+    pub detail: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ReadinessReport {
+    pub ready: bool,
+    pub checks: Vec<HealthCheck>,
+}
+
+//* Liveness probe, only tells the orchestrator that the process is up */
+#[utoipa::path(tag = "health", responses((status = 200, description = "The process is up")))]
+#[get("/healthz")]
+pub async fn get_healthz() -> HttpResponse {
+    HttpResponse::Ok().json(serde_json::json!({ "status": "ok" }))
+}
+
+//* Readiness probe, returns 503 when a dependency is down or the indexer has stalled */
+#[utoipa::path(
+    tag = "health",
+    responses(
+        (status = 200, body = ReadinessReport),
+        (status = 503, description = "A dependency is down or the indexer has stalled", body = ReadinessReport),
+    )
+)]
+#[get("/readyz")]
+pub async fn get_readyz(
+    db: web::Data<Arc<PgPool>>,
+    redis: web::Data<MultiplexedConnection>,
+    thresholds: web::Data<HealthThresholds>,
+) -> HttpResponse {
+    let mut checks = Vec::new();
+
+    let conn = db.get_ref();
+
+    checks.push(match sqlx::query("SELECT 1").execute(&**conn).await {
+        Ok(_) => HealthCheck {
+            name: "postgres",
+            ok: true,
+            detail: "connected".to_string(),
+        },
+        Err(err) => HealthCheck {
+            name: "postgres",
+            ok: false,
+            detail: err.to_string(),
+        },
+    });
+
+    let mut redis_conn = redis.get_ref().clone();
+
+    checks.push(
+        match redis::cmd("PING")
+            .query_async::<String>(&mut redis_conn)
+            .await
+        {
+            Ok(_) => HealthCheck {
+                name: "redis",
+                ok: true,
+                detail: "connected".to_string(),
+            },
+            Err(err) => HealthCheck {
+                name: "redis",
+                ok: false,
+                detail: err.to_string(),
+            },
+        },
+    );
+
+    if thresholds.check_ingestion {
+        push_ingestion_checks(&mut checks, &thresholds);
+    }
+
+    let ready = checks.iter().all(|check| check.ok);
+
+    let report = ReadinessReport { ready, checks };
+
+    if ready {
+        HttpResponse::Ok().json(&report)
+    } else {
+        HttpResponse::ServiceUnavailable().json(&report)
+    }
+}
+
+// The indexer is receiving events, pricing them and keeping up with storing the trades
+fn push_ingestion_checks(checks: &mut Vec<HealthCheck>, thresholds: &HealthThresholds) {
+    checks.push(age_check(
+        "last_event",
+        metrics().last_event_age_secs(),
+        thresholds.max_event_lag_secs,
+    ));
+
+    checks.push(age_check(
+        "sol_price",
+        metrics().sol_price_age_secs(),
+        thresholds.max_sol_price_age_secs,
+    ));
+
+    // The trade buffer is flushed once a batch fills up, the processor queues show whether the pipeline keeps up
+    let backlog = metrics().processor_backlog();
+
+    checks.push(HealthCheck {
+        name: "backlog",
+        ok: backlog <= thresholds.max_backlog_depth,
+        detail: format!(
+            "{} queued events (max {})",
+            backlog, thresholds.max_backlog_depth
+        ),
+    });
+}
+
+// Fails when the timestamp is older than the threshold or was never set
+fn age_check(name: &'static str, age_secs: Option<i64>, max_age_secs: i64) -> HealthCheck {
+    match age_secs {
+        Some(age) => HealthCheck {
+            name,
+            ok: age <= max_age_secs,
+            detail: format!("{}s ago (max {}s)", age, max_age_secs),
+        },
+        None => HealthCheck {
+            name,
+            ok: false,
+            detail: "never updated".to_string(),
+        },
+    }
+}
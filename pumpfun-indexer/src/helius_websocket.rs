@@ -8,12 +8,8 @@ use helius::types::{
 };
 use tokio::sync::RwLock;
 
-use crate::config::IndexerConfig;
-
 //Helius WebSocket configuration for subscribing to transactions related to the Pumpfun program, to be passed into the Helius WebSocket datasource.
-pub fn get_helius_websocket() -> HeliusWebsocket {
-    let api_key = IndexerConfig::get_config().api_key;
-
+pub fn get_helius_websocket(api_key: String) -> HeliusWebsocket {
     let helius_websocket = carbon_helius_atlas_ws_datasource::HeliusWebsocket::new(
         api_key,
         carbon_helius_atlas_ws_datasource::Filters {
@@ -0,0 +1,74 @@
+use std::collections::HashMap;
+
+use anyhow::Error;
+use redis::{aio::MultiplexedConnection, AsyncCommands};
+use serde::{Deserialize, Serialize};
+use solana_client::client_error::reqwest::{
+    self,
+    header::{HeaderMap, HeaderValue, CONTENT_TYPE},
+};
+
+use crate::{
+    config::{EventEncoding, IndexerConfig},
+    events::EventEnvelope,
+};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CoinPriceData {
+    pub usd: f64,
+}
+
+pub type CoinPriceResponse = HashMap<String, CoinPriceData>;
+
+// Function to fetch the latest SOL price from the CoinGecko API
+pub async fn get_latest_sol_price() -> Result<f64, Error> {
+    let api_key = IndexerConfig::get_config().coingecko_api;
+
+    let coingecko_url =
+        "https://api.coingecko.com/api/v3/simple/price?ids=solana&vs_currencies=usd";
+
+    let mut headers = HeaderMap::new();
+
+    headers.insert("x-cg-api-key", HeaderValue::from_str(&api_key)?);
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+
+    let client = reqwest::Client::new();
+
+    let res = client.get(coingecko_url).headers(headers).send().await?;
+
+    let response: CoinPriceResponse = match res.json().await {
+        Ok(r) => r,
+        Err(err) => {
+            tracing::error!("Failed to parse api response. Failed with error: {}", err);
+            return Err(Error::msg("Failed to parse api response"));
+        }
+    };
+
+    response
+        .get("solana")
+        .map(|price| price.usd)
+        .ok_or_else(|| Error::msg("SOL price missing from the api response"))
+}
+
+// Publishes a trade envelope on the "trade" channel, consumed by `consume_and_store`
+#[tracing::instrument(name = "publish_trade", skip_all, fields(mint = %envelope.mint, id = %envelope.id))]
+pub async fn store_in_redis(
+    redis: &mut MultiplexedConnection,
+    envelope: EventEnvelope,
+    encoding: EventEncoding,
+) {
+    let trade_details = match envelope.encode(encoding) {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            tracing::error!(error = ?err, "Failed to encode trade envelope");
+            return;
+        }
+    };
+
+    let _: () = redis
+        .publish("trade", trade_details)
+        .await
+        .expect("Failed to publish trade details");
+
+    tracing::debug!("redis published into trade channel");
+}
@@ -0,0 +1,58 @@
+use std::sync::Arc;
+
+use crate::events::{EventEnvelope, EventPayload};
+
+type Hook = Arc<dyn Fn(&EventEnvelope) + Send + Sync>;
+
+// Callbacks of a service embedding the processor, called in order with the envelope of every processed event. They
+// run inside the processor, so anything slow should be handed off to a task or a channel.
+#[derive(Clone, Default)]
+pub struct Hooks {
+    on_create: Vec<Hook>,
+    on_trade: Vec<Hook>,
+    on_graduate: Vec<Hook>,
+}
+
+impl std::fmt::Debug for Hooks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Hooks")
+            .field("on_create", &self.on_create.len())
+            .field("on_trade", &self.on_trade.len())
+            .field("on_graduate", &self.on_graduate.len())
+            .finish()
+    }
+}
+
+impl Hooks {
+    // A token was launched, the payload is EventPayload::Create
+    pub fn on_create<F: Fn(&EventEnvelope) + Send + Sync + 'static>(&mut self, hook: F) {
+        self.on_create.push(Arc::new(hook));
+    }
+
+    // A buy or sell on the bonding curve, the payload is EventPayload::Trade
+    pub fn on_trade<F: Fn(&EventEnvelope) + Send + Sync + 'static>(&mut self, hook: F) {
+        self.on_trade.push(Arc::new(hook));
+    }
+
+    // The bonding curve completed, the payload is EventPayload::Complete
+    pub fn on_graduate<F: Fn(&EventEnvelope) + Send + Sync + 'static>(&mut self, hook: F) {
+        self.on_graduate.push(Arc::new(hook));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.on_create.is_empty() && self.on_trade.is_empty() && self.on_graduate.is_empty()
+    }
+
+    pub fn call(&self, envelope: &EventEnvelope) {
+        let hooks = match envelope.payload {
+            EventPayload::Create { .. } => &self.on_create,
+            EventPayload::Trade(_) => &self.on_trade,
+            EventPayload::Complete { .. } => &self.on_graduate,
+            EventPayload::StatusChange { .. } => return,
+        };
+
+        for hook in hooks {
+            hook(envelope);
+        }
+    }
+}
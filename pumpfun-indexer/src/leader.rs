@@ -0,0 +1,339 @@
+use std::time::Duration;
+
+use redis::{aio::MultiplexedConnection, RedisResult, Script};
+use sqlx::{Connection, PgConnection};
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+use crate::{
+    config::{IndexerConfig, LeaderElectionConfig, LeaderElectionKind},
+    metrics::metrics,
+};
+
+// Extends the lease only if this replica still owns it
+const RENEW_LEASE: &str = r#"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    return redis.call('PEXPIRE', KEYS[1], ARGV[2])
+end
+return 0
+"#;
+
+// Deletes the lease only if this replica still owns it
+const RELEASE_LEASE: &str = r#"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    return redis.call('DEL', KEYS[1])
+end
+return 0
+"#;
+
+// Whether this replica holds the ingest leadership. Only the leader writes to the DB, Redis and the sinks, the
+// standbys keep their websocket and in-memory state warm so that they can take over right away.
+#[derive(Debug, Clone)]
+pub struct Leadership {
+    leader: watch::Receiver<bool>,
+}
+
+impl Default for Leadership {
+    // Without an election the replica is always the leader
+    fn default() -> Self {
+        let (_, leader) = watch::channel(true);
+
+        Self { leader }
+    }
+}
+
+impl Leadership {
+    pub fn is_leader(&self) -> bool {
+        *self.leader.borrow()
+    }
+
+    // Waits for the leadership to change and returns whether this replica is the leader now. Without an election it
+    // never changes, so this never returns.
+    pub async fn changed(&mut self) -> bool {
+        if self.leader.changed().await.is_err() {
+            std::future::pending::<()>().await;
+        }
+
+        *self.leader.borrow_and_update()
+    }
+
+    // A replica that never wins the election
+    #[cfg(test)]
+    pub fn standby() -> Self {
+        let (_, leader) = watch::channel(false);
+
+        Self { leader }
+    }
+}
+
+// Starts campaigning with the backend of LEADER_ELECTION. The replica stays a standby until it wins.
+pub fn start_leader_election(config: &IndexerConfig, shutdown: CancellationToken) -> Leadership {
+    let election = config.leader_election.clone();
+
+    if election.kind == LeaderElectionKind::None {
+        metrics().leader.set(1);
+
+        return Leadership::default();
+    }
+
+    let (tx, leader) = watch::channel(false);
+
+    metrics().leader.set(0);
+
+    let database_url = config.database_url.clone();
+    let redis_url = config.redis_url.clone();
+
+    //Spawn a new thread that acquires the leadership and keeps renewing it
+    tokio::spawn(async move {
+        match election.kind {
+            LeaderElectionKind::Postgres => {
+                run_postgres_election(database_url, election, tx, shutdown).await
+            }
+            LeaderElectionKind::Redis => {
+                run_redis_election(redis_url, election, tx, shutdown).await
+            }
+            LeaderElectionKind::None => {}
+        }
+    });
+
+    Leadership { leader }
+}
+
+// Campaigns like `start_leader_election` but fails if the leadership isn't won within one lease, for the roles that
+// must not run next to a leader. Without an election there is nothing to take and the replica is the leader.
+pub async fn acquire_leadership(
+    config: &IndexerConfig,
+    shutdown: CancellationToken,
+) -> Result<Leadership, anyhow::Error> {
+    let mut leadership = start_leader_election(config, shutdown.clone());
+
+    if config.leader_election.kind == LeaderElectionKind::None {
+        return Ok(leadership);
+    }
+
+    let lease = Duration::from_millis(config.leader_election.lease_ms);
+
+    match tokio::time::timeout(lease, leadership.changed()).await {
+        Ok(true) => Ok(leadership),
+        _ => {
+            // Stops campaigning so the lock isn't taken after giving up
+            shutdown.cancel();
+
+            Err(anyhow::Error::msg(
+                "Another replica holds the ingest leadership, stop it before backfilling",
+            ))
+        }
+    }
+}
+
+fn set_leader(tx: &watch::Sender<bool>, leader: bool) {
+    if *tx.borrow() == leader {
+        return;
+    }
+
+    tx.send_replace(leader);
+
+    metrics().leader.set(leader as i64);
+
+    if leader {
+        metrics()
+            .leadership_changes
+            .with_label_values(&["acquired"])
+            .inc();
+        tracing::info!("Acquired the ingest leadership, writing");
+    } else {
+        metrics()
+            .leadership_changes
+            .with_label_values(&["lost"])
+            .inc();
+        tracing::warn!("Lost the ingest leadership, standing by");
+    }
+}
+
+// The lock is held by the session, so it is freed by Postgres as soon as the connection of a crashed leader closes.
+// The leader steps down on the first failed check, before Postgres notices the dead session and frees the lock.
+async fn run_postgres_election(
+    database_url: String,
+    election: LeaderElectionConfig,
+    tx: watch::Sender<bool>,
+    shutdown: CancellationToken,
+) {
+    let lease = Duration::from_millis(election.lease_ms);
+    let mut connection: Option<PgConnection> = None;
+
+    loop {
+        if connection.is_none() {
+            match PgConnection::connect(&database_url).await {
+                Ok(conn) => connection = Some(conn),
+                Err(err) => {
+                    tracing::error!(error = ?err, "Failed to connect for the leader election")
+                }
+            }
+        }
+
+        // Copied out, the borrow of the watch can't be held across an await
+        let leader = *tx.borrow();
+
+        let held = match &mut connection {
+            Some(conn) => {
+                match tokio::time::timeout(lease, hold_advisory_lock(conn, leader, &election.key))
+                    .await
+                {
+                    Ok(Ok(held)) => held,
+                    Ok(Err(err)) => {
+                        tracing::error!(error = ?err, "Leader election check failed");
+                        // A new session is opened, the old one releases the lock when it closes
+                        connection = None;
+                        false
+                    }
+                    Err(_) => {
+                        tracing::error!("Leader election check timed out");
+                        connection = None;
+                        false
+                    }
+                }
+            }
+            None => false,
+        };
+
+        set_leader(&tx, held);
+
+        tokio::select! {
+            _ = shutdown.cancelled() => break,
+            _ = tokio::time::sleep(lease / 3) => {}
+        }
+    }
+
+    // Closing the session frees the lock so a standby takes over without waiting. The local state is left as is, the
+    // pipeline is stopping and only drains what it processed as the leader.
+    if let Some(conn) = connection {
+        let _ = conn.close().await;
+    }
+}
+
+async fn hold_advisory_lock(
+    connection: &mut PgConnection,
+    held: bool,
+    key: &str,
+) -> Result<bool, sqlx::Error> {
+    if held {
+        // Session locks can't be lost while the session lives, a working connection means it is still held
+        sqlx::query("SELECT 1").execute(&mut *connection).await?;
+
+        return Ok(true);
+    }
+
+    sqlx::query_scalar("SELECT pg_try_advisory_lock(hashtext($1))")
+        .bind(key)
+        .fetch_one(connection)
+        .await
+}
+
+// The leader renews the lease three times per lease and steps down on the first failed renewal, so it has stopped
+// writing before the lease expires and a standby acquires it.
+async fn run_redis_election(
+    redis_url: String,
+    election: LeaderElectionConfig,
+    tx: watch::Sender<bool>,
+    shutdown: CancellationToken,
+) {
+    let lease = Duration::from_millis(election.lease_ms);
+    let id = Uuid::new_v4().to_string();
+    let renew = Script::new(RENEW_LEASE);
+    let mut connection: Option<MultiplexedConnection> = None;
+
+    loop {
+        if connection.is_none() {
+            match redis::Client::open(redis_url.as_str()) {
+                Ok(client) => match client.get_multiplexed_async_connection().await {
+                    Ok(conn) => connection = Some(conn),
+                    Err(err) => {
+                        tracing::error!(error = ?err, "Failed to connect for the leader election")
+                    }
+                },
+                Err(err) => {
+                    tracing::error!(error = ?err, "Invalid Redis URL for the leader election")
+                }
+            }
+        }
+
+        let leader = *tx.borrow();
+
+        let held = match &mut connection {
+            Some(conn) => {
+                match tokio::time::timeout(
+                    lease,
+                    hold_redis_lease(conn, leader, &election, &id, &renew),
+                )
+                .await
+                {
+                    Ok(Ok(held)) => held,
+                    Ok(Err(err)) => {
+                        tracing::error!(error = ?err, "Leader election check failed");
+                        connection = None;
+                        false
+                    }
+                    Err(_) => {
+                        tracing::error!("Leader election check timed out");
+                        connection = None;
+                        false
+                    }
+                }
+            }
+            None => false,
+        };
+
+        set_leader(&tx, held);
+
+        tokio::select! {
+            _ = shutdown.cancelled() => break,
+            _ = tokio::time::sleep(lease / 3) => {}
+        }
+    }
+
+    let leader = *tx.borrow();
+
+    // Hand the lease over right away instead of letting it expire
+    if let (true, Some(mut conn)) = (leader, connection) {
+        let released: RedisResult<i64> = Script::new(RELEASE_LEASE)
+            .key(&election.key)
+            .arg(&id)
+            .invoke_async(&mut conn)
+            .await;
+
+        if let Err(err) = released {
+            tracing::error!(error = ?err, "Failed to release the leader lease");
+        }
+    }
+}
+
+async fn hold_redis_lease(
+    connection: &mut MultiplexedConnection,
+    held: bool,
+    election: &LeaderElectionConfig,
+    id: &str,
+    renew: &Script,
+) -> RedisResult<bool> {
+    if held {
+        let renewed: i64 = renew
+            .key(&election.key)
+            .arg(id)
+            .arg(election.lease_ms)
+            .invoke_async(connection)
+            .await?;
+
+        return Ok(renewed == 1);
+    }
+
+    let acquired: Option<String> = redis::cmd("SET")
+        .arg(&election.key)
+        .arg(id)
+        .arg("NX")
+        .arg("PX")
+        .arg(election.lease_ms)
+        .query_async(connection)
+        .await?;
+
+    Ok(acquired.is_some())
+}
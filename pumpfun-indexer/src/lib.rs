@@ -0,0 +1,49 @@
+use std::sync::Arc;
+
+use crate::state::ShardedBondingState;
+
+// Public surface for the services embedding the processor, plus the roles the `indexer` binary runs and what the
+// benches drive. Everything else is internal and may change without notice.
+pub mod bonding_curve;
+pub mod config;
+pub mod events;
+pub mod hooks;
+pub mod logging;
+pub mod roles;
+pub mod state;
+pub mod trade_writer;
+pub mod types;
+pub mod utils;
+
+mod alerts;
+mod auth;
+mod backfill;
+mod cache;
+mod checkpoint;
+mod datasources;
+mod db;
+mod dispatch;
+mod errors;
+mod funding;
+mod graphql;
+mod health;
+mod helius_websocket;
+mod helpers;
+mod leader;
+mod metrics;
+mod openapi;
+mod pumpfun_processor;
+mod ranking;
+mod rpc_poller;
+mod shutdown;
+mod sinks;
+mod sniper;
+mod store;
+mod tokens;
+mod webhooks;
+
+pub use cache::CacheInvalidator;
+pub use pumpfun_processor::{ProcessorBuilder, PumpfunInstructionProcessor};
+pub use store::Store;
+
+pub type BondingMcStateMap = Arc<ShardedBondingState>;
@@ -0,0 +1,25 @@
+use tracing_subscriber::EnvFilter;
+
+use crate::config::IndexerConfig;
+
+// Initializes the tracing subscriber. LOG_LEVEL takes per-module directives (e.g. "info,pumpfun_indexer::db=debug,sqlx=warn")
+// and LOG_FORMAT=json switches to JSON lines with the active spans attached to every event.
+pub fn init_logging(config: &IndexerConfig) {
+    let filter = EnvFilter::try_new(&config.log_level).unwrap_or_else(|err| {
+        eprintln!("Invalid LOG_LEVEL {:?}: {}, using info", config.log_level, err);
+        EnvFilter::new("info")
+    });
+
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+
+    if config.log_format == "json" {
+        builder
+            .json()
+            .flatten_event(true)
+            .with_current_span(true)
+            .with_span_list(true)
+            .init();
+    } else {
+        builder.init();
+    }
+}
@@ -0,0 +1,569 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicI64, Ordering},
+        LazyLock, RwLock,
+    },
+    time::Instant,
+};
+
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    get,
+    middleware::Next,
+    HttpResponse,
+};
+use async_trait::async_trait;
+use carbon_core::{error::CarbonResult, metrics::Metrics};
+use prometheus::{
+    core::Collector, Encoder, Gauge, Histogram, HistogramOpts, HistogramVec, IntCounter,
+    IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry, TextEncoder,
+};
+use sqlx::types::chrono::Utc;
+
+// Names reported by the instruction processor through carbon's MetricsCollection
+pub const EVENTS_DECODED_PREFIX: &str = "pumpfun_events_decoded_";
+pub const PROCESSING_LATENCY: &str = "pumpfun_processing_latency_seconds";
+pub const STATE_MAP_SIZE: &str = "bonding_state_map_size";
+
+// Registry and typed metrics shared by the pipeline, the DB layer and the API
+pub struct IndexerMetrics {
+    pub registry: Registry,
+    pub events_decoded: IntCounterVec,
+    pub processing_latency: Histogram,
+    pub trade_buffer_depth: IntGauge,
+    pub trade_batch_target: IntGauge,
+    pub trade_writes_in_flight: IntGauge,
+    pub trades_dropped: IntCounterVec,
+    pub db_batch_size: HistogramVec,
+    pub db_batch_latency: HistogramVec,
+    pub db_errors: IntCounterVec,
+    pub sol_price_age: Gauge,
+    pub state_map_size: IntGauge,
+    pub state_map_lookups: IntCounterVec,
+    pub state_map_reloads: IntCounterVec,
+    pub state_map_evictions: IntCounterVec,
+    pub stale_trades_ignored: IntCounter,
+    pub http_requests: HistogramVec,
+    pub leader: IntGauge,
+    pub leadership_changes: IntCounterVec,
+    pub datasource_updates: IntCounterVec,
+    pub datasource_first_arrivals: IntCounterVec,
+    pub datasource_restarts: IntCounterVec,
+    pub datasource_arrival_lag: HistogramVec,
+    pub datasource_block_latency: HistogramVec,
+    pub processor_queue_depth: IntGaugeVec,
+    // Unix timestamp of the last successful SOL price refresh, turned into an age on scrape
+    sol_price_updated_at: AtomicI64,
+    // Unix timestamp of the last decoded Pump.fun event, used by the readiness check
+    last_event_at: AtomicI64,
+    // Any other counter, gauge or histogram reported by carbon or the datasources
+    carbon_counters: RwLock<HashMap<String, IntCounter>>,
+    carbon_gauges: RwLock<HashMap<String, Gauge>>,
+    carbon_histograms: RwLock<HashMap<String, Histogram>>,
+}
+
+static METRICS: LazyLock<IndexerMetrics> = LazyLock::new(IndexerMetrics::new);
+
+// Returns the process wide metrics
+pub fn metrics() -> &'static IndexerMetrics {
+    &METRICS
+}
+
+impl IndexerMetrics {
+    fn new() -> Self {
+        let registry = Registry::new_custom(Some("indexer".to_string()), None)
+            .expect("Failed to create metrics registry");
+
+        let events_decoded = IntCounterVec::new(
+            Opts::new("events_decoded_total", "Pump.fun events decoded per type"),
+            &["type"],
+        )
+        .unwrap();
+
+        let processing_latency = Histogram::with_opts(HistogramOpts::new(
+            "processing_latency_seconds",
+            "Time spent processing a decoded Pump.fun event",
+        ))
+        .unwrap();
+
+        let trade_buffer_depth = IntGauge::new(
+            "trade_buffer_depth",
+            "Trades buffered in memory waiting to be stored",
+        )
+        .unwrap();
+
+        let trade_batch_target = IntGauge::new(
+            "trade_batch_target",
+            "Batch size the trade writer currently adapted to",
+        )
+        .unwrap();
+
+        let trade_writes_in_flight = IntGauge::new(
+            "trade_writes_in_flight",
+            "Trade batches being written to the DB",
+        )
+        .unwrap();
+
+        let trades_dropped = IntCounterVec::new(
+            Opts::new(
+                "trades_dropped_total",
+                "Trades not stored by reason, unknown_token, duplicate or write_failed",
+            ),
+            &["reason"],
+        )
+        .unwrap();
+
+        let db_batch_size = HistogramVec::new(
+            HistogramOpts::new("db_batch_size", "Number of rows written per DB batch").buckets(
+                vec![1.0, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 1000.0, 5000.0, 20000.0],
+            ),
+            &["operation"],
+        )
+        .unwrap();
+
+        let db_batch_latency = HistogramVec::new(
+            HistogramOpts::new("db_batch_latency_seconds", "Latency of DB batch writes"),
+            &["operation"],
+        )
+        .unwrap();
+
+        let db_errors = IntCounterVec::new(
+            Opts::new("db_errors_total", "Failed DB operations"),
+            &["operation"],
+        )
+        .unwrap();
+
+        let sol_price_age = Gauge::new(
+            "sol_price_age_seconds",
+            "Seconds since the SOL price was last refreshed",
+        )
+        .unwrap();
+
+        let state_map_size = IntGauge::new(
+            "state_map_size",
+            "Tokens tracked in the in-memory bonding curve and market cap map",
+        )
+        .unwrap();
+
+        let state_map_lookups = IntCounterVec::new(
+            Opts::new(
+                "state_map_lookups_total",
+                "Lookups of the traded tokens in the in-memory state map by result",
+            ),
+            &["result"],
+        )
+        .unwrap();
+
+        let state_map_reloads = IntCounterVec::new(
+            Opts::new(
+                "state_map_reloads_total",
+                "Tokens missing from the in-memory state map looked up in the DB by result",
+            ),
+            &["result"],
+        )
+        .unwrap();
+
+        let state_map_evictions = IntCounterVec::new(
+            Opts::new(
+                "state_map_evictions_total",
+                "Tokens evicted from the in-memory state map by reason",
+            ),
+            &["reason"],
+        )
+        .unwrap();
+
+        let stale_trades_ignored = IntCounter::new(
+            "state_map_stale_trades_total",
+            "Trades older than the one the token state comes from, not applied to it",
+        )
+        .unwrap();
+
+        let http_requests = HistogramVec::new(
+            HistogramOpts::new("http_request_duration_seconds", "HTTP request latencies"),
+            &["method", "path", "status"],
+        )
+        .unwrap();
+
+        let leader = IntGauge::new(
+            "leader",
+            "1 while this replica holds the ingest leadership and writes",
+        )
+        .unwrap();
+
+        let leadership_changes = IntCounterVec::new(
+            Opts::new(
+                "leadership_changes_total",
+                "Times this replica acquired or lost the ingest leadership",
+            ),
+            &["change"],
+        )
+        .unwrap();
+
+        let datasource_updates = IntCounterVec::new(
+            Opts::new(
+                "datasource_updates_total",
+                "Updates received from each redundant datasource, duplicates included",
+            ),
+            &["source"],
+        )
+        .unwrap();
+
+        let datasource_first_arrivals = IntCounterVec::new(
+            Opts::new(
+                "datasource_first_arrivals_total",
+                "Transactions a datasource delivered before any other one",
+            ),
+            &["source"],
+        )
+        .unwrap();
+
+        let datasource_restarts = IntCounterVec::new(
+            Opts::new(
+                "datasource_restarts_total",
+                "Number of times each redundant datasource was restarted after it stopped",
+            ),
+            &["source"],
+        )
+        .unwrap();
+
+        let datasource_arrival_lag = HistogramVec::new(
+            HistogramOpts::new(
+                "datasource_arrival_lag_seconds",
+                "Time a datasource delivered a transaction after the first datasource that did",
+            )
+            .buckets(vec![
+                0.0, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0,
+            ]),
+            &["source"],
+        )
+        .unwrap();
+
+        let datasource_block_latency = HistogramVec::new(
+            HistogramOpts::new(
+                "datasource_block_latency_seconds",
+                "Time between the block time of a transaction and its arrival from a datasource",
+            )
+            .buckets(vec![0.5, 1.0, 2.0, 3.0, 5.0, 10.0, 30.0, 60.0, 300.0]),
+            &["source"],
+        )
+        .unwrap();
+
+        let processor_queue_depth = IntGaugeVec::new(
+            Opts::new(
+                "processor_queue_depth",
+                "Decoded events waiting for a processor worker by worker",
+            ),
+            &["worker"],
+        )
+        .unwrap();
+
+        registry.register(Box::new(events_decoded.clone())).unwrap();
+        registry
+            .register(Box::new(processing_latency.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(trade_buffer_depth.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(trade_batch_target.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(trade_writes_in_flight.clone()))
+            .unwrap();
+        registry.register(Box::new(trades_dropped.clone())).unwrap();
+        registry.register(Box::new(db_batch_size.clone())).unwrap();
+        registry.register(Box::new(db_batch_latency.clone())).unwrap();
+        registry.register(Box::new(db_errors.clone())).unwrap();
+        registry.register(Box::new(sol_price_age.clone())).unwrap();
+        registry.register(Box::new(state_map_size.clone())).unwrap();
+        registry
+            .register(Box::new(state_map_lookups.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(state_map_reloads.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(state_map_evictions.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(stale_trades_ignored.clone()))
+            .unwrap();
+        registry.register(Box::new(http_requests.clone())).unwrap();
+        registry.register(Box::new(leader.clone())).unwrap();
+        registry
+            .register(Box::new(leadership_changes.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(datasource_updates.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(datasource_first_arrivals.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(datasource_restarts.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(datasource_arrival_lag.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(datasource_block_latency.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(processor_queue_depth.clone()))
+            .unwrap();
+
+        Self {
+            registry,
+            events_decoded,
+            processing_latency,
+            trade_buffer_depth,
+            trade_batch_target,
+            trade_writes_in_flight,
+            trades_dropped,
+            db_batch_size,
+            db_batch_latency,
+            db_errors,
+            sol_price_age,
+            state_map_size,
+            state_map_lookups,
+            state_map_reloads,
+            state_map_evictions,
+            stale_trades_ignored,
+            http_requests,
+            leader,
+            leadership_changes,
+            datasource_updates,
+            datasource_first_arrivals,
+            datasource_restarts,
+            datasource_arrival_lag,
+            datasource_block_latency,
+            processor_queue_depth,
+            sol_price_updated_at: AtomicI64::new(0),
+            last_event_at: AtomicI64::new(0),
+            carbon_counters: RwLock::new(HashMap::new()),
+            carbon_gauges: RwLock::new(HashMap::new()),
+            carbon_histograms: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn mark_sol_price_updated(&self) {
+        self.sol_price_updated_at
+            .store(Utc::now().timestamp(), Ordering::Relaxed);
+    }
+
+    pub fn mark_event_processed(&self) {
+        self.last_event_at
+            .store(Utc::now().timestamp(), Ordering::Relaxed);
+    }
+
+    // Seconds since the last SOL price refresh, None if the price was never fetched
+    pub fn sol_price_age_secs(&self) -> Option<i64> {
+        age_secs(&self.sol_price_updated_at)
+    }
+
+    // Seconds since the last decoded Pump.fun event, None if nothing was decoded yet
+    pub fn last_event_age_secs(&self) -> Option<i64> {
+        age_secs(&self.last_event_at)
+    }
+
+    // Decoded events waiting in the queues of all the processor workers
+    pub fn processor_backlog(&self) -> i64 {
+        self.processor_queue_depth
+            .collect()
+            .iter()
+            .flat_map(|family| family.get_metric())
+            .map(|metric| metric.get_gauge().get_value() as i64)
+            .sum()
+    }
+
+    // Records the size, latency and outcome of a DB batch write
+    pub fn observe_db_batch(&self, operation: &str, rows: usize, started: Instant, failed: bool) {
+        self.db_batch_size
+            .with_label_values(&[operation])
+            .observe(rows as f64);
+        self.db_batch_latency
+            .with_label_values(&[operation])
+            .observe(started.elapsed().as_secs_f64());
+
+        if failed {
+            self.db_errors.with_label_values(&[operation]).inc();
+        }
+    }
+
+    // Encodes every registered metric in the Prometheus text format
+    pub fn render(&self) -> String {
+        if let Some(age) = self.sol_price_age_secs() {
+            self.sol_price_age.set(age as f64);
+        }
+
+        let mut buffer = Vec::new();
+
+        if let Err(err) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+            tracing::error!("Failed to encode metrics: {}", err);
+        }
+
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+
+    fn carbon_counter(&self, name: &str) -> Option<IntCounter> {
+        if let Some(counter) = self.carbon_counters.read().unwrap().get(name) {
+            return Some(counter.clone());
+        }
+
+        let counter = IntCounter::new(
+            format!("carbon_{}", sanitize(name)),
+            format!("carbon counter {}", name),
+        )
+        .ok()?;
+
+        self.registry.register(Box::new(counter.clone())).ok()?;
+        self.carbon_counters
+            .write()
+            .unwrap()
+            .insert(name.to_string(), counter.clone());
+
+        Some(counter)
+    }
+
+    fn carbon_gauge(&self, name: &str) -> Option<Gauge> {
+        if let Some(gauge) = self.carbon_gauges.read().unwrap().get(name) {
+            return Some(gauge.clone());
+        }
+
+        let gauge = Gauge::new(
+            format!("carbon_{}", sanitize(name)),
+            format!("carbon gauge {}", name),
+        )
+        .ok()?;
+
+        self.registry.register(Box::new(gauge.clone())).ok()?;
+        self.carbon_gauges
+            .write()
+            .unwrap()
+            .insert(name.to_string(), gauge.clone());
+
+        Some(gauge)
+    }
+
+    fn carbon_histogram(&self, name: &str) -> Option<Histogram> {
+        if let Some(histogram) = self.carbon_histograms.read().unwrap().get(name) {
+            return Some(histogram.clone());
+        }
+
+        let histogram = Histogram::with_opts(HistogramOpts::new(
+            format!("carbon_{}", sanitize(name)),
+            format!("carbon histogram {}", name),
+        ))
+        .ok()?;
+
+        self.registry.register(Box::new(histogram.clone())).ok()?;
+        self.carbon_histograms
+            .write()
+            .unwrap()
+            .insert(name.to_string(), histogram.clone());
+
+        Some(histogram)
+    }
+}
+
+fn age_secs(timestamp: &AtomicI64) -> Option<i64> {
+    let timestamp = timestamp.load(Ordering::Relaxed);
+
+    if timestamp == 0 {
+        return None;
+    }
+
+    Some(Utc::now().timestamp() - timestamp)
+}
+
+// Prometheus metric names only allow [a-zA-Z0-9_:]
+fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+// carbon Metrics implementation, the pipeline and the processor report through it into the Prometheus registry
+pub struct PrometheusMetrics;
+
+#[async_trait]
+impl Metrics for PrometheusMetrics {
+    async fn initialize(&self) -> CarbonResult<()> {
+        LazyLock::force(&METRICS);
+        Ok(())
+    }
+
+    async fn flush(&self) -> CarbonResult<()> {
+        // Prometheus pulls from /metrics, nothing to push
+        Ok(())
+    }
+
+    async fn shutdown(&self) -> CarbonResult<()> {
+        Ok(())
+    }
+
+    async fn update_gauge(&self, name: &str, value: f64) -> CarbonResult<()> {
+        if name == STATE_MAP_SIZE {
+            metrics().state_map_size.set(value as i64);
+        } else if let Some(gauge) = metrics().carbon_gauge(name) {
+            gauge.set(value);
+        }
+
+        Ok(())
+    }
+
+    async fn increment_counter(&self, name: &str, value: u64) -> CarbonResult<()> {
+        if let Some(event_type) = name.strip_prefix(EVENTS_DECODED_PREFIX) {
+            metrics().mark_event_processed();
+            metrics()
+                .events_decoded
+                .with_label_values(&[event_type])
+                .inc_by(value);
+        } else if let Some(counter) = metrics().carbon_counter(name) {
+            counter.inc_by(value);
+        }
+
+        Ok(())
+    }
+
+    async fn record_histogram(&self, name: &str, value: f64) -> CarbonResult<()> {
+        if name == PROCESSING_LATENCY {
+            metrics().processing_latency.observe(value);
+        } else if let Some(histogram) = metrics().carbon_histogram(name) {
+            histogram.observe(value);
+        }
+
+        Ok(())
+    }
+}
+
+// Actix middleware recording a latency histogram per method, route pattern and status
+pub async fn track_http_metrics(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let started = Instant::now();
+    let method = req.method().to_string();
+    let path = req
+        .match_pattern()
+        .unwrap_or_else(|| "unmatched".to_string());
+
+    let res = next.call(req).await?;
+
+    metrics()
+        .http_requests
+        .with_label_values(&[method.as_str(), path.as_str(), res.status().as_str()])
+        .observe(started.elapsed().as_secs_f64());
+
+    Ok(res)
+}
+
+//* This endpoint exposes the Prometheus metrics */
+#[get("/metrics")]
+pub async fn get_metrics() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(metrics().render())
+}
@@ -0,0 +1,72 @@
+use utoipa::{
+    openapi::security::{ApiKey, ApiKeyValue, SecurityScheme},
+    Modify, OpenApi,
+};
+
+use crate::{
+    alerts::{__path_get_alert_stream, __path_get_alerts},
+    auth::{
+        __path_delete_api_key, __path_get_api_key_usage, __path_get_api_keys, __path_post_api_key,
+    },
+    errors::ErrorResponse,
+    health::{__path_get_healthz, __path_get_readyz},
+    ranking::{__path_get_king_of_the_hill, __path_get_trending},
+    tokens::{__path_get_token, __path_get_token_candles, __path_get_tokens},
+    webhooks::{
+        __path_delete_webhook_by_id, __path_disable_webhook, __path_enable_webhook,
+        __path_get_webhook_deliveries, __path_get_webhooks, __path_post_webhook,
+    },
+};
+
+// OpenAPI document of the REST API, served at /openapi.json and browsable at /swagger-ui/.
+// The GraphQL endpoint documents itself through introspection.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Pump.fun indexer API"),
+    paths(
+        get_tokens,
+        get_token,
+        get_token_candles,
+        get_trending,
+        get_king_of_the_hill,
+        get_alerts,
+        get_alert_stream,
+        post_webhook,
+        get_webhooks,
+        delete_webhook_by_id,
+        enable_webhook,
+        disable_webhook,
+        get_webhook_deliveries,
+        post_api_key,
+        get_api_keys,
+        delete_api_key,
+        get_api_key_usage,
+        get_healthz,
+        get_readyz,
+    ),
+    components(schemas(ErrorResponse)),
+    modifiers(&ApiKeyAuth),
+    security(("api_key" = [])),
+    tags(
+        (name = "tokens", description = "Indexed tokens and rankings"),
+        (name = "alerts", description = "Rug-pull and dev-dump alerts"),
+        (name = "webhooks", description = "Outbound webhook subscriptions"),
+        (name = "admin", description = "API key management, requires an admin key"),
+        (name = "health", description = "Liveness and readiness probes"),
+    )
+)]
+pub struct ApiDoc;
+
+// Every endpoint but the probes takes the API key in the X-API-Key header
+struct ApiKeyAuth;
+
+impl Modify for ApiKeyAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme(
+                "api_key",
+                SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("X-API-Key"))),
+            );
+        }
+    }
+}
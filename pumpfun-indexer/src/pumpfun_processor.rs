@@ -0,0 +1,491 @@
+use std::{sync::Arc, time::Instant};
+
+use async_trait::async_trait;
+use carbon_core::{
+    error::CarbonResult, instruction::InstructionProcessorInputType, metrics::MetricsCollection,
+    processor::Processor, transaction::TransactionMetadata,
+};
+use carbon_pumpfun_decoder::instructions::PumpfunInstruction;
+use redis::aio::MultiplexedConnection;
+use sqlx::types::chrono::Utc;
+use tokio::sync::{mpsc::UnboundedSender, RwLock};
+use tokio_util::task::TaskTracker;
+use tracing::{Instrument, Span};
+
+use crate::{
+    alerts::AlertEngine,
+    bonding_curve::{bonding_curve_progress, market_cap_usd, TOKEN_DECIMALS, TOTAL_SUPPLY},
+    cache::CacheInvalidator,
+    checkpoint::{CheckpointTracker, SeenEvents, LIVE_DATASOURCE},
+    config::EventEncoding,
+    events::{EventEmitter, EventEnvelope, EventPayload},
+    funding::{extract_system_transfers, FundingObservation},
+    helpers::store_in_redis,
+    hooks::Hooks,
+    leader::Leadership,
+    metrics::{metrics, EVENTS_DECODED_PREFIX, PROCESSING_LATENCY, STATE_MAP_SIZE},
+    state::Lookup,
+    store::Store,
+    types::{BondStatus, BondingCurveAndMcInfo},
+    webhooks::WebhookEmitter,
+    BondingMcStateMap,
+};
+
+// Applies the decoded Pump.fun events to the store, the bonding state map and the Redis trade channel. Built with
+// `PumpfunInstructionProcessor::builder`, everything but the store and Redis is optional.
+pub struct PumpfunInstructionProcessor {
+    store: Store,
+    redis: MultiplexedConnection,
+    bonding_state_map: BondingMcStateMap,
+    sol_price: Arc<RwLock<f64>>,
+    checkpoint: CheckpointTracker,
+    seen_events: SeenEvents,
+    funding: Option<UnboundedSender<FundingObservation>>,
+    alerts: Option<AlertEngine>,
+    webhooks: Option<WebhookEmitter>,
+    events: EventEmitter,
+    encoding: EventEncoding,
+    cache: CacheInvalidator,
+    leadership: Leadership,
+    publishes: TaskTracker,
+}
+
+pub struct ProcessorBuilder {
+    processor: PumpfunInstructionProcessor,
+    hooks: Hooks,
+}
+
+impl ProcessorBuilder {
+    // Tokens whose trades are stored, preloaded with `Store::load_bonding_state`. Starts empty, the stored tokens are
+    // then loaded on their first trade.
+    pub fn bonding_state(mut self, bonding_state_map: BondingMcStateMap) -> Self {
+        self.processor.bonding_state_map = bonding_state_map;
+        self
+    }
+
+    // SOL price in USD the market caps are computed with, kept up to date by the caller
+    pub fn sol_price(mut self, sol_price: Arc<RwLock<f64>>) -> Self {
+        self.processor.sol_price = sol_price;
+        self
+    }
+
+    // Tracks the processed slots, defaults to the tracker of the live datasource which is never flushed
+    pub(crate) fn checkpoint(mut self, checkpoint: CheckpointTracker) -> Self {
+        self.processor.checkpoint = checkpoint;
+        self
+    }
+
+    pub(crate) fn funding(mut self, funding: UnboundedSender<FundingObservation>) -> Self {
+        self.processor.funding = Some(funding);
+        self
+    }
+
+    pub(crate) fn alerts(mut self, alerts: AlertEngine) -> Self {
+        self.processor.alerts = Some(alerts);
+        self
+    }
+
+    pub(crate) fn webhooks(mut self, webhooks: WebhookEmitter) -> Self {
+        self.processor.webhooks = Some(webhooks);
+        self
+    }
+
+    pub(crate) fn events(mut self, events: EventEmitter) -> Self {
+        self.processor.events = events;
+        self
+    }
+
+    // Encoding of the envelopes published on the Redis trade channel
+    pub fn encoding(mut self, encoding: EventEncoding) -> Self {
+        self.processor.encoding = encoding;
+        self
+    }
+
+    pub fn cache(mut self, cache: CacheInvalidator) -> Self {
+        self.processor.cache = cache;
+        self
+    }
+
+    // Tracks the publishes on the Redis trade channel, the caller waits for it before the flushers stop
+    pub fn publishes(mut self, publishes: TaskTracker) -> Self {
+        self.processor.publishes = publishes;
+        self
+    }
+
+    // While this replica is a standby only the in-memory state is updated, nothing is written or emitted
+    pub(crate) fn leadership(mut self, leadership: Leadership) -> Self {
+        self.processor.leadership = leadership;
+        self
+    }
+
+    pub fn on_create<F: Fn(&EventEnvelope) + Send + Sync + 'static>(mut self, hook: F) -> Self {
+        self.hooks.on_create(hook);
+        self
+    }
+
+    pub fn on_trade<F: Fn(&EventEnvelope) + Send + Sync + 'static>(mut self, hook: F) -> Self {
+        self.hooks.on_trade(hook);
+        self
+    }
+
+    pub fn on_graduate<F: Fn(&EventEnvelope) + Send + Sync + 'static>(mut self, hook: F) -> Self {
+        self.hooks.on_graduate(hook);
+        self
+    }
+
+    pub fn build(self) -> PumpfunInstructionProcessor {
+        let mut processor = self.processor;
+
+        processor.events = processor.events.with_hooks(self.hooks);
+
+        processor
+    }
+}
+
+#[async_trait]
+impl Processor for PumpfunInstructionProcessor {
+    type InputType = InstructionProcessorInputType<PumpfunInstruction>;
+
+    //This function is called whenever any kind of event is emitted from the Pumpfun program.
+    async fn process(
+        &mut self,
+        data: Self::InputType,
+        metrics: Arc<MetricsCollection>,
+    ) -> CarbonResult<()> {
+        let started = Instant::now();
+
+        let transaction_metadata = data.0.transaction_metadata.clone();
+        let signature = transaction_metadata.signature.to_string();
+
+        self.checkpoint
+            .dispatched(transaction_metadata.slot, &signature);
+
+        // The backfill and the live websocket overlap around the checkpoint, so skip events that were already processed.
+        // A standby doesn't remember what it processed, the failover backfill replays it once it takes over and the
+        // events have to be written then.
+        let event_key = format!("{}:{}:{}", signature, data.0.stack_height, data.0.index);
+
+        if self.leadership.is_leader() && !self.seen_events.insert(event_key.clone()) {
+            tracing::debug!(%signature, "Skipping already processed event");
+            return Ok(());
+        }
+
+        let pumpfun_instruction: PumpfunInstruction = data.1.data;
+
+        let event_type = match &pumpfun_instruction {
+            PumpfunInstruction::CreateEvent(_) => "create",
+            PumpfunInstruction::TradeEvent(_) => "trade",
+            PumpfunInstruction::CompleteEvent(_) => "complete",
+            _ => "other",
+        };
+
+        metrics
+            .increment_counter(&format!("{}{}", EVENTS_DECODED_PREFIX, event_type), 1)
+            .await?;
+
+        let span = tracing::info_span!(
+            "process_event",
+            %signature,
+            slot = transaction_metadata.slot,
+            event = event_type,
+            mint = tracing::field::Empty,
+        );
+
+        self.handle_instruction(
+            pumpfun_instruction,
+            &event_key,
+            &transaction_metadata,
+            metrics.clone(),
+        )
+        .instrument(span)
+        .await?;
+
+        self.checkpoint
+            .observe(transaction_metadata.slot, signature)
+            .await;
+
+        // Processed one at a time, everything dispatched so far has been processed
+        self.checkpoint.completed(u64::MAX);
+
+        metrics
+            .record_histogram(PROCESSING_LATENCY, started.elapsed().as_secs_f64())
+            .await?;
+
+        Ok(())
+    }
+}
+
+impl PumpfunInstructionProcessor {
+    pub fn builder(store: Store, redis: MultiplexedConnection) -> ProcessorBuilder {
+        ProcessorBuilder {
+            processor: Self {
+                store,
+                redis,
+                bonding_state_map: BondingMcStateMap::default(),
+                sol_price: Arc::new(RwLock::new(0.0)),
+                checkpoint: CheckpointTracker::new(LIVE_DATASOURCE),
+                seen_events: SeenEvents::default(),
+                funding: None,
+                alerts: None,
+                webhooks: None,
+                events: EventEmitter::default(),
+                encoding: EventEncoding::default(),
+                cache: CacheInvalidator::default(),
+                leadership: Leadership::default(),
+                publishes: TaskTracker::new(),
+            },
+            hooks: Hooks::default(),
+        }
+    }
+
+    // Makes sure the state of a traded token is in memory, reloading it from the DB if it was evicted. Returns false
+    // for the tokens that aren't stored or have graduated.
+    async fn ensure_tracked(&self, mint: &str) -> bool {
+        match self.bonding_state_map.lookup(mint) {
+            Lookup::Hit => return true,
+            Lookup::Unknown => return false,
+            Lookup::Miss => {}
+        }
+
+        match self.store.reload_token_state(mint).await {
+            Ok(Some(info)) => {
+                metrics()
+                    .state_map_reloads
+                    .with_label_values(&["found"])
+                    .inc();
+                self.bonding_state_map.reload(info);
+                true
+            }
+            Ok(None) => {
+                metrics()
+                    .state_map_reloads
+                    .with_label_values(&["not_found"])
+                    .inc();
+                self.bonding_state_map.mark_unknown(mint);
+                false
+            }
+            // Skipped without remembering the mint, the next trade retries
+            Err(_) => {
+                metrics()
+                    .state_map_reloads
+                    .with_label_values(&["error"])
+                    .inc();
+                false
+            }
+        }
+    }
+
+    // Applies a decoded event to the DB, the in-memory state map and the Redis trade channel
+    async fn handle_instruction(
+        &mut self,
+        pumpfun_instruction: PumpfunInstruction,
+        event_key: &str,
+        transaction_metadata: &TransactionMetadata,
+        metrics: Arc<MetricsCollection>,
+    ) -> CarbonResult<()> {
+        let slot = transaction_metadata.slot;
+        let signature = transaction_metadata.signature.to_string();
+        let leader = self.leadership.is_leader();
+
+        //Pattern matching to check which event is being processed
+        match pumpfun_instruction {
+            // This is the event when a new token is created
+            PumpfunInstruction::CreateEvent(create_event) => {
+                Span::current().record("mint", tracing::field::display(&create_event.mint));
+                tracing::info!(
+                    name = %create_event.name,
+                    symbol = %create_event.symbol,
+                    creator = %create_event.user,
+                    "New token created"
+                );
+                if leader {
+                    if let Some(webhooks) = &self.webhooks {
+                        webhooks.launch(&create_event, slot, &signature);
+                    }
+                    self.events
+                        .create(event_key, &create_event, slot, &signature)
+                        .await;
+
+                    self.store
+                        .create_token(create_event.clone(), slot, signature)
+                        .await;
+
+                    self.cache.token_changed(&create_event.mint.to_string());
+                }
+
+                // The standbys feed the alert rules too so their windows are warm, the dispatcher drops what they fire
+                if let Some(alerts) = &mut self.alerts {
+                    alerts.on_create(
+                        create_event.mint.to_string(),
+                        create_event.user.to_string(),
+                        transaction_metadata
+                            .block_time
+                            .unwrap_or_else(|| Utc::now().timestamp()),
+                    );
+                }
+
+                self.bonding_state_map.insert(BondingCurveAndMcInfo {
+                    contract_address: create_event.mint.to_string(),
+                    bonding_curve_address: create_event.bonding_curve.to_string(),
+                    bonding_curve_percentage: 0,
+                    market_cap: Some(0),
+                    market_cap_slot: None,
+                });
+
+                metrics
+                    .update_gauge(STATE_MAP_SIZE, self.bonding_state_map.len() as f64)
+                    .await?;
+            }
+            // This is the event when a trade event occurs for any token
+            PumpfunInstruction::TradeEvent(trade_event) => {
+                Span::current().record("mint", tracing::field::display(&trade_event.mint));
+
+                let sol_price = *self.sol_price.read().await;
+
+                //Get the market cap based on the virtual reserves, total supply, and latest SOL price in USD
+                let market_cap = market_cap_usd(
+                    trade_event.virtual_sol_reserves,
+                    trade_event.virtual_token_reserves,
+                    TOKEN_DECIMALS,
+                    TOTAL_SUPPLY,
+                    sol_price,
+                );
+
+                let envelope = EventEnvelope::trade(
+                    event_key.to_string(),
+                    &trade_event,
+                    slot,
+                    &signature,
+                    sol_price,
+                    market_cap,
+                );
+
+                if leader {
+                    self.events.emit(envelope.clone()).await;
+                }
+
+                let progress = bonding_curve_progress(trade_event.virtual_token_reserves as i128);
+
+                let curve_result = match i64::try_from(progress) {
+                    Ok(value) => value,
+                    Err(_) => {
+                        tracing::error!("Failed to convert bonding curve progress: {}", progress);
+                        0
+                    }
+                };
+
+                let mint = trade_event.mint.to_string();
+
+                // Tokens evicted from memory are reloaded before the trade is applied
+                let tracked = self.ensure_tracked(&mint).await;
+
+                //Update the bonding curve percentage and market cap of the token, it is flushed with the next batch
+                // if the token exists in our DB and here in our Hashmap, then only process it
+                if tracked
+                    && self
+                        .bonding_state_map
+                        .update(&mint, curve_result as i32, market_cap, slot)
+                {
+                    tracing::debug!(market_cap, curve_result, "Updated token state");
+
+                    // The create event of the token may predate this process, its creator and launch time come from
+                    // the DB then so the creator and top holder rules still apply
+                    if let Some(alerts) = self.alerts.as_mut().filter(|a| !a.is_tracking(&mint)) {
+                        if let Ok(Some(launch)) = self.store.token_launch(&mint).await {
+                            alerts.on_create(
+                                mint.clone(),
+                                launch.creator_address,
+                                launch.created_at.timestamp(),
+                            );
+                        }
+                    }
+
+                    if let (Some(alerts), EventPayload::Trade(trade)) =
+                        (&mut self.alerts, &envelope.payload)
+                    {
+                        alerts.on_trade(&mint, trade, Some(market_cap), slot, &signature);
+                    }
+
+                    if !leader {
+                        return Ok(());
+                    }
+
+                    if let Some(funding) = &self.funding {
+                        // Direct SOL transfers into the trader inside this transaction are funding evidence
+                        for (from, to, _) in extract_system_transfers(
+                            &transaction_metadata.message,
+                            Some(&transaction_metadata.meta),
+                        ) {
+                            if to == trade_event.user && from != to {
+                                let _ = funding.send(FundingObservation::Transfer {
+                                    funder: from.to_string(),
+                                    wallet: to.to_string(),
+                                    slot,
+                                    signature: signature.clone(),
+                                });
+                            }
+                        }
+
+                        let _ =
+                            funding.send(FundingObservation::Wallet(trade_event.user.to_string()));
+                    }
+
+                    if let Some(webhooks) = &self.webhooks {
+                        webhooks.trade(&trade_event, market_cap, slot, &signature);
+                    }
+
+                    let mut redis_clone = self.redis.clone();
+                    let encoding = self.encoding;
+
+                    //create a new thread that publishes the data in the "trade" channel to keep this block non-blocking
+                    self.publishes.spawn(
+                        async move {
+                            store_in_redis(&mut redis_clone, envelope, encoding).await;
+                        }
+                        .in_current_span(),
+                    );
+                }
+            }
+            // This is the event when a token is graduated
+            PumpfunInstruction::CompleteEvent(complete_event) => {
+                Span::current().record("mint", tracing::field::display(&complete_event.mint));
+                tracing::info!("Bonded");
+
+                // No more trades happen on the curve, its last changes are still flushed
+                self.bonding_state_map
+                    .remove(&complete_event.mint.to_string(), "graduated");
+
+                if !leader {
+                    return Ok(());
+                }
+
+                if let Some(webhooks) = &self.webhooks {
+                    webhooks.graduation(&complete_event.mint, slot, &signature);
+                }
+                self.events
+                    .complete(event_key, &complete_event, slot, &signature)
+                    .await;
+
+                //Change the status of the token to "Graduated" in the DB
+                self.store
+                    .change_status(BondStatus::Graduated, complete_event.mint)
+                    .await;
+
+                self.cache.token_changed(&complete_event.mint.to_string());
+
+                self.events
+                    .status_change(
+                        event_key,
+                        complete_event.mint.to_string(),
+                        BondStatus::Graduated,
+                        slot,
+                        &signature,
+                    )
+                    .await;
+            }
+            _ => {}
+        };
+
+        Ok(())
+    }
+}
@@ -0,0 +1,306 @@
+use std::{sync::Arc, time::Duration};
+
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use serde::Deserialize;
+use sqlx::{types::chrono::Utc, PgPool};
+use utoipa::IntoParams;
+
+use crate::{
+    cache::{ResponseCache, TRENDING_TAG},
+    config::RankingConfig,
+    db::rank::{fetch_king_of_the_hill, fetch_token_activity, fetch_trending, save_token_ranks},
+    errors::{ApiError, ErrorResponse},
+    types::{RankedToken, TokenActivity, TokenRank},
+};
+
+const DEFAULT_TRENDING_LIMIT: i64 = 50;
+const MAX_TRENDING_LIMIT: i64 = 200;
+
+// Raw (un-normalized) signals of a token before weighting
+struct Signals {
+    volume_sol: f64,
+    unique_buyers: i64,
+    holder_growth: i64,
+    curve_velocity: f64,
+    market_cap_momentum: f64,
+}
+
+fn signals(activity: &TokenActivity, now_secs: i64) -> Signals {
+    let market_cap = activity.market_cap.unwrap_or(0);
+
+    // Velocities are measured against the snapshot saved by the previous ranking run
+    let elapsed_secs = activity
+        .previous_updated_at
+        .map(|at| (now_secs - at.timestamp()).max(1) as f64);
+
+    let curve_velocity = match (activity.previous_bonding_curve_percentage, elapsed_secs) {
+        (Some(previous), Some(elapsed)) => {
+            (activity.bonding_curve_percentage - previous) as f64 / elapsed
+        }
+        _ => 0.0,
+    };
+
+    let market_cap_momentum = match (activity.previous_market_cap, elapsed_secs) {
+        (Some(previous), Some(_)) if previous > 0 => (market_cap - previous) as f64 / previous as f64,
+        _ => 0.0,
+    };
+
+    Signals {
+        volume_sol: activity.decayed_volume_sol,
+        unique_buyers: activity.unique_buyers,
+        holder_growth: activity.holders - activity.holders_before,
+        curve_velocity,
+        market_cap_momentum,
+    }
+}
+
+// Min-max normalization into 0..1 so that signals with different units can be weighted together
+fn normalize(values: &[f64]) -> Vec<f64> {
+    let min = values.iter().cloned().fold(f64::INFINITY, f64::min);
+    let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+
+    if !min.is_finite() || !max.is_finite() || max - min <= f64::EPSILON {
+        return vec![0.0; values.len()];
+    }
+
+    values.iter().map(|v| (v - min) / (max - min)).collect()
+}
+
+// Scores every active token with the configured weights and returns them ordered by rank (1 = hottest)
+pub fn compute_ranks(activity: &[TokenActivity], config: &RankingConfig) -> Vec<TokenRank> {
+    let now_secs = Utc::now().timestamp();
+
+    let signals: Vec<Signals> = activity.iter().map(|a| signals(a, now_secs)).collect();
+
+    let volume = normalize(&signals.iter().map(|s| s.volume_sol).collect::<Vec<_>>());
+    let buyers = normalize(
+        &signals
+            .iter()
+            .map(|s| s.unique_buyers as f64)
+            .collect::<Vec<_>>(),
+    );
+    let holder_growth = normalize(
+        &signals
+            .iter()
+            .map(|s| s.holder_growth as f64)
+            .collect::<Vec<_>>(),
+    );
+    let curve_velocity = normalize(&signals.iter().map(|s| s.curve_velocity).collect::<Vec<_>>());
+    let momentum = normalize(
+        &signals
+            .iter()
+            .map(|s| s.market_cap_momentum)
+            .collect::<Vec<_>>(),
+    );
+
+    let mut ranks: Vec<TokenRank> = activity
+        .iter()
+        .zip(signals.iter())
+        .enumerate()
+        .map(|(i, (activity, signal))| TokenRank {
+            token_id: activity.token_id,
+            rank: 0,
+            score: config.volume_weight * volume[i]
+                + config.buyers_weight * buyers[i]
+                + config.holder_growth_weight * holder_growth[i]
+                + config.curve_velocity_weight * curve_velocity[i]
+                + config.market_cap_momentum_weight * momentum[i],
+            volume_sol: signal.volume_sol,
+            unique_buyers: signal.unique_buyers,
+            holder_growth: signal.holder_growth,
+            curve_velocity: signal.curve_velocity,
+            market_cap_momentum: signal.market_cap_momentum,
+            bonding_curve_percentage: activity.bonding_curve_percentage,
+            market_cap: activity.market_cap.unwrap_or(0),
+        })
+        .collect();
+
+    ranks.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+    for (i, rank) in ranks.iter_mut().enumerate() {
+        rank.rank = i as i32 + 1;
+    }
+
+    ranks
+}
+
+// Recomputes the ranking from the trades in the configured window and stores it in the token_rank table
+#[tracing::instrument(skip_all)]
+pub async fn refresh_rankings(db: Arc<PgPool>, config: &RankingConfig) {
+    let now = Utc::now();
+    let window_start = now - sqlx::types::chrono::Duration::seconds(config.window_secs);
+
+    let activity =
+        match fetch_token_activity(db.clone(), now, window_start, config.half_life_secs).await {
+            Ok(activity) => activity,
+            Err(err) => {
+                tracing::error!(error = ?err, "Failed to refresh rankings");
+                return;
+            }
+        };
+
+    let ranks = compute_ranks(&activity, config);
+
+    tracing::debug!(tokens = ranks.len(), "Computed token ranks");
+
+    save_token_ranks(db, ranks).await;
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct TrendingQuery {
+    // Number of tokens to return, 1 to 200 (default 50)
+    pub limit: Option<i64>,
+}
+
+//* This endpoint returns the highest ranked tokens */
+//* Use http://localhost:8000/tokens/trending?limit=20 */
+#[utoipa::path(
+    tag = "tokens",
+    params(TrendingQuery),
+    responses(
+        (status = 200, description = "Ranked tokens, hottest first", body = [RankedToken]),
+        (status = 304, description = "Unchanged since the ETag sent in If-None-Match"),
+        (status = 500, body = ErrorResponse),
+    )
+)]
+#[get("/tokens/trending")]
+pub async fn get_trending(
+    req: HttpRequest,
+    db: web::Data<Arc<PgPool>>,
+    cache: web::Data<ResponseCache>,
+    query: web::Query<TrendingQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_TRENDING_LIMIT)
+        .clamp(1, MAX_TRENDING_LIMIT);
+
+    cache
+        .json(
+            &req,
+            &format!("trending:{}", limit),
+            &[TRENDING_TAG.to_string()],
+            Duration::from_secs(cache.config().trending_ttl_secs),
+            async { Ok(fetch_trending(db.get_ref(), limit).await?) },
+        )
+        .await
+}
+
+//* This endpoint returns the non graduated token with the highest market cap among the ranked tokens */
+#[utoipa::path(
+    tag = "tokens",
+    responses(
+        (status = 200, body = RankedToken),
+        (status = 304, description = "Unchanged since the ETag sent in If-None-Match"),
+        (status = 404, description = "No ranked token is bonding", body = ErrorResponse),
+        (status = 500, body = ErrorResponse),
+    )
+)]
+#[get("/tokens/king-of-the-hill")]
+pub async fn get_king_of_the_hill(
+    req: HttpRequest,
+    db: web::Data<Arc<PgPool>>,
+    cache: web::Data<ResponseCache>,
+) -> Result<HttpResponse, ApiError> {
+    cache
+        .json(
+            &req,
+            "king-of-the-hill",
+            &[TRENDING_TAG.to_string()],
+            Duration::from_secs(cache.config().trending_ttl_secs),
+            async {
+                fetch_king_of_the_hill(db.get_ref()).await?.ok_or_else(|| {
+                    ApiError::NotFound("No token is currently king of the hill".to_string())
+                })
+            },
+        )
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::types::chrono::Duration as ChronoDuration;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::types::BondStatus;
+
+    fn activity(volume_sol: f64, unique_buyers: i64) -> TokenActivity {
+        TokenActivity {
+            token_id: Uuid::new_v4(),
+            bond_status: BondStatus::NewlyLaunched,
+            bonding_curve_percentage: 10,
+            market_cap: Some(10_000),
+            decayed_volume_sol: volume_sol,
+            unique_buyers,
+            holders: 10,
+            holders_before: 10,
+            previous_bonding_curve_percentage: None,
+            previous_market_cap: None,
+            previous_updated_at: None,
+        }
+    }
+
+    fn config() -> RankingConfig {
+        RankingConfig {
+            volume_weight: 0.5,
+            buyers_weight: 0.5,
+            ..RankingConfig::default()
+        }
+    }
+
+    #[test]
+    fn normalizes_into_the_unit_range() {
+        assert_eq!(normalize(&[2.0, 4.0, 6.0]), vec![0.0, 0.5, 1.0]);
+        assert_eq!(normalize(&[-1.0, 1.0]), vec![0.0, 1.0]);
+    }
+
+    #[test]
+    fn normalizes_flat_and_empty_signals_to_zero() {
+        assert_eq!(normalize(&[3.0, 3.0, 3.0]), vec![0.0, 0.0, 0.0]);
+        assert_eq!(normalize(&[7.0]), vec![0.0]);
+        assert!(normalize(&[]).is_empty());
+    }
+
+    #[test]
+    fn ranks_the_hottest_token_first() {
+        let tokens = [activity(1.0, 1), activity(10.0, 5), activity(5.0, 10)];
+
+        let ranks = compute_ranks(&tokens, &config());
+
+        let order: Vec<Uuid> = ranks.iter().map(|rank| rank.token_id).collect();
+        let positions: Vec<i32> = ranks.iter().map(|rank| rank.rank).collect();
+
+        // The second and third tie on the weighted score, the first has the lowest of both signals
+        assert_eq!(positions, vec![1, 2, 3]);
+        assert_eq!(order[2], tokens[0].token_id);
+        assert!((ranks[0].score - ranks[1].score).abs() < 1e-9);
+        assert_eq!(ranks[2].score, 0.0);
+    }
+
+    #[test]
+    fn measures_velocities_against_the_previous_snapshot() {
+        let mut rising = activity(1.0, 1);
+        rising.bonding_curve_percentage = 30;
+        rising.market_cap = Some(20_000);
+        rising.previous_bonding_curve_percentage = Some(10);
+        rising.previous_market_cap = Some(10_000);
+        rising.previous_updated_at = Some(Utc::now() - ChronoDuration::seconds(10));
+
+        let flat = activity(1.0, 1);
+
+        let config = RankingConfig {
+            curve_velocity_weight: 1.0,
+            market_cap_momentum_weight: 1.0,
+            ..RankingConfig::default()
+        };
+
+        let ranks = compute_ranks(&[flat.clone(), rising.clone()], &config);
+
+        assert_eq!(ranks[0].token_id, rising.token_id);
+        assert!((ranks[0].curve_velocity - 2.0).abs() < 0.5);
+        assert!((ranks[0].market_cap_momentum - 1.0).abs() < 1e-9);
+        assert_eq!(ranks[1].curve_velocity, 0.0);
+        assert_eq!(ranks[1].market_cap_momentum, 0.0);
+    }
+}
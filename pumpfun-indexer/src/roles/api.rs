@@ -0,0 +1,219 @@
+use std::time::Duration;
+
+use actix_web::{middleware::from_fn, web, App, HttpServer};
+use redis::{PushKind, Value};
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
+
+use crate::{
+    alerts::{get_alert_stream, get_alerts, ALERTS_CHANNEL},
+    auth::{
+        authenticate, cors, delete_api_key, get_api_key_usage, get_api_keys, post_api_key,
+        run_usage_flusher, ApiAccess,
+    },
+    config::IndexerConfig,
+    db::trade::parse_trade_message,
+    errors::extractor_error,
+    events::EventEnvelope,
+    graphql::{build_schema, get_graphiql, get_graphql_ws, post_graphql},
+    health::{get_healthz, get_readyz, HealthThresholds},
+    metrics::{get_metrics, track_http_metrics},
+    openapi::ApiDoc,
+    ranking::{get_king_of_the_hill, get_trending},
+    roles::{connect_postgres, connect_redis, connect_response_cache, serve, Shutdown},
+    tokens::{get_token, get_token_candles, get_tokens},
+    types::Alert,
+    webhooks::{
+        delete_webhook_by_id, disable_webhook, enable_webhook, get_webhook_deliveries,
+        get_webhooks, post_webhook,
+    },
+};
+
+// Alerts a slow /alerts/stream subscriber can fall behind before it starts skipping
+const ALERT_STREAM_CAPACITY: usize = 256;
+
+// Events a slow GraphQL subscriber can fall behind before it starts skipping
+const LIVE_EVENTS_CAPACITY: usize = 1024;
+
+const RELAY_RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+// Forwards the trades and alerts published by the ingest processes to the subscribers of this process, until the
+// connection drops or the shutdown token is cancelled
+async fn relay_live_messages(
+    redis_url: &str,
+    live_events: &broadcast::Sender<EventEnvelope>,
+    alert_stream: &broadcast::Sender<Alert>,
+    shutdown: &CancellationToken,
+) -> Result<(), anyhow::Error> {
+    let (push_tx, mut push_rx) = tokio::sync::mpsc::unbounded_channel();
+
+    let mut redis = connect_redis(redis_url, Some(push_tx)).await?;
+
+    redis.psubscribe("trade").await?;
+    redis.subscribe(ALERTS_CHANNEL).await?;
+
+    loop {
+        let msg = tokio::select! {
+            _ = shutdown.cancelled() => return Ok(()),
+            msg = push_rx.recv() => match msg {
+                Some(msg) => msg,
+                None => return Err(anyhow::Error::msg("Redis connection closed")),
+            },
+        };
+
+        // No subscribers is not an error
+        match msg.kind {
+            PushKind::PMessage => {
+                if let Some(envelope) = parse_trade_message(msg) {
+                    let _ = live_events.send(envelope);
+                }
+            }
+            PushKind::Message => {
+                let Some(Value::BulkString(data)) = msg.data.get(1) else {
+                    continue;
+                };
+
+                match serde_json::from_slice::<Alert>(data) {
+                    Ok(alert) => {
+                        let _ = alert_stream.send(alert);
+                    }
+                    Err(err) => tracing::error!(error = ?err, "Failed to decode published alert"),
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+// Serves the REST and GraphQL API. Holds no ingestion state, everything is read from Postgres and the live
+// subscriptions are fed from Redis, so any number of instances can run behind a load balancer.
+pub async fn run(config: IndexerConfig) -> Result<(), anyhow::Error> {
+    let db = connect_postgres(&config).await?;
+
+    let api_redis = connect_redis(&config.redis_url, None).await?;
+
+    let shutdown = Shutdown::listen(&config);
+
+    let response_cache = connect_response_cache(&config).await;
+
+    if config.cache.enabled && !response_cache.is_shared() {
+        tracing::warn!(
+            "CACHE_REDIS_URL is not set, cached responses are only invalidated when they expire"
+        );
+    }
+
+    let (alert_stream, _) = broadcast::channel(ALERT_STREAM_CAPACITY);
+
+    // Processed trades for the GraphQL trade subscriptions
+    let (live_events, _) = broadcast::channel(LIVE_EVENTS_CAPACITY);
+
+    let redis_url = config.redis_url.clone();
+    let relay_live_events = live_events.clone();
+    let relay_alert_stream = alert_stream.clone();
+    let relay_shutdown = shutdown.shutdown.clone();
+
+    //Spawn a new thread that relays the published trades and alerts to the subscribers, reconnecting when Redis drops
+    tokio::spawn(async move {
+        loop {
+            if let Err(err) = relay_live_messages(
+                &redis_url,
+                &relay_live_events,
+                &relay_alert_stream,
+                &relay_shutdown,
+            )
+            .await
+            {
+                tracing::error!(error = ?err, "Live relay stopped, reconnecting");
+            }
+
+            tokio::select! {
+                _ = relay_shutdown.cancelled() => break,
+                _ = tokio::time::sleep(RELAY_RECONNECT_DELAY) => {}
+            }
+        }
+    });
+
+    let schema = build_schema(db.clone(), live_events, &config.api);
+
+    let health_thresholds = HealthThresholds {
+        check_ingestion: false,
+        ..HealthThresholds::from(&config)
+    };
+    let sniper_config = config.sniper.clone();
+    let funding_config = config.funding.clone();
+    let webhook_config = config.webhooks.clone();
+
+    let api_access = web::Data::new(ApiAccess::new(
+        db.clone(),
+        api_redis.clone(),
+        config.api.clone(),
+    ));
+    let cors_origins = config.api.cors_origins.clone();
+
+    //Spawn a new thread that writes the per API key request counts every few seconds
+    let usage_handle = tokio::spawn(run_usage_flusher(
+        api_access.clone(),
+        shutdown.flush.clone(),
+    ));
+
+    let openapi = ApiDoc::openapi();
+
+    // Start the Actix web server for serving the API
+    let server = HttpServer::new(move || {
+        App::new()
+            .app_data(web::QueryConfig::default().error_handler(extractor_error))
+            .app_data(web::JsonConfig::default().error_handler(extractor_error))
+            .app_data(web::PathConfig::default().error_handler(extractor_error))
+            .app_data(web::Data::new(db.clone()))
+            .app_data(web::Data::new(api_redis.clone()))
+            .app_data(web::Data::new(health_thresholds.clone()))
+            .app_data(web::Data::new(sniper_config.clone()))
+            .app_data(web::Data::new(funding_config.clone()))
+            .app_data(web::Data::new(webhook_config.clone()))
+            .app_data(web::Data::new(alert_stream.clone()))
+            .app_data(web::Data::new(schema.clone()))
+            .app_data(api_access.clone())
+            .app_data(web::Data::from(response_cache.clone()))
+            .wrap(from_fn(authenticate))
+            .wrap(cors(&cors_origins))
+            .wrap(from_fn(track_http_metrics))
+            .service(get_trending)
+            .service(get_king_of_the_hill)
+            .service(get_tokens)
+            // After the fixed /tokens/... routes, which would otherwise match as an address
+            .service(get_token)
+            .service(get_token_candles)
+            .service(get_alerts)
+            .service(get_alert_stream)
+            .service(post_webhook)
+            .service(get_webhooks)
+            .service(delete_webhook_by_id)
+            .service(enable_webhook)
+            .service(disable_webhook)
+            .service(get_webhook_deliveries)
+            .service(post_api_key)
+            .service(get_api_keys)
+            .service(delete_api_key)
+            .service(get_api_key_usage)
+            .service(post_graphql)
+            .service(get_graphiql)
+            .service(get_graphql_ws)
+            .service(get_metrics)
+            .service(get_healthz)
+            .service(get_readyz)
+            .service(SwaggerUi::new("/swagger-ui/{_:.*}").url("/openapi.json", openapi.clone()))
+    })
+    .disable_signals()
+    .shutdown_timeout(config.shutdown_timeout_secs)
+    .bind(("0.0.0.0", config.http_port))?
+    .run();
+
+    serve(server, &shutdown.shutdown).await?;
+
+    // Nothing to wait for before flushing, the API has no pipeline
+    shutdown.drain(async {}, vec![usage_handle]).await;
+
+    Ok(())
+}
@@ -0,0 +1,112 @@
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    backfill::{resume_checkpoint, CheckpointBackfill},
+    checkpoint::{CheckpointTracker, LIVE_DATASOURCE},
+    config::{IndexerConfig, LeaderElectionKind},
+    leader::acquire_leadership,
+    roles::{
+        connect_postgres, connect_redis,
+        ingest::{spawn_pipeline, start_ingestion},
+        Shutdown,
+    },
+    sinks::connect_sink,
+};
+
+// Replays every Pump.fun transaction since the last checkpoint up to the current head, then exits with the
+// checkpoint moved to the head. Meant for catching up a long outage before the ingest role is started again, the
+// two must not run at the same time since the trades of the overlap would be stored twice. With an election the
+// backfill takes the ingest leadership and refuses to start while another replica holds it.
+pub async fn run(config: IndexerConfig) -> Result<(), anyhow::Error> {
+    let db = connect_postgres(&config).await?;
+
+    let election = CancellationToken::new();
+    let leadership = acquire_leadership(&config, election.clone()).await?;
+
+    // Released once the backfill returns, so a waiting ingest replica takes over right away
+    let _release = election.drop_guard();
+
+    if config.leader_election.kind == LeaderElectionKind::None {
+        tracing::warn!("No LEADER_ELECTION configured, make sure the ingest role is stopped");
+    }
+
+    let (push_tx, push_rx) = tokio::sync::mpsc::unbounded_channel();
+
+    let redis = connect_redis(&config.redis_url, Some(push_tx)).await?;
+
+    let shutdown = Shutdown::listen(&config);
+
+    let event_sink = connect_sink(&config.event_sink).await?;
+
+    let Some(checkpoint) = resume_checkpoint(db.clone(), event_sink.is_some()).await? else {
+        tracing::info!("No checkpoint yet, nothing to backfill");
+        return Ok(());
+    };
+
+    tracing::info!(
+        "Backfilling from checkpoint at slot {} ({})",
+        checkpoint.slot,
+        checkpoint.signature
+    );
+
+    let checkpoint_tracker = CheckpointTracker::new(LIVE_DATASOURCE);
+    checkpoint_tracker.begin_replay();
+
+    let done = CancellationToken::new();
+
+    let backfill = CheckpointBackfill {
+        rpc_url: config.rpc_url.clone(),
+        checkpoint,
+        tracker: checkpoint_tracker.clone(),
+        done: Some(done.clone()),
+    };
+
+    let replay_tracker = checkpoint_tracker.clone();
+
+    let ingestion = start_ingestion(
+        &config,
+        db,
+        redis,
+        push_rx,
+        checkpoint_tracker,
+        event_sink,
+        leadership,
+        &shutdown,
+    )
+    .await?;
+
+    let pipeline_handle = spawn_pipeline(
+        None,
+        Some(backfill),
+        None,
+        ingestion.processor,
+        ingestion.workers,
+        ingestion.publishes,
+        &shutdown,
+    );
+
+    // Stop the pipeline once the gap is replayed, it still processes what the backfill already sent
+    tokio::select! {
+        _ = done.cancelled() => {}
+        _ = shutdown.shutdown.cancelled() => {}
+    }
+
+    shutdown
+        .drain(
+            async {
+                if let Err(err) = pipeline_handle.await {
+                    tracing::error!("Pipeline task failed during shutdown: {:?}", err);
+                }
+
+                // The pipeline and its workers have drained, so every replayed transaction is processed. The
+                // checkpoint only moves if the backfill got through the whole gap.
+                if replay_tracker.is_replay_sent() {
+                    replay_tracker.set_backfilling(false);
+                }
+            },
+            ingestion.flush_handles,
+        )
+        .await;
+
+    Ok(())
+}
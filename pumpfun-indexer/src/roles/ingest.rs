@@ -0,0 +1,448 @@
+use std::sync::Arc;
+
+use carbon_core::pipeline::Pipeline;
+use carbon_pumpfun_decoder::PumpfunDecoder;
+use redis::{aio::MultiplexedConnection, PushInfo};
+use sqlx::PgPool;
+use tokio::{
+    sync::{mpsc::UnboundedReceiver, RwLock},
+    task::JoinHandle,
+    time,
+};
+use tokio_util::task::TaskTracker;
+
+use crate::{
+    alerts::{run_alert_dispatcher, AlertEngine},
+    backfill::{resume_checkpoint, CheckpointBackfill, FailoverBackfill},
+    checkpoint::{CheckpointTracker, LIVE_DATASOURCE},
+    config::{IndexerConfig, LeaderElectionKind},
+    datasources::RedundantDatasource,
+    db::trade::consume_and_store,
+    dispatch::PartitionedProcessor,
+    events::EventEmitter,
+    funding::run_funding_resolver,
+    health::HealthThresholds,
+    helpers::get_latest_sol_price,
+    leader::{start_leader_election, Leadership},
+    metrics::{metrics, PrometheusMetrics},
+    pumpfun_processor::PumpfunInstructionProcessor,
+    roles::{
+        connect_postgres, connect_redis, ops_server, serve, start_cache_invalidator, Shutdown,
+    },
+    sinks::{connect_sink, run_event_sink, EventSink, EVENT_SINK_DATASOURCE},
+    store::Store,
+    webhooks::{run_webhook_enqueuer, WebhookEmitter},
+};
+
+// The instruction processors and the tasks they hand their work to, shared by the ingest and backfill roles
+pub(crate) struct Ingestion {
+    pub processor: PartitionedProcessor,
+    // Workers of the processor, they finish once the pipeline has stopped and their queues are drained
+    pub workers: Vec<JoinHandle<()>>,
+    // Publishes of the processors on the Redis trade channel, waited for once the workers have finished
+    pub publishes: TaskTracker,
+    // Tasks that drain their buffers once the flush token is cancelled
+    pub flush_handles: Vec<JoinHandle<()>>,
+}
+
+// Loads the state of the tokens into the map of the processor and starts every task the processor feeds. The flushers
+// keep running on a standby but only write once it is the leader.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn start_ingestion(
+    config: &IndexerConfig,
+    db: Arc<PgPool>,
+    redis: MultiplexedConnection,
+    mut push_rx: UnboundedReceiver<PushInfo>,
+    checkpoint_tracker: CheckpointTracker,
+    event_sink: Option<Box<dyn EventSink>>,
+    leadership: Leadership,
+    shutdown: &Shutdown,
+) -> Result<Ingestion, anyhow::Error> {
+    let store = Store::new(db.clone());
+
+    //Fetch the bonding curve and market cap info of all the tokens from DB, it is passed to the instruction processor
+    let bonding_curve_and_mc_info_map = store
+        .load_bonding_state(config.state.max_tokens, config.state.shards)
+        .await?;
+
+    tracing::info!(
+        tokens = bonding_curve_and_mc_info_map.len(),
+        "Loaded bonding curve info"
+    );
+
+    let mut flush_handles = Vec::new();
+
+    let sol_price = Arc::new(RwLock::new(0.0));
+
+    let sol_price_clone = sol_price.clone();
+    let price_shutdown = shutdown.shutdown.clone();
+
+    //* This thread fetches the latest solana price every 15 sec to calculate the market cap since pairs are present in SOL pair(TOKEN/SOL) */
+    tokio::spawn(async move {
+        loop {
+            // On failure the previous price is kept and the readiness check reports its age until a fetch succeeds
+            match get_latest_sol_price().await {
+                Ok(price) => {
+                    tracing::debug!(price, "Refreshed SOL price");
+
+                    {
+                        let mut price_ref = sol_price_clone.write().await;
+
+                        *price_ref = price;
+                    }
+
+                    metrics().mark_sol_price_updated();
+                }
+                Err(err) => {
+                    tracing::error!(error = ?err, "Failed to fetch the SOL price, retrying");
+                }
+            }
+
+            tokio::select! {
+                _ = price_shutdown.cancelled() => break,
+                _ = tokio::time::sleep(time::Duration::from_secs(15)) => {}
+            }
+        }
+    });
+
+    let (cache_invalidator, cache_invalidator_handle) =
+        start_cache_invalidator(config, &shutdown.flush).await;
+
+    let store_clone = store.clone();
+    let info_map = bonding_curve_and_mc_info_map.clone();
+    let market_cap_cache = cache_invalidator.clone();
+    let market_cap_leadership = leadership.clone();
+    let market_cap_shutdown = shutdown.flush.clone();
+    let flush_interval = time::Duration::from_secs(config.state.flush_interval_secs);
+    let flush_batch_size = config.state.flush_batch_size;
+    let idle_ttl = time::Duration::from_secs(config.state.idle_ttl_secs);
+
+    //Spawn a new thread that writes the bonding curve and market cap of the tokens changed since the previous flush
+    flush_handles.push(tokio::spawn(async move {
+        loop {
+            let stopping = tokio::select! {
+                _ = market_cap_shutdown.cancelled() => true,
+                _ = tokio::time::sleep(flush_interval) => false,
+            };
+
+            // Evicted first so the unwritten changes of the idle tokens go out with this flush
+            info_map.evict_idle(idle_ttl);
+
+            metrics().state_map_size.set(info_map.len() as i64);
+
+            // A standby keeps its changes flagged, they are written once it takes over
+            if market_cap_leadership.is_leader() {
+                store_clone
+                    .flush_bonding_state(&info_map, flush_batch_size, &market_cap_cache)
+                    .await;
+            }
+
+            if stopping {
+                tracing::info!("Flushed bonding curve and market cap state");
+                break;
+            }
+        }
+    }));
+
+    let db_clone_2 = db.clone();
+    let connection_clone = redis.clone();
+    let trade_writer_config = config.trade_writer.clone();
+    let trade_leadership = leadership.clone();
+    let trade_shutdown = shutdown.flush.clone();
+    let trade_cache = cache_invalidator.clone();
+
+    //Spawn a new thread to subscribes to the Redis "trade" channel
+    flush_handles.push(tokio::spawn(async move {
+        loop {
+            consume_and_store(
+                &mut connection_clone.clone(),
+                db_clone_2.clone(),
+                &mut push_rx,
+                &trade_writer_config,
+                &trade_leadership,
+                trade_cache.clone(),
+                trade_shutdown.clone(),
+            )
+            .await;
+
+            tokio::select! {
+                _ = trade_shutdown.cancelled() => break,
+                _ = tokio::time::sleep(tokio::time::Duration::from_secs(10)) => {}
+            }
+        }
+    }));
+
+    let db_clone_3 = db.clone();
+    let tracker_clone = checkpoint_tracker.clone();
+    let checkpoint_leadership = leadership.clone();
+    let checkpoint_shutdown = shutdown.flush.clone();
+
+    //Spawn a new thread that persists the highest fully processed slot every 10 seconds
+    flush_handles.push(tokio::spawn(async move {
+        let mut was_leader = checkpoint_leadership.is_leader();
+
+        loop {
+            let stopping = tokio::select! {
+                _ = checkpoint_shutdown.cancelled() => true,
+                _ = tokio::time::sleep(tokio::time::Duration::from_secs(10)) => false,
+            };
+
+            let leader = checkpoint_leadership.is_leader();
+
+            // The progress of a standby was never written. Right after a takeover the failover backfill holds the
+            // checkpoint back, this round is skipped so it can't be persisted before that.
+            if leader && was_leader {
+                tracker_clone.flush(db_clone_3.clone(), stopping).await;
+            }
+
+            was_leader = leader;
+
+            if stopping {
+                break;
+            }
+        }
+    }));
+
+    let event_tx = match event_sink {
+        Some(sink) => {
+            let (event_tx, event_rx) =
+                tokio::sync::mpsc::channel(config.event_sink.queue_size.max(1));
+
+            //Spawn a new thread that publishes the decoded events to the configured broker
+            flush_handles.push(tokio::spawn(run_event_sink(
+                db.clone(),
+                sink,
+                config.event_sink.clone(),
+                event_rx,
+                checkpoint_tracker.follower(EVENT_SINK_DATASOURCE),
+                leadership.clone(),
+                shutdown.flush.clone(),
+            )));
+
+            Some(event_tx)
+        }
+        None => None,
+    };
+
+    let (funding_tx, funding_rx) = tokio::sync::mpsc::unbounded_channel();
+
+    //Spawn a new thread that resolves where trader wallets got their SOL from
+    tokio::spawn(run_funding_resolver(
+        db.clone(),
+        config.rpc_url.clone(),
+        config.funding.clone(),
+        funding_rx,
+        shutdown.shutdown.clone(),
+    ));
+
+    let (alert_tx, alert_rx) = tokio::sync::mpsc::unbounded_channel();
+
+    //Spawn a new thread that stores the fired alerts and publishes them for the stream and the webhook
+    flush_handles.push(tokio::spawn(run_alert_dispatcher(
+        db.clone(),
+        config.alerts.webhook_url.clone(),
+        config.alerts.webhook_timeout_secs,
+        alert_rx,
+        redis.clone(),
+        leadership.clone(),
+        shutdown.flush.clone(),
+    )));
+
+    let (webhook_tx, webhook_rx) = tokio::sync::mpsc::unbounded_channel();
+
+    //Spawn a new thread that queues the webhook events, the workers deliver them
+    flush_handles.push(tokio::spawn(run_webhook_enqueuer(
+        db.clone(),
+        webhook_rx,
+        shutdown.flush.clone(),
+    )));
+
+    let publishes = TaskTracker::new();
+
+    //Initialize one PumpfunInstructionProcessor per worker, the dispatcher tracks the checkpoint for all of them
+    let (processor, workers) = PartitionedProcessor::spawn(
+        config.processor.workers,
+        config.processor.queue_size,
+        checkpoint_tracker,
+        event_tx.clone(),
+        |_| {
+            PumpfunInstructionProcessor::builder(store.clone(), redis.clone())
+                .bonding_state(bonding_curve_and_mc_info_map.clone())
+                .sol_price(sol_price.clone())
+                .funding(funding_tx.clone())
+                // The rules only look at the trades of a single mint, so every worker evaluates its own mints
+                .alerts(AlertEngine::new(
+                    config.alerts.rules.clone(),
+                    alert_tx.clone(),
+                ))
+                .webhooks(WebhookEmitter::new(
+                    webhook_tx.clone(),
+                    config.webhooks.large_trade_sol,
+                ))
+                // The API processes follow the trades on the Redis "trade" channel, there are no live subscribers here
+                .events(EventEmitter::new(event_tx.clone(), None))
+                .encoding(config.event_encoding)
+                .cache(cache_invalidator.clone())
+                .publishes(publishes.clone())
+                .leadership(leadership.clone())
+                .build()
+        },
+    );
+
+    tracing::info!(workers = workers.len(), "Started the processor workers");
+
+    // Waited for after the other flushing tasks, which invalidate what they write last
+    flush_handles.push(cache_invalidator_handle);
+
+    Ok(Ingestion {
+        processor,
+        workers,
+        publishes,
+        flush_handles,
+    })
+}
+
+// Runs the Pumpfun pipeline until its datasources finish or the shutdown token is cancelled, then waits for the
+// workers to process what is still queued and for their Redis publishes
+pub(crate) fn spawn_pipeline(
+    live: Option<RedundantDatasource>,
+    backfill: Option<CheckpointBackfill>,
+    failover: Option<FailoverBackfill>,
+    processor: PartitionedProcessor,
+    workers: Vec<JoinHandle<()>>,
+    publishes: TaskTracker,
+    shutdown: &Shutdown,
+) -> JoinHandle<()> {
+    let pipeline_shutdown = shutdown.shutdown.clone();
+
+    tokio::spawn(async move {
+        let mut builder = Pipeline::builder();
+
+        if let Some(live) = live {
+            builder = builder.datasource(live);
+        }
+
+        if let Some(backfill) = backfill {
+            builder = builder.datasource(backfill);
+        }
+
+        if let Some(failover) = failover {
+            builder = builder.datasource(failover);
+        }
+
+        builder
+            .instruction(PumpfunDecoder, processor)
+            .metrics(Arc::new(PrometheusMetrics))
+            .metrics_flush_interval(15)
+            .datasource_cancellation_token(pipeline_shutdown)
+            .shutdown_strategy(carbon_core::pipeline::ShutdownStrategy::ProcessPending)
+            .build()
+            .unwrap()
+            .run()
+            .await
+            .unwrap();
+
+        for worker in workers {
+            if let Err(err) = worker.await {
+                tracing::error!("Processor worker failed: {:?}", err);
+            }
+        }
+
+        publishes.close();
+        publishes.wait().await;
+    })
+}
+
+// Indexes the Pumpfun instructions from the redundant live datasources, plus the catch-up backfill if there is a gap
+// since the previous run. Serves the metrics and health probes on OPS_PORT.
+pub async fn run(config: IndexerConfig) -> Result<(), anyhow::Error> {
+    let db = connect_postgres(&config).await?;
+
+    // Create an unbounded channel for Redis push notifications
+    let (push_tx, push_rx) = tokio::sync::mpsc::unbounded_channel();
+
+    // Only the trade channel is used here, the keys in Redis belong to the API (rate limits, cache) and are left alone
+    let redis = connect_redis(&config.redis_url, Some(push_tx)).await?;
+
+    let shutdown = Shutdown::listen(&config);
+
+    // Only the leader writes, the other replicas run the same pipeline as warm standbys
+    let leadership = start_leader_election(&config, shutdown.shutdown.clone());
+
+    // Load how far the previous run got, so the gap since then can be backfilled alongside the websocket
+    let event_sink = connect_sink(&config.event_sink).await?;
+    let checkpoint_tracker = CheckpointTracker::new(LIVE_DATASOURCE);
+
+    // With an election the replica starts as a standby, the failover backfill replays the gap once it takes over
+    let electing = config.leader_election.kind != LeaderElectionKind::None;
+
+    let last_checkpoint = match electing {
+        true => None,
+        false => resume_checkpoint(db.clone(), event_sink.is_some()).await?,
+    };
+
+    let failover = electing.then(|| FailoverBackfill {
+        rpc_url: config.rpc_url.clone(),
+        db: db.clone(),
+        publishing: event_sink.is_some(),
+        tracker: checkpoint_tracker.clone(),
+        leadership: leadership.clone(),
+    });
+
+    let backfill = last_checkpoint.map(|checkpoint| {
+        tracing::info!(
+            "Resuming from checkpoint at slot {} ({})",
+            checkpoint.slot,
+            checkpoint.signature
+        );
+
+        // Mark the backfill as running before the pipeline starts so the checkpoint can't skip the gap
+        checkpoint_tracker.begin_replay();
+
+        CheckpointBackfill {
+            rpc_url: config.rpc_url.clone(),
+            checkpoint,
+            tracker: checkpoint_tracker.clone(),
+            done: None,
+        }
+    });
+
+    let ingestion = start_ingestion(
+        &config,
+        db.clone(),
+        redis.clone(),
+        push_rx,
+        checkpoint_tracker,
+        event_sink,
+        leadership,
+        &shutdown,
+    )
+    .await?;
+
+    let pipeline_handle = spawn_pipeline(
+        Some(RedundantDatasource::from_config(&config.datasources)),
+        backfill,
+        failover,
+        ingestion.processor,
+        ingestion.workers,
+        ingestion.publishes,
+        &shutdown,
+    );
+
+    let server = ops_server(&config, db, redis, HealthThresholds::from(&config))?;
+
+    serve(server, &shutdown.shutdown).await?;
+
+    shutdown
+        .drain(
+            async {
+                if let Err(err) = pipeline_handle.await {
+                    tracing::error!("Pipeline task failed during shutdown: {:?}", err);
+                }
+            },
+            ingestion.flush_handles,
+        )
+        .await;
+
+    Ok(())
+}
@@ -0,0 +1,31 @@
+use std::str::FromStr;
+
+use refinery::config::Config;
+
+use crate::config::IndexerConfig;
+
+mod embedded {
+    use refinery::embed_migrations;
+    embed_migrations!("../db/migrations");
+}
+
+// Applies the pending migrations of db/migrations to DATABASE_URL, the same migrations the db crate embeds
+pub async fn run(config: IndexerConfig) -> Result<(), anyhow::Error> {
+    let mut conf = Config::from_str(&config.database_url)?;
+
+    // The refinery Postgres driver is blocking
+    let report = tokio::task::spawn_blocking(move || embedded::migrations::runner().run(&mut conf))
+        .await??;
+
+    for migration in report.applied_migrations() {
+        tracing::info!(
+            version = migration.version(),
+            name = migration.name(),
+            "Applied migration"
+        );
+    }
+
+    tracing::info!("Migrations applied successfully");
+
+    Ok(())
+}
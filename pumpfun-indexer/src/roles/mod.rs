@@ -0,0 +1,191 @@
+use std::{future::Future, sync::Arc, time::Duration};
+
+use actix_web::{dev::Server, middleware::from_fn, web, App, HttpServer};
+use redis::{aio::MultiplexedConnection, IntoConnectionInfo, ProtocolVersion, PushInfo};
+use sqlx::PgPool;
+use tokio::{sync::mpsc::UnboundedSender, task::JoinHandle};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    cache::{run_cache_invalidator, CacheInvalidator, ResponseCache},
+    config::IndexerConfig,
+    health::{get_healthz, get_readyz, HealthThresholds},
+    metrics::{get_metrics, track_http_metrics},
+    shutdown,
+    utils::connect_db,
+};
+
+pub mod api;
+pub mod backfill;
+pub mod ingest;
+pub mod migrate;
+pub mod worker;
+
+// Cancellation tokens shared by the tasks of a role
+pub(crate) struct Shutdown {
+    // Cancelled on Ctrl-C/SIGTERM, stops the datasource, the pollers and the HTTP servers
+    pub shutdown: CancellationToken,
+    // Cancelled only once the pipeline has finished processing pending updates, so that the buffers are drained last
+    pub flush: CancellationToken,
+    pub timeout: Duration,
+}
+
+impl Shutdown {
+    pub fn listen(config: &IndexerConfig) -> Self {
+        let shutdown = CancellationToken::new();
+
+        tokio::spawn(shutdown::listen_for_shutdown(shutdown.clone()));
+
+        Self {
+            shutdown,
+            flush: CancellationToken::new(),
+            timeout: Duration::from_secs(config.shutdown_timeout_secs),
+        }
+    }
+
+    // Waits for `stopped` (the pipeline), then cancels the flush token and waits for the flushing tasks, all within
+    // the shutdown timeout
+    pub async fn drain<F: Future>(&self, stopped: F, flush_handles: Vec<JoinHandle<()>>) {
+        self.shutdown.cancel();
+
+        let drained = tokio::time::timeout(self.timeout, async {
+            stopped.await;
+
+            self.flush.cancel();
+
+            for handle in flush_handles {
+                if let Err(err) = handle.await {
+                    tracing::error!("Task failed during shutdown: {:?}", err);
+                }
+            }
+        })
+        .await;
+
+        if drained.is_err() {
+            tracing::error!(
+                "Shutdown timed out after {} seconds, some buffered data may be lost",
+                self.timeout.as_secs()
+            );
+        } else {
+            tracing::info!("Shutdown complete");
+        }
+    }
+}
+
+//* Returns a DB instance for Postgres DB */
+pub(crate) async fn connect_postgres(config: &IndexerConfig) -> Result<Arc<PgPool>, anyhow::Error> {
+    let db = Arc::new(
+        connect_db(&config.database_url)
+            .await
+            .map_err(|_| anyhow::Error::msg("Failed to connect to DB"))?,
+    );
+
+    tracing::info!("Database Connected");
+
+    Ok(db)
+}
+
+// Multiplexed connection to REDIS_URL, it can be shared between multiple threads. With a push sender the messages
+// of the subscribed channels are delivered to it, RESP3 is used so that they arrive on the same connection.
+pub(crate) async fn connect_redis(
+    redis_url: &str,
+    push: Option<UnboundedSender<PushInfo>>,
+) -> Result<MultiplexedConnection, anyhow::Error> {
+    let mut connection_info = redis_url.into_connection_info()?;
+    connection_info.redis.protocol = ProtocolVersion::RESP3;
+
+    let redis_client = redis::Client::open(connection_info)?;
+
+    let mut redis_config = redis::AsyncConnectionConfig::new();
+
+    if let Some(push) = push {
+        redis_config = redis_config.set_push_sender(push);
+    }
+
+    Ok(redis_client
+        .get_multiplexed_async_connection_with_config(&redis_config)
+        .await?)
+}
+
+// The shared response cache is optional, without it every API instance only has its in-process cache
+pub(crate) async fn connect_response_cache(config: &IndexerConfig) -> Arc<ResponseCache> {
+    let cache_redis = match &config.cache.redis_url {
+        Some(url) => match redis::Client::open(url.as_str()) {
+            Ok(client) => match client.get_multiplexed_async_connection().await {
+                Ok(connection) => Some(connection),
+                Err(err) => {
+                    tracing::error!(error = ?err, "Failed to connect to the cache Redis, using the in-process cache only");
+                    None
+                }
+            },
+            Err(err) => {
+                tracing::error!(error = ?err, "Invalid CACHE_REDIS_URL, using the in-process cache only");
+                None
+            }
+        },
+        None => None,
+    };
+
+    Arc::new(ResponseCache::new(config.cache.clone(), cache_redis))
+}
+
+// Spawns the task applying the invalidations of the returned invalidator, it finishes once the flush token is
+// cancelled and every clone of the invalidator was dropped. The ingest and worker roles have no cached responses of
+// their own, their invalidations only reach the API processes through the shared cache.
+pub(crate) async fn start_cache_invalidator(
+    config: &IndexerConfig,
+    flush: &CancellationToken,
+) -> (CacheInvalidator, JoinHandle<()>) {
+    let cache = connect_response_cache(config).await;
+
+    if config.cache.enabled && !cache.is_shared() {
+        tracing::warn!("CACHE_REDIS_URL is not set, the API only sees new data once its cached responses expire");
+    }
+
+    let (cache_tx, cache_rx) = tokio::sync::mpsc::unbounded_channel();
+
+    //Spawn a new thread that applies the cache invalidations
+    let handle = tokio::spawn(run_cache_invalidator(cache, cache_rx, flush.clone()));
+
+    (
+        CacheInvalidator::new(config.cache.enabled.then_some(cache_tx)),
+        handle,
+    )
+}
+
+// Metrics and health probes of the roles that don't serve the API
+pub(crate) fn ops_server(
+    config: &IndexerConfig,
+    db: Arc<PgPool>,
+    redis: MultiplexedConnection,
+    thresholds: HealthThresholds,
+) -> std::io::Result<Server> {
+    Ok(HttpServer::new(move || {
+        App::new()
+            .app_data(web::Data::new(db.clone()))
+            .app_data(web::Data::new(redis.clone()))
+            .app_data(web::Data::new(thresholds.clone()))
+            .wrap(from_fn(track_http_metrics))
+            .service(get_metrics)
+            .service(get_healthz)
+            .service(get_readyz)
+    })
+    .disable_signals()
+    .shutdown_timeout(config.shutdown_timeout_secs)
+    .bind(("0.0.0.0", config.ops_port))?
+    .run())
+}
+
+// Runs the server until it stops on its own or the shutdown token is cancelled, signals are handled by the token
+// instead of Actix
+pub(crate) async fn serve(server: Server, shutdown: &CancellationToken) -> std::io::Result<()> {
+    let server_handle = server.handle();
+    let server_shutdown = shutdown.clone();
+
+    tokio::spawn(async move {
+        server_shutdown.cancelled().await;
+        server_handle.stop(true).await;
+    });
+
+    server.await
+}
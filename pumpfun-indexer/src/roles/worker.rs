@@ -0,0 +1,62 @@
+use tokio::time;
+
+use crate::{
+    config::IndexerConfig,
+    health::HealthThresholds,
+    ranking::refresh_rankings,
+    roles::{
+        connect_postgres, connect_redis, ops_server, serve, start_cache_invalidator, Shutdown,
+    },
+    webhooks::run_webhook_delivery,
+};
+
+// Background jobs that only need the database: the trending ranking refresh and the webhook deliveries. Serves the
+// metrics and health probes on OPS_PORT.
+pub async fn run(config: IndexerConfig) -> Result<(), anyhow::Error> {
+    let db = connect_postgres(&config).await?;
+
+    let redis = connect_redis(&config.redis_url, None).await?;
+
+    let shutdown = Shutdown::listen(&config);
+
+    let (cache_invalidator, _) = start_cache_invalidator(&config, &shutdown.flush).await;
+
+    let db_clone = db.clone();
+    let ranking_config = config.ranking.clone();
+    let ranking_shutdown = shutdown.shutdown.clone();
+
+    //Spawn a new thread that recomputes the trending ranking on the configured interval
+    tokio::spawn(async move {
+        loop {
+            refresh_rankings(db_clone.clone(), &ranking_config).await;
+
+            cache_invalidator.rankings_changed();
+
+            tokio::select! {
+                _ = ranking_shutdown.cancelled() => break,
+                _ = tokio::time::sleep(time::Duration::from_secs(ranking_config.interval_secs)) => {}
+            }
+        }
+    });
+
+    //Spawn a new thread that delivers the queued webhook events to the registered receivers
+    let webhook_handle = tokio::spawn(run_webhook_delivery(
+        db.clone(),
+        config.webhooks.clone(),
+        shutdown.shutdown.clone(),
+    ));
+
+    let thresholds = HealthThresholds {
+        check_ingestion: false,
+        ..HealthThresholds::from(&config)
+    };
+
+    let server = ops_server(&config, db, redis, thresholds)?;
+
+    serve(server, &shutdown.shutdown).await?;
+
+    // Lets the deliveries in flight finish, an unfinished one is retried once its lease expires
+    shutdown.drain(webhook_handle, Vec::new()).await;
+
+    Ok(())
+}
@@ -0,0 +1,144 @@
+use std::{str::FromStr, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use carbon_core::{
+    datasource::{Datasource, Update, UpdateType},
+    error::{CarbonResult, Error as CarbonError},
+    metrics::MetricsCollection,
+};
+use carbon_pumpfun_decoder::PROGRAM_ID;
+use futures::{stream, StreamExt};
+use solana_client::{
+    nonblocking::rpc_client::RpcClient, rpc_client::GetConfirmedSignaturesForAddress2Config,
+};
+use solana_sdk::{commitment_config::CommitmentConfig, signature::Signature};
+use sqlx::types::chrono::Utc;
+use tokio::sync::mpsc::UnboundedSender;
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    backfill::{fetch_signature_times_since, fetch_transaction_update},
+    datasources::SignatureWindow,
+};
+
+// Datasource that polls an RPC node for the new Pump.fun signatures and fetches their transactions. Slower than the
+// websockets, it keeps delivering while they reconnect. The transactions are fetched concurrently and delivered in
+// order, the ones older than the dedup window are skipped since the other datasources may have delivered them already.
+pub struct RpcPoller {
+    pub rpc_url: String,
+    pub interval: Duration,
+    pub max_concurrent_fetches: usize,
+    // Transactions another datasource already delivered are not fetched again
+    pub seen: SignatureWindow,
+}
+
+// Newest signature of the program, polling starts after it
+async fn latest_signature(rpc: &RpcClient) -> CarbonResult<Option<Signature>> {
+    let page = rpc
+        .get_signatures_for_address_with_config(
+            &PROGRAM_ID,
+            GetConfirmedSignaturesForAddress2Config {
+                before: None,
+                until: None,
+                limit: Some(1),
+                commitment: Some(CommitmentConfig::confirmed()),
+            },
+        )
+        .await
+        .map_err(|err| CarbonError::Custom(format!("Failed to fetch signatures: {}", err)))?;
+
+    Ok(page
+        .first()
+        .and_then(|status| Signature::from_str(&status.signature).ok()))
+}
+
+#[async_trait]
+impl Datasource for RpcPoller {
+    async fn consume(
+        &self,
+        sender: &UnboundedSender<Update>,
+        cancellation_token: CancellationToken,
+        _metrics: Arc<MetricsCollection>,
+    ) -> CarbonResult<()> {
+        let rpc =
+            RpcClient::new_with_commitment(self.rpc_url.clone(), CommitmentConfig::confirmed());
+
+        let mut until: Option<Signature> = None;
+
+        loop {
+            tokio::select! {
+                _ = cancellation_token.cancelled() => return Ok(()),
+                _ = tokio::time::sleep(self.interval) => {}
+            }
+
+            let Some(last) = until else {
+                match latest_signature(&rpc).await {
+                    Ok(latest) => until = latest,
+                    Err(err) => tracing::error!("RPC poller failed to find the head: {:?}", err),
+                }
+
+                continue;
+            };
+
+            let signatures = match fetch_signature_times_since(&rpc, last).await {
+                Ok(signatures) => signatures,
+                Err(err) => {
+                    tracing::error!("RPC poller failed to list new transactions: {:?}", err);
+                    continue;
+                }
+            };
+
+            let oldest_block_time = Utc::now().timestamp() - self.seen.window().as_secs() as i64;
+
+            let pending: Vec<Signature> = signatures
+                .iter()
+                .filter(|(_, block_time)| block_time.is_none_or(|at| at >= oldest_block_time))
+                .map(|(signature, _)| *signature)
+                .filter(|signature| !self.seen.contains(signature))
+                .collect();
+
+            if pending.len() < signatures.len() {
+                tracing::debug!(
+                    skipped = signatures.len() - pending.len(),
+                    "RPC poller skipped delivered or expired transactions"
+                );
+            }
+
+            let mut updates = stream::iter(pending)
+                .map(|signature| {
+                    let rpc = &rpc;
+
+                    async move { fetch_transaction_update(rpc, signature).await }
+                })
+                .buffered(self.max_concurrent_fetches.max(1));
+
+            while let Some(result) = updates.next().await {
+                if cancellation_token.is_cancelled() {
+                    return Ok(());
+                }
+
+                let update = match result {
+                    Ok(Some(update)) => update,
+                    Ok(None) => continue,
+                    Err(err) => {
+                        tracing::error!("RPC poller failed to fetch a transaction: {:?}", err);
+                        continue;
+                    }
+                };
+
+                if sender.send(update).is_err() {
+                    tracing::error!("Pipeline closed while polling");
+                    return Ok(());
+                }
+            }
+
+            if let Some((newest, _)) = signatures.last() {
+                until = Some(*newest);
+            }
+        }
+    }
+
+    fn update_types(&self) -> Vec<UpdateType> {
+        vec![UpdateType::Transaction]
+    }
+}
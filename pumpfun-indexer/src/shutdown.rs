@@ -0,0 +1,32 @@
+use tokio::signal;
+use tokio_util::sync::CancellationToken;
+
+// Waits for Ctrl-C (or SIGTERM on unix) and cancels the shutdown token so every task can wind down.
+pub async fn listen_for_shutdown(shutdown: CancellationToken) {
+    let ctrl_c = async {
+        signal::ctrl_c()
+            .await
+            .expect("Failed to install Ctrl-C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        signal::unix::signal(signal::unix::SignalKind::terminate())
+            .expect("Failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+        _ = shutdown.cancelled() => return,
+    }
+
+    tracing::info!("Shutdown signal received, draining buffers");
+
+    shutdown.cancel();
+}
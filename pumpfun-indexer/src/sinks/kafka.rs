@@ -0,0 +1,87 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use rdkafka::{
+    message::{Header, OwnedHeaders},
+    producer::{FutureProducer, FutureRecord},
+    util::Timeout,
+    ClientConfig,
+};
+
+use crate::{
+    config::{EventEncoding, EventSinkConfig},
+    events::EventEnvelope,
+    sinks::EventSink,
+};
+
+const SEND_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Produces every event to a Kafka topic keyed by mint, so the events of a token stay ordered within a partition
+pub struct KafkaSink {
+    producer: FutureProducer,
+    topic: String,
+    encoding: EventEncoding,
+}
+
+impl KafkaSink {
+    pub fn connect(config: &EventSinkConfig) -> Result<Self, anyhow::Error> {
+        let producer: FutureProducer = ClientConfig::new()
+            .set("bootstrap.servers", &config.kafka_brokers)
+            // Only count an event as published once every in-sync replica has it
+            .set("acks", "all")
+            .set("enable.idempotence", "true")
+            .set("message.timeout.ms", SEND_TIMEOUT.as_millis().to_string())
+            .create()?;
+
+        Ok(Self {
+            producer,
+            topic: config.kafka_topic.clone(),
+            encoding: config.encoding,
+        })
+    }
+}
+
+#[async_trait]
+impl EventSink for KafkaSink {
+    fn name(&self) -> &'static str {
+        "kafka"
+    }
+
+    async fn publish(&self, envelope: &EventEnvelope) -> Result<(), anyhow::Error> {
+        let payload = envelope.encode(self.encoding)?;
+        let version = envelope.version.to_string();
+
+        let headers = OwnedHeaders::new()
+            .insert(Header {
+                key: "id",
+                value: Some(envelope.id.as_str()),
+            })
+            .insert(Header {
+                key: "type",
+                value: Some(envelope.event_type()),
+            })
+            .insert(Header {
+                key: "version",
+                value: Some(version.as_str()),
+            })
+            .insert(Header {
+                key: "content-type",
+                value: Some(self.encoding.content_type()),
+            });
+
+        self.producer
+            .send(
+                FutureRecord::to(&self.topic)
+                    .key(&envelope.mint)
+                    .payload(&payload)
+                    .headers(headers),
+                Timeout::After(SEND_TIMEOUT),
+            )
+            .await
+            .map_err(|(err, _)| {
+                anyhow::Error::msg(format!("Error: Fail to produce event: {}", err))
+            })?;
+
+        Ok(())
+    }
+}
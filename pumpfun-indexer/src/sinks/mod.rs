@@ -0,0 +1,349 @@
+use std::{sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use sqlx::PgPool;
+use tokio::sync::mpsc::Receiver;
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    checkpoint::CheckpointTracker,
+    config::{EventSinkConfig, EventSinkKind},
+    events::EventEnvelope,
+    leader::Leadership,
+};
+
+pub mod kafka;
+pub mod nats;
+pub mod redis_stream;
+
+// Name under which the progress of the event sink is checkpointed
+pub const EVENT_SINK_DATASOURCE: &str = "event_sink";
+
+const INITIAL_RETRY_BACKOFF: Duration = Duration::from_millis(100);
+
+const CHECKPOINT_FLUSH_INTERVAL: Duration = Duration::from_secs(10);
+
+// What the sink worker receives, in order. Once a slot is completed every event of it and of the slots before it has
+// been sent ahead of the completion.
+#[derive(Debug)]
+pub enum SinkMessage {
+    Event(EventEnvelope),
+    // Slot and first signature of a slot whose events were all processed, sent by the dispatcher
+    SlotCompleted(u64, String),
+}
+
+// A broker the decoded events are published to. `publish` only returns Ok once the broker acknowledged the event.
+#[async_trait]
+pub trait EventSink: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    async fn publish(&self, envelope: &EventEnvelope) -> Result<(), anyhow::Error>;
+}
+
+// Connects the sink selected by EVENT_SINK, None when publishing is disabled
+pub async fn connect_sink(
+    config: &EventSinkConfig,
+) -> Result<Option<Box<dyn EventSink>>, anyhow::Error> {
+    let sink: Box<dyn EventSink> = match config.kind {
+        EventSinkKind::None => return Ok(None),
+        EventSinkKind::RedisStreams => {
+            Box::new(redis_stream::RedisStreamSink::connect(config).await?)
+        }
+        EventSinkKind::Kafka => Box::new(kafka::KafkaSink::connect(config)?),
+        EventSinkKind::Nats => Box::new(nats::NatsSink::connect(config).await?),
+    };
+
+    tracing::info!(sink = sink.name(), "Publishing decoded events");
+
+    Ok(Some(sink))
+}
+
+// Publishes the envelopes in order, retrying each one until the broker acknowledges it or the indexer shuts down. The
+// workers emit the events of different slots out of order, so the sink checkpoint follows the slots the dispatcher
+// completed instead. A slot completion comes after all of its events, it only moves the checkpoint once they were
+// published, so after a restart the backfill replays whatever was not acknowledged. A standby publishes nothing, so
+// like the live checkpoint it only flushes while it was already the leader on the previous round.
+pub async fn run_event_sink(
+    db: Arc<PgPool>,
+    sink: Box<dyn EventSink>,
+    config: EventSinkConfig,
+    mut rx: Receiver<SinkMessage>,
+    checkpoint: CheckpointTracker,
+    leadership: Leadership,
+    shutdown: CancellationToken,
+) {
+    let max_backoff = Duration::from_millis(config.max_retry_backoff_ms.max(1));
+
+    let mut flush_interval = tokio::time::interval(CHECKPOINT_FLUSH_INTERVAL);
+    let mut was_leader = leadership.is_leader();
+
+    'receive: loop {
+        let message = tokio::select! {
+            _ = flush_interval.tick() => {
+                let leader = leadership.is_leader();
+
+                if leader && was_leader {
+                    checkpoint.flush(db.clone(), false).await;
+                }
+
+                was_leader = leader;
+                continue;
+            }
+            message = rx.recv() => match message {
+                Some(message) => message,
+                None => break,
+            },
+            // Publish whatever is left before stopping
+            _ = shutdown.cancelled() => match rx.try_recv() {
+                Ok(message) => message,
+                Err(_) => break,
+            },
+        };
+
+        let envelope = match message {
+            SinkMessage::Event(envelope) => envelope,
+            SinkMessage::SlotCompleted(slot, signature) => {
+                checkpoint.observe(slot, signature).await;
+                continue;
+            }
+        };
+
+        let mut backoff = INITIAL_RETRY_BACKOFF;
+
+        loop {
+            match sink.publish(&envelope).await {
+                Ok(()) => break,
+                Err(err) => {
+                    tracing::error!(
+                        error = ?err,
+                        sink = sink.name(),
+                        id = %envelope.id,
+                        "Failed to publish event, retrying"
+                    );
+
+                    // The unpublished event keeps its slot and the later ones out of the checkpoint
+                    tokio::select! {
+                        _ = shutdown.cancelled() => {
+                            tracing::warn!(sink = sink.name(), "Stopping with unpublished events");
+                            break 'receive;
+                        }
+                        _ = tokio::time::sleep(backoff) => {}
+                    }
+
+                    backoff = (backoff * 2).min(max_backoff);
+                }
+            }
+        }
+    }
+
+    // The last completed slot whose events were all published is as far as the checkpoint can go
+    if leadership.is_leader() && was_leader {
+        checkpoint.flush(db, true).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, env};
+
+    use sqlx::postgres::PgPoolOptions;
+    use tokio::{net::TcpListener, sync::mpsc::channel};
+
+    use super::*;
+    use crate::{events::EventPayload, types::BondStatus};
+
+    fn event(id: &str, slot: u64) -> EventEnvelope {
+        EventEnvelope::new(
+            id.to_string(),
+            slot,
+            "signature",
+            "mint".to_string(),
+            EventPayload::StatusChange {
+                status: BondStatus::Graduated,
+            },
+        )
+    }
+
+    // The checkpoint is never written, the tracker is kept backfilling so no database is needed
+    fn checkpoint() -> CheckpointTracker {
+        let checkpoint = CheckpointTracker::new(EVENT_SINK_DATASOURCE);
+        checkpoint.set_backfilling(true);
+        checkpoint
+    }
+
+    fn unused_db() -> Arc<PgPool> {
+        Arc::new(
+            PgPoolOptions::new()
+                .connect_lazy("postgres://localhost/unused")
+                .unwrap(),
+        )
+    }
+
+    struct UnavailableSink;
+
+    #[async_trait]
+    impl EventSink for UnavailableSink {
+        fn name(&self) -> &'static str {
+            "unavailable"
+        }
+
+        async fn publish(&self, _: &EventEnvelope) -> Result<(), anyhow::Error> {
+            Err(anyhow::Error::msg("Broker unavailable"))
+        }
+    }
+
+    #[tokio::test]
+    async fn stops_retrying_on_shutdown() {
+        let (tx, rx) = channel(8);
+        let shutdown = CancellationToken::new();
+
+        let worker = tokio::spawn(run_event_sink(
+            unused_db(),
+            Box::new(UnavailableSink),
+            EventSinkConfig {
+                max_retry_backoff_ms: 60_000,
+                ..EventSinkConfig::default()
+            },
+            rx,
+            checkpoint(),
+            Leadership::default(),
+            shutdown.clone(),
+        ));
+
+        tx.send(SinkMessage::Event(event("sig:1:0", 1)))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        shutdown.cancel();
+
+        tokio::time::timeout(Duration::from_secs(1), worker)
+            .await
+            .expect("The sink kept retrying after the shutdown")
+            .unwrap();
+    }
+
+    // A standby publishes nothing, flushing its completed slots would move the shared checkpoint past events the
+    // leader has yet to publish. The database is a bare listener, any write would connect to it.
+    #[tokio::test]
+    async fn standby_never_writes_the_checkpoint() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let db = Arc::new(
+            PgPoolOptions::new()
+                .connect_lazy(&format!(
+                    "postgres://indexer@{}/unused",
+                    listener.local_addr().unwrap()
+                ))
+                .unwrap(),
+        );
+
+        let (tx, rx) = channel(8);
+        let shutdown = CancellationToken::new();
+
+        let worker = tokio::spawn(run_event_sink(
+            db,
+            Box::new(UnavailableSink),
+            EventSinkConfig::default(),
+            rx,
+            CheckpointTracker::new(EVENT_SINK_DATASOURCE),
+            Leadership::standby(),
+            shutdown.clone(),
+        ));
+
+        for slot in [10, 11, 12] {
+            tx.send(SinkMessage::SlotCompleted(slot, format!("sig{slot}")))
+                .await
+                .unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        shutdown.cancel();
+
+        tokio::time::timeout(Duration::from_secs(1), worker)
+            .await
+            .expect("The standby sink didn't stop")
+            .unwrap();
+
+        assert!(
+            tokio::time::timeout(Duration::from_millis(100), listener.accept())
+                .await
+                .is_err(),
+            "The standby wrote the sink checkpoint"
+        );
+    }
+
+    // Needs a local Redis at EVENT_SINK_TEST_REDIS_URL (default redis://127.0.0.1:6379), run with
+    // `cargo test -- --ignored`
+    #[tokio::test]
+    #[ignore]
+    async fn publishes_to_a_local_redis_stream_in_order() {
+        let config = EventSinkConfig {
+            redis_url: env::var("EVENT_SINK_TEST_REDIS_URL")
+                .unwrap_or_else(|_| "redis://127.0.0.1:6379".to_string()),
+            redis_stream: format!("pumpfun:test:{}", uuid::Uuid::new_v4()),
+            redis_stream_max_len: 1_000,
+            max_retry_backoff_ms: 1_000,
+            ..EventSinkConfig::default()
+        };
+
+        let sink = redis_stream::RedisStreamSink::connect(&config)
+            .await
+            .expect("Failed to connect to the local Redis");
+
+        let (tx, rx) = channel(8);
+
+        let worker = tokio::spawn(run_event_sink(
+            unused_db(),
+            Box::new(sink),
+            config.clone(),
+            rx,
+            checkpoint(),
+            Leadership::default(),
+            CancellationToken::new(),
+        ));
+
+        let ids = ["sig1:1:0", "sig1:1:1", "sig2:1:0"];
+
+        for (index, id) in ids.iter().enumerate() {
+            tx.send(SinkMessage::Event(event(id, 10 + index as u64)))
+                .await
+                .unwrap();
+        }
+        tx.send(SinkMessage::SlotCompleted(11, "sig1".to_string()))
+            .await
+            .unwrap();
+
+        drop(tx);
+        worker.await.unwrap();
+
+        let client = redis::Client::open(config.redis_url.as_str()).unwrap();
+        let mut redis = client.get_multiplexed_async_connection().await.unwrap();
+
+        let entries: Vec<(String, HashMap<String, String>)> = redis::cmd("XRANGE")
+            .arg(&config.redis_stream)
+            .arg("-")
+            .arg("+")
+            .query_async(&mut redis)
+            .await
+            .unwrap();
+
+        let _: () = redis::cmd("DEL")
+            .arg(&config.redis_stream)
+            .query_async(&mut redis)
+            .await
+            .unwrap();
+
+        let published: Vec<&str> = entries
+            .iter()
+            .map(|(_, fields)| fields["id"].as_str())
+            .collect();
+
+        assert_eq!(published, ids);
+        assert_eq!(entries[0].1["type"], "status_change");
+        assert_eq!(entries[0].1["content_type"], "application/json");
+
+        let decoded = EventEnvelope::decode(entries[2].1["payload"].as_bytes()).unwrap();
+        assert_eq!(decoded.id, "sig2:1:0");
+        assert_eq!(decoded.slot, 12);
+    }
+}
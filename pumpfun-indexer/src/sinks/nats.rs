@@ -0,0 +1,73 @@
+use async_nats::{
+    jetstream::{self, stream},
+    HeaderMap,
+};
+use async_trait::async_trait;
+
+use crate::{
+    config::{EventEncoding, EventSinkConfig},
+    events::EventEnvelope,
+    sinks::EventSink,
+};
+
+// Publishes every event to a JetStream stream on `<subject prefix>.<event type>`. The envelope id is used as the
+// message id so JetStream drops the duplicates of a replay within its de-duplication window.
+pub struct NatsSink {
+    jetstream: jetstream::Context,
+    subject_prefix: String,
+    encoding: EventEncoding,
+}
+
+impl NatsSink {
+    pub async fn connect(config: &EventSinkConfig) -> Result<Self, anyhow::Error> {
+        let client = async_nats::connect(&config.nats_url).await?;
+        let jetstream = jetstream::new(client);
+
+        jetstream
+            .get_or_create_stream(stream::Config {
+                name: config.nats_stream.clone(),
+                subjects: vec![format!("{}.>", config.nats_subject_prefix)],
+                ..Default::default()
+            })
+            .await
+            .map_err(|err| {
+                anyhow::Error::msg(format!("Error: Fail to create NATS stream: {}", err))
+            })?;
+
+        Ok(Self {
+            jetstream,
+            subject_prefix: config.nats_subject_prefix.clone(),
+            encoding: config.encoding,
+        })
+    }
+}
+
+#[async_trait]
+impl EventSink for NatsSink {
+    fn name(&self) -> &'static str {
+        "nats"
+    }
+
+    async fn publish(&self, envelope: &EventEnvelope) -> Result<(), anyhow::Error> {
+        let payload = envelope.encode(self.encoding)?;
+
+        let mut headers = HeaderMap::new();
+        headers.insert("Nats-Msg-Id", envelope.id.as_str());
+        headers.insert("Event-Version", envelope.version.to_string().as_str());
+        headers.insert("Content-Type", self.encoding.content_type());
+
+        // The second await waits for the JetStream acknowledgement
+        self.jetstream
+            .publish_with_headers(
+                format!("{}.{}", self.subject_prefix, envelope.event_type()),
+                headers,
+                payload.into(),
+            )
+            .await
+            .map_err(|err| anyhow::Error::msg(format!("Error: Fail to publish event: {}", err)))?
+            .await
+            .map_err(|err| anyhow::Error::msg(format!("Error: Fail to publish event: {}", err)))?;
+
+        Ok(())
+    }
+}
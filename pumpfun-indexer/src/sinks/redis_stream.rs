@@ -0,0 +1,63 @@
+use async_trait::async_trait;
+use redis::aio::MultiplexedConnection;
+
+use crate::{
+    config::{EventEncoding, EventSinkConfig},
+    events::EventEnvelope,
+    sinks::EventSink,
+};
+
+// Appends every event to a Redis stream with XADD, consumer groups give the consumers their own offsets
+pub struct RedisStreamSink {
+    connection: MultiplexedConnection,
+    stream: String,
+    max_len: usize,
+    encoding: EventEncoding,
+}
+
+impl RedisStreamSink {
+    pub async fn connect(config: &EventSinkConfig) -> Result<Self, anyhow::Error> {
+        let client = redis::Client::open(config.redis_url.as_str())?;
+        let connection = client.get_multiplexed_async_connection().await?;
+
+        Ok(Self {
+            connection,
+            stream: config.redis_stream.clone(),
+            max_len: config.redis_stream_max_len,
+            encoding: config.encoding,
+        })
+    }
+}
+
+#[async_trait]
+impl EventSink for RedisStreamSink {
+    fn name(&self) -> &'static str {
+        "redis_streams"
+    }
+
+    async fn publish(&self, envelope: &EventEnvelope) -> Result<(), anyhow::Error> {
+        let payload = envelope.encode(self.encoding)?;
+        let mut connection = self.connection.clone();
+
+        let _: String = redis::cmd("XADD")
+            .arg(&self.stream)
+            .arg("MAXLEN")
+            .arg("~")
+            .arg(self.max_len)
+            .arg("*")
+            .arg("id")
+            .arg(&envelope.id)
+            .arg("type")
+            .arg(envelope.event_type())
+            .arg("version")
+            .arg(envelope.version)
+            .arg("content_type")
+            .arg(self.encoding.content_type())
+            .arg("payload")
+            .arg(payload)
+            .query_async(&mut connection)
+            .await?;
+
+        Ok(())
+    }
+}
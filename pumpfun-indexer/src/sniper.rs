@@ -0,0 +1,283 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    config::SniperConfig,
+    types::{Token, Trade},
+};
+
+// Outcome of the launch analysis of a single token
+#[derive(Debug, Clone, Default)]
+pub struct LaunchAnalysis {
+    // Wallets that bought in the create transaction or within the launch slot window
+    pub sniper_wallets: HashSet<String>,
+    // Groups of early buyers that bought in the same launch slot or were funded from the same source
+    pub bundles: Vec<HashSet<String>>,
+    // Percentage of the total supply currently held by snipers and bundlers
+    pub sniper_percent: f64,
+    pub bundled: bool,
+}
+
+// Flags the buys made in the create transaction or the first `slot_window` slots after it, clusters the early
+// buyers that landed in the same slot or share a funding cluster and computes how much of the supply those wallets still hold.
+pub fn analyze_launch(
+    token: &Token,
+    trades: &[Trade],
+    clusters: &HashMap<String, String>,
+    config: &SniperConfig,
+    total_supply: f64,
+) -> LaunchAnalysis {
+    // Tokens indexed before slots were tracked fall back to the first traded slot
+    let Some(launch_slot) = token
+        .created_slot
+        .or_else(|| trades.iter().filter_map(|t| t.slot).min())
+    else {
+        return LaunchAnalysis::default();
+    };
+
+    let mut sniper_wallets = HashSet::new();
+    let mut buyers_per_slot: HashMap<i64, HashSet<String>> = HashMap::new();
+
+    for trade in trades.iter().filter(|t| t.is_buy) {
+        let in_create_tx = match (&token.created_signature, &trade.signature) {
+            (Some(created), Some(signature)) => created == signature,
+            _ => false,
+        };
+
+        let in_window = trade
+            .slot
+            .map(|slot| slot <= launch_slot + config.slot_window)
+            .unwrap_or(false);
+
+        if !in_create_tx && !in_window {
+            continue;
+        }
+
+        sniper_wallets.insert(trade.user_address.clone());
+
+        if let Some(slot) = trade.slot {
+            buyers_per_slot
+                .entry(slot)
+                .or_default()
+                .insert(trade.user_address.clone());
+        }
+    }
+
+    let mut buyers_per_cluster: HashMap<&str, HashSet<String>> = HashMap::new();
+
+    for wallet in sniper_wallets.iter() {
+        if let Some(cluster) = clusters.get(wallet) {
+            buyers_per_cluster
+                .entry(cluster.as_str())
+                .or_default()
+                .insert(wallet.clone());
+        }
+    }
+
+    let bundles: Vec<HashSet<String>> = buyers_per_slot
+        .into_values()
+        .chain(buyers_per_cluster.into_values())
+        .filter(|wallets| wallets.len() >= config.bundle_min_wallets)
+        .collect();
+
+    let mut balances: HashMap<&str, i64> = HashMap::new();
+
+    for trade in trades {
+        let balance = balances.entry(trade.user_address.as_str()).or_insert(0);
+
+        if trade.is_buy {
+            *balance += trade.token_amount;
+        } else {
+            *balance -= trade.token_amount;
+        }
+    }
+
+    let sniper_holdings: i64 = balances
+        .iter()
+        .filter(|(wallet, balance)| **balance > 0 && sniper_wallets.contains(**wallet))
+        .map(|(_, balance)| *balance)
+        .sum();
+
+    let sniper_percent = if total_supply > 0.0 {
+        ((sniper_holdings as f64 / 10f64.powi(6)) / total_supply) * 100.0
+    } else {
+        0.0
+    };
+
+    LaunchAnalysis {
+        bundled: !bundles.is_empty(),
+        sniper_wallets,
+        bundles,
+        sniper_percent,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::types::chrono::Utc;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::types::BondStatus;
+
+    const TOTAL_SUPPLY: f64 = 1_000_000_000.0;
+
+    // Raw amount of a number of whole tokens (6 decimals)
+    const TOKEN: i64 = 1_000_000;
+
+    fn token(created_slot: Option<i64>) -> Token {
+        Token {
+            id: Uuid::new_v4(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            name: "Token".to_string(),
+            ticker: "TKN".to_string(),
+            contract_address: "mint".to_string(),
+            bonding_curve_percentage: 0,
+            bond_status: BondStatus::NewlyLaunched,
+            market_cap: None,
+            uri: String::new(),
+            bonding_curve_address: "curve".to_string(),
+            creator_address: "creator".to_string(),
+            created_slot,
+            created_signature: Some("create".to_string()),
+        }
+    }
+
+    fn trade(wallet: &str, is_buy: bool, tokens: i64, slot: i64, signature: &str) -> Trade {
+        Trade {
+            id: Uuid::new_v4(),
+            sol_amount: 0,
+            token_amount: tokens * TOKEN,
+            is_buy,
+            user_address: wallet.to_string(),
+            token_id: Uuid::nil(),
+            slot: Some(slot),
+            signature: Some(signature.to_string()),
+        }
+    }
+
+    fn config() -> SniperConfig {
+        SniperConfig {
+            slot_window: 2,
+            bundle_min_wallets: 3,
+        }
+    }
+
+    #[test]
+    fn flags_the_buys_of_the_create_transaction_and_the_slot_window() {
+        let trades = [
+            trade("creator", true, 10_000_000, 100, "create"),
+            trade("sniper", true, 20_000_000, 102, "a"),
+            trade("late", true, 50_000_000, 103, "b"),
+            trade("seller", false, 1_000_000, 101, "c"),
+        ];
+
+        let analysis = analyze_launch(
+            &token(Some(100)),
+            &trades,
+            &HashMap::new(),
+            &config(),
+            TOTAL_SUPPLY,
+        );
+
+        let expected: HashSet<String> = ["creator", "sniper"].map(String::from).into();
+
+        assert_eq!(analysis.sniper_wallets, expected);
+        assert!(!analysis.bundled);
+        assert!((analysis.sniper_percent - 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn only_counts_what_the_snipers_still_hold() {
+        let trades = [
+            trade("sniper", true, 20_000_000, 100, "a"),
+            trade("dumper", true, 30_000_000, 101, "b"),
+            trade("dumper", false, 30_000_000, 150, "c"),
+        ];
+
+        let analysis = analyze_launch(
+            &token(Some(100)),
+            &trades,
+            &HashMap::new(),
+            &config(),
+            TOTAL_SUPPLY,
+        );
+
+        assert_eq!(analysis.sniper_wallets.len(), 2);
+        assert!((analysis.sniper_percent - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn bundles_the_buyers_of_a_slot_and_of_a_funding_cluster() {
+        let trades = [
+            trade("a", true, 1_000, 101, "1"),
+            trade("b", true, 1_000, 101, "2"),
+            trade("c", true, 1_000, 101, "3"),
+            trade("d", true, 1_000, 100, "4"),
+            trade("e", true, 1_000, 102, "5"),
+            trade("f", true, 1_000, 102, "6"),
+        ];
+
+        // d, e and f landed in different slots but were funded from the same source
+        let clusters: HashMap<String, String> = ["d", "e", "f"]
+            .map(|wallet| (wallet.to_string(), "funder".to_string()))
+            .into();
+
+        let analysis = analyze_launch(
+            &token(Some(100)),
+            &trades,
+            &clusters,
+            &config(),
+            TOTAL_SUPPLY,
+        );
+
+        let mut bundles: Vec<Vec<String>> = analysis
+            .bundles
+            .iter()
+            .map(|bundle| {
+                let mut wallets: Vec<String> = bundle.iter().cloned().collect();
+                wallets.sort();
+                wallets
+            })
+            .collect();
+        bundles.sort();
+
+        assert!(analysis.bundled);
+        assert_eq!(
+            bundles,
+            vec![
+                vec!["a".to_string(), "b".to_string(), "c".to_string()],
+                vec!["d".to_string(), "e".to_string(), "f".to_string()],
+            ]
+        );
+    }
+
+    #[test]
+    fn falls_back_to_the_first_traded_slot() {
+        let trades = [
+            trade("early", true, 1_000, 500, "a"),
+            trade("late", true, 1_000, 510, "b"),
+        ];
+
+        let analysis = analyze_launch(
+            &token(None),
+            &trades,
+            &HashMap::new(),
+            &config(),
+            TOTAL_SUPPLY,
+        );
+
+        let expected: HashSet<String> = ["early"].map(String::from).into();
+
+        assert_eq!(analysis.sniper_wallets, expected);
+    }
+
+    #[test]
+    fn returns_nothing_without_a_launch_slot() {
+        let analysis = analyze_launch(&token(None), &[], &HashMap::new(), &config(), TOTAL_SUPPLY);
+
+        assert!(analysis.sniper_wallets.is_empty());
+        assert!(!analysis.bundled);
+        assert_eq!(analysis.sniper_percent, 0.0);
+    }
+}
@@ -0,0 +1,482 @@
+use std::{
+    collections::{HashMap, HashSet},
+    hash::{BuildHasher, RandomState},
+    num::NonZeroUsize,
+    sync::{Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use lru::LruCache;
+
+use crate::{metrics::metrics, types::BondingCurveAndMcInfo};
+
+// Tokens kept when the state is built without a configured bound
+pub const DEFAULT_MAX_TOKENS: usize = 100_000;
+
+// Shards of the state map when none is configured
+pub const DEFAULT_SHARDS: usize = 64;
+
+// Mints missing from the DB are remembered this long so their trades don't query it every time
+const UNKNOWN_MINT_TTL: Duration = Duration::from_secs(300);
+const UNKNOWN_MINTS_CAPACITY: usize = 10_000;
+
+#[derive(Debug)]
+struct TrackedToken {
+    info: BondingCurveAndMcInfo,
+    last_active: Instant,
+}
+
+// Result of looking up a mint before applying a trade
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lookup {
+    Hit,
+    // Not in memory, it may have been evicted and has to be reloaded from the DB
+    Miss,
+    // Recently looked up and not stored, the trade is skipped
+    Unknown,
+}
+
+// Bonding curve progress and market cap of the active tokens, keyed by mint. Bounded to `capacity` tokens, the least
+// recently traded ones are evicted first and reloaded from the DB on their next trade. Remembers which tokens changed
+// since the last flush so only those are written back, evicted tokens with unwritten changes are kept until then.
+#[derive(Debug)]
+pub struct BondingState {
+    tokens: LruCache<String, TrackedToken>,
+    dirty: HashSet<String>,
+    evicted: Vec<BondingCurveAndMcInfo>,
+    unknown: LruCache<String, Instant>,
+}
+
+impl Default for BondingState {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_TOKENS)
+    }
+}
+
+impl BondingState {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            tokens: LruCache::new(NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN)),
+            dirty: HashSet::new(),
+            evicted: Vec::new(),
+            unknown: LruCache::new(
+                NonZeroUsize::new(UNKNOWN_MINTS_CAPACITY.min(capacity))
+                    .unwrap_or(NonZeroUsize::MIN),
+            ),
+        }
+    }
+
+    // State loaded from the DB with the newest tokens first, nothing to flush yet
+    pub fn from_tokens(tokens: Vec<BondingCurveAndMcInfo>, capacity: usize) -> Self {
+        let mut state = Self::new(capacity);
+
+        // Tracked oldest first so the newest tokens are the last to be evicted
+        for info in tokens.into_iter().rev() {
+            state.track(info);
+        }
+
+        state
+    }
+
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    pub fn get(&self, mint: &str) -> Option<&BondingCurveAndMcInfo> {
+        self.tokens.peek(mint).map(|token| &token.info)
+    }
+
+    pub fn lookup(&mut self, mint: &str) -> Lookup {
+        if self.tokens.contains(mint) {
+            metrics()
+                .state_map_lookups
+                .with_label_values(&["hit"])
+                .inc();
+            return Lookup::Hit;
+        }
+
+        if let Some(looked_up_at) = self.unknown.get(mint) {
+            if looked_up_at.elapsed() < UNKNOWN_MINT_TTL {
+                metrics()
+                    .state_map_lookups
+                    .with_label_values(&["unknown"])
+                    .inc();
+                return Lookup::Unknown;
+            }
+
+            self.unknown.pop(mint);
+        }
+
+        metrics()
+            .state_map_lookups
+            .with_label_values(&["miss"])
+            .inc();
+
+        Lookup::Miss
+    }
+
+    // Starts tracking a new token, its row only has the defaults so it is flushed too. A create replayed by a backfill
+    // after trades of the token were already applied leaves their state alone.
+    pub fn insert(&mut self, info: BondingCurveAndMcInfo) {
+        if self.tokens.contains(&info.contract_address) {
+            return;
+        }
+
+        self.dirty.insert(info.contract_address.clone());
+        self.track(info);
+    }
+
+    // Tracks a token reloaded from the DB after a miss. If it was evicted with changes that aren't written yet, the
+    // newest of those is tracked instead of the DB row and flushed with the next batch.
+    pub fn reload(&mut self, info: BondingCurveAndMcInfo) {
+        let mut latest = info;
+        let mut unwritten = false;
+
+        self.evicted.retain(|evicted| {
+            if evicted.contract_address != latest.contract_address {
+                return true;
+            }
+
+            if evicted.market_cap_slot >= latest.market_cap_slot {
+                latest = evicted.clone();
+                unwritten = true;
+            }
+
+            false
+        });
+
+        if unwritten {
+            self.dirty.insert(latest.contract_address.clone());
+        }
+
+        self.track(latest);
+    }
+
+    // Remembers a mint the DB doesn't have, so its next trades are skipped without a query
+    pub fn mark_unknown(&mut self, mint: &str) {
+        self.unknown.put(mint.to_string(), Instant::now());
+    }
+
+    fn track(&mut self, info: BondingCurveAndMcInfo) {
+        let mint = info.contract_address.clone();
+
+        self.unknown.pop(&mint);
+
+        let token = TrackedToken {
+            info,
+            last_active: Instant::now(),
+        };
+
+        if let Some((evicted, token)) = self.tokens.push(mint.clone(), token) {
+            if evicted != mint {
+                self.evict(evicted, token, "capacity");
+            }
+        }
+    }
+
+    fn evict(&mut self, mint: String, token: TrackedToken, reason: &str) {
+        if self.dirty.remove(&mint) {
+            self.evicted.push(token.info);
+        }
+
+        metrics()
+            .state_map_evictions
+            .with_label_values(&[reason])
+            .inc();
+    }
+
+    // Applies the state after a trade of the given slot. A trade older than the one the state comes from, like one
+    // replayed by a backfill while the live stream is ahead, is ignored. Returns false if the token isn't tracked.
+    pub fn update(
+        &mut self,
+        mint: &str,
+        bonding_curve_percentage: i32,
+        market_cap: i64,
+        slot: u64,
+    ) -> bool {
+        let Some(token) = self.tokens.get_mut(mint) else {
+            return false;
+        };
+
+        token.last_active = Instant::now();
+
+        let info = &mut token.info;
+        let slot = slot as i64;
+
+        if info.market_cap_slot.is_some_and(|applied| slot < applied) {
+            metrics().stale_trades_ignored.inc();
+            return true;
+        }
+
+        info.market_cap_slot = Some(slot);
+
+        if info.bonding_curve_percentage != bonding_curve_percentage
+            || info.market_cap != Some(market_cap)
+        {
+            info.bonding_curve_percentage = bonding_curve_percentage;
+            info.market_cap = Some(market_cap);
+
+            self.dirty.insert(mint.to_string());
+        }
+
+        true
+    }
+
+    // Stops tracking a token that won't trade on the curve anymore, its unwritten changes are still flushed
+    pub fn remove(&mut self, mint: &str, reason: &str) {
+        if let Some(token) = self.tokens.pop(mint) {
+            self.evict(mint.to_string(), token, reason);
+        }
+    }
+
+    // Evicts the tokens without a trade for `idle_ttl`, starting from the least recently traded
+    pub fn evict_idle(&mut self, idle_ttl: Duration) {
+        while let Some((_, token)) = self.tokens.peek_lru() {
+            if token.last_active.elapsed() < idle_ttl {
+                break;
+            }
+
+            if let Some((mint, token)) = self.tokens.pop_lru() {
+                self.evict(mint, token, "idle");
+            }
+        }
+    }
+
+    pub fn dirty_len(&self) -> usize {
+        self.dirty.len() + self.evicted.len()
+    }
+
+    // Current state of the tokens changed since the last call, one per mint, they count as flushed from now on. A mint
+    // evicted and tracked again since the last call is taken with its tracked state, its newest eviction otherwise.
+    pub fn take_dirty(&mut self) -> Vec<BondingCurveAndMcInfo> {
+        let mut changed: HashMap<String, BondingCurveAndMcInfo> = HashMap::new();
+
+        for info in std::mem::take(&mut self.evicted) {
+            let info = match self.tokens.peek(&info.contract_address) {
+                Some(token) => token.info.clone(),
+                None => info,
+            };
+
+            match changed.get(&info.contract_address) {
+                Some(newer) if newer.market_cap_slot > info.market_cap_slot => {}
+                _ => {
+                    changed.insert(info.contract_address.clone(), info);
+                }
+            }
+        }
+
+        for mint in self.dirty.drain() {
+            if let Some(token) = self.tokens.peek(&mint) {
+                changed.insert(mint, token.info.clone());
+            }
+        }
+
+        changed.into_values().collect()
+    }
+
+    // Flags tokens as changed again, used when writing them failed
+    pub fn restore_dirty(&mut self, changed: &[BondingCurveAndMcInfo]) {
+        for info in changed {
+            if self.tokens.contains(&info.contract_address) {
+                self.dirty.insert(info.contract_address.clone());
+            } else {
+                self.evicted.push(info.clone());
+            }
+        }
+    }
+}
+
+// BondingState split into shards by mint, each behind its own lock, so trades of different tokens don't contend and
+// the flusher only holds one shard at a time. Every shard is an LRU of its share of the capacity. The locks are never
+// held across an await.
+#[derive(Debug)]
+pub struct ShardedBondingState {
+    shards: Vec<Mutex<BondingState>>,
+    hasher: RandomState,
+}
+
+impl Default for ShardedBondingState {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_TOKENS, DEFAULT_SHARDS)
+    }
+}
+
+impl ShardedBondingState {
+    pub fn new(capacity: usize, shards: usize) -> Self {
+        let shards = shards.max(1);
+        let shard_capacity = capacity.div_ceil(shards);
+
+        Self {
+            shards: (0..shards)
+                .map(|_| Mutex::new(BondingState::new(shard_capacity)))
+                .collect(),
+            hasher: RandomState::new(),
+        }
+    }
+
+    // State loaded from the DB with the newest tokens first, nothing to flush yet
+    pub fn from_tokens(tokens: Vec<BondingCurveAndMcInfo>, capacity: usize, shards: usize) -> Self {
+        let state = Self::new(capacity, shards);
+
+        // Tracked oldest first so the newest tokens are the last to be evicted
+        for info in tokens.into_iter().rev() {
+            state.reload(info);
+        }
+
+        state
+    }
+
+    fn shard(&self, mint: &str) -> MutexGuard<'_, BondingState> {
+        let index = self.hasher.hash_one(mint) as usize % self.shards.len();
+
+        self.shards[index].lock().unwrap()
+    }
+
+    pub fn len(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| shard.lock().unwrap().len())
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, mint: &str) -> Option<BondingCurveAndMcInfo> {
+        self.shard(mint).get(mint).cloned()
+    }
+
+    pub fn lookup(&self, mint: &str) -> Lookup {
+        self.shard(mint).lookup(mint)
+    }
+
+    pub fn insert(&self, info: BondingCurveAndMcInfo) {
+        self.shard(&info.contract_address).insert(info);
+    }
+
+    pub fn reload(&self, info: BondingCurveAndMcInfo) {
+        self.shard(&info.contract_address).reload(info);
+    }
+
+    pub fn mark_unknown(&self, mint: &str) {
+        self.shard(mint).mark_unknown(mint);
+    }
+
+    pub fn update(
+        &self,
+        mint: &str,
+        bonding_curve_percentage: i32,
+        market_cap: i64,
+        slot: u64,
+    ) -> bool {
+        self.shard(mint)
+            .update(mint, bonding_curve_percentage, market_cap, slot)
+    }
+
+    pub fn remove(&self, mint: &str, reason: &str) {
+        self.shard(mint).remove(mint, reason);
+    }
+
+    pub fn evict_idle(&self, idle_ttl: Duration) {
+        for shard in self.shards.iter() {
+            shard.lock().unwrap().evict_idle(idle_ttl);
+        }
+    }
+
+    pub fn dirty_len(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| shard.lock().unwrap().dirty_len())
+            .sum()
+    }
+
+    // Changed tokens of every shard, taken one shard at a time
+    pub fn take_dirty(&self) -> Vec<BondingCurveAndMcInfo> {
+        let mut changed = Vec::new();
+
+        for shard in self.shards.iter() {
+            changed.extend(shard.lock().unwrap().take_dirty());
+        }
+
+        changed
+    }
+
+    pub fn restore_dirty(&self, changed: &[BondingCurveAndMcInfo]) {
+        for info in changed {
+            self.shard(&info.contract_address)
+                .restore_dirty(std::slice::from_ref(info));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(mint: &str, market_cap: i64, slot: i64) -> BondingCurveAndMcInfo {
+        BondingCurveAndMcInfo {
+            contract_address: mint.to_string(),
+            bonding_curve_address: format!("{}-curve", mint),
+            bonding_curve_percentage: 0,
+            market_cap: Some(market_cap),
+            market_cap_slot: Some(slot),
+        }
+    }
+
+    #[test]
+    fn takes_one_row_per_mint_after_a_reload() {
+        let mut state = BondingState::new(1);
+
+        state.insert(token("a", 0, 1));
+        assert!(state.update("a", 10, 1_000, 2));
+
+        // Evicts "a" with unwritten changes
+        state.insert(token("b", 0, 1));
+        state.remove("b", "graduated");
+
+        // Reloaded from a DB row that doesn't have the unwritten changes yet
+        state.reload(token("a", 0, 1));
+        assert_eq!(state.get("a").and_then(|info| info.market_cap), Some(1_000));
+
+        assert!(state.update("a", 20, 2_000, 3));
+
+        let mut dirty = state.take_dirty();
+        dirty.sort_by(|x, y| x.contract_address.cmp(&y.contract_address));
+
+        let mints: Vec<&str> = dirty
+            .iter()
+            .map(|info| info.contract_address.as_str())
+            .collect();
+
+        assert_eq!(mints, vec!["a", "b"]);
+        assert_eq!(dirty[0].market_cap, Some(2_000));
+        assert_eq!(dirty[0].market_cap_slot, Some(3));
+        assert!(state.take_dirty().is_empty());
+    }
+
+    #[test]
+    fn keeps_the_tracked_state_of_a_restored_mint() {
+        let mut state = BondingState::new(10);
+
+        state.insert(token("a", 0, 1));
+        assert!(state.update("a", 10, 1_000, 2));
+
+        let failed = state.take_dirty();
+        assert!(state.update("a", 20, 2_000, 3));
+
+        // A failed write of a token that was evicted meanwhile comes back as an evicted row
+        state.remove("a", "idle");
+        state.restore_dirty(&failed);
+        state.reload(token("a", 0, 1));
+
+        let dirty = state.take_dirty();
+
+        assert_eq!(dirty.len(), 1);
+        assert_eq!(dirty[0].market_cap, Some(2_000));
+    }
+}
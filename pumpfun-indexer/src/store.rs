@@ -0,0 +1,115 @@
+use std::sync::Arc;
+
+use carbon_pumpfun_decoder::instructions::create_event::CreateEvent;
+use solana_pubkey::Pubkey;
+use sqlx::PgPool;
+
+use crate::{
+    cache::CacheInvalidator,
+    db::{
+        checkpoint::{get_checkpoint, save_checkpoint},
+        token::{
+            change_status, create_token, get_bonding_curve_and_mc_info,
+            get_token_bonding_curve_and_mc_info, get_token_launch,
+            update_bonding_curve_and_market_cap,
+        },
+        trade::copy_trades,
+    },
+    events::EventEnvelope,
+    state::ShardedBondingState,
+    types::{BondStatus, BondingCurveAndMcInfo, Checkpoint, TokenLaunch},
+    utils::connect_db,
+    BondingMcStateMap,
+};
+
+// Writes of the instruction processor and the tasks it feeds, on top of the Postgres pool. Services embedding the
+// processor use it to share the pool with their own queries.
+#[derive(Debug, Clone)]
+pub struct Store {
+    db: Arc<PgPool>,
+}
+
+impl Store {
+    pub fn new(db: Arc<PgPool>) -> Self {
+        Self { db }
+    }
+
+    pub async fn connect(database_url: &str) -> Result<Self, anyhow::Error> {
+        Ok(Self::new(Arc::new(connect_db(database_url).await?)))
+    }
+
+    pub fn pool(&self) -> Arc<PgPool> {
+        self.db.clone()
+    }
+
+    pub async fn create_token(&self, event: CreateEvent, slot: u64, signature: String) {
+        create_token(self.db.clone(), event, slot, signature).await;
+    }
+
+    pub async fn change_status(&self, status: BondStatus, mint: Pubkey) {
+        change_status(status, mint, self.db.clone()).await;
+    }
+
+    // Bonding curve progress and market cap of the most recently created tokens still on the curve, keyed by mint.
+    // At most `capacity` tokens are kept, the others are reloaded with `reload_token_state` when they trade.
+    pub async fn load_bonding_state(
+        &self,
+        capacity: usize,
+        shards: usize,
+    ) -> Result<BondingMcStateMap, anyhow::Error> {
+        let state = get_bonding_curve_and_mc_info(self.db.clone(), capacity as i64).await?;
+
+        Ok(Arc::new(ShardedBondingState::from_tokens(
+            state, capacity, shards,
+        )))
+    }
+
+    pub async fn reload_token_state(
+        &self,
+        mint: &str,
+    ) -> Result<Option<BondingCurveAndMcInfo>, anyhow::Error> {
+        get_token_bonding_curve_and_mc_info(self.db.clone(), mint).await
+    }
+
+    pub async fn token_launch(&self, mint: &str) -> Result<Option<TokenLaunch>, anyhow::Error> {
+        get_token_launch(self.db.clone(), mint).await
+    }
+
+    // Writes the tokens changed since the last flush in batches of `batch_size` and invalidates their cached responses
+    // once written. The tokens of a failed batch are flagged again so the next flush retries them.
+    pub async fn flush_bonding_state(
+        &self,
+        state: &BondingMcStateMap,
+        batch_size: usize,
+        cache: &CacheInvalidator,
+    ) {
+        let dirty = state.take_dirty();
+
+        for batch in dirty.chunks(batch_size.max(1)) {
+            if update_bonding_curve_and_market_cap(self.db.clone(), batch)
+                .await
+                .is_err()
+            {
+                state.restore_dirty(batch);
+                continue;
+            }
+
+            for token in batch {
+                cache.token_changed(&token.contract_address);
+            }
+        }
+    }
+
+    // Stores trades the way the trade writer does, returns how many were new
+    pub async fn store_trades(&self, trades: &[EventEnvelope]) -> Result<u64, sqlx::Error> {
+        copy_trades(&self.db, trades).await
+    }
+
+    pub async fn checkpoint(&self, datasource: &str) -> Result<Option<Checkpoint>, anyhow::Error> {
+        get_checkpoint(self.db.clone(), datasource).await
+    }
+
+    pub async fn save_checkpoint(&self, checkpoint: &Checkpoint) {
+        save_checkpoint(self.db.clone(), checkpoint).await;
+    }
+}
@@ -0,0 +1,176 @@
+use std::{sync::Arc, time::Duration};
+
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use serde::Deserialize;
+use sqlx::{
+    types::chrono::{DateTime, Utc},
+    PgPool,
+};
+use utoipa::IntoParams;
+
+use crate::{
+    cache::{candles_tag, token_tag, ResponseCache},
+    config::{FundingConfig, SniperConfig},
+    db::{graphql::fetch_candles, query::fetch_token_data},
+    errors::{ApiError, ErrorResponse},
+    graphql::objects::CandleObject,
+    types::TokenDetails,
+};
+
+const DEFAULT_CANDLE_INTERVAL_SECS: i64 = 60;
+const MAX_CANDLE_INTERVAL_SECS: i64 = 86_400;
+const MAX_CANDLES: i64 = 1_000;
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct CandlesQuery {
+    // Candle width in seconds, 1 to 86400 (default 60)
+    pub interval_secs: Option<i64>,
+    // Start of the range, RFC 3339 (default: a day before `to`)
+    pub from: Option<DateTime<Utc>>,
+    // End of the range, RFC 3339 (default: now)
+    pub to: Option<DateTime<Utc>>,
+}
+
+//* This endpoint returns the token data from the DB */
+//* Use http://localhost:8000/tokens to fetch the tokens information */
+#[utoipa::path(
+    tag = "tokens",
+    responses(
+        (status = 200, description = "Every indexed token with its holder stats", body = [TokenDetails]),
+        (status = 304, description = "Unchanged since the ETag sent in If-None-Match"),
+        (status = 500, description = "The tokens could not be loaded", body = ErrorResponse),
+    )
+)]
+#[get("/tokens")]
+pub async fn get_tokens(
+    req: HttpRequest,
+    db: web::Data<Arc<PgPool>>,
+    cache: web::Data<ResponseCache>,
+    sniper_config: web::Data<SniperConfig>,
+    funding_config: web::Data<FundingConfig>,
+) -> Result<HttpResponse, ApiError> {
+    let conn = db.get_ref();
+
+    cache
+        .json(
+            &req,
+            "tokens",
+            &[],
+            Duration::from_secs(cache.config().tokens_ttl_secs),
+            async {
+                Ok(fetch_token_data(
+                    conn,
+                    sniper_config.get_ref(),
+                    funding_config.get_ref(),
+                    None,
+                )
+                .await?)
+            },
+        )
+        .await
+}
+
+//* This endpoint returns the data of a single token */
+//* Use http://localhost:8000/tokens/<contract address> */
+#[utoipa::path(
+    tag = "tokens",
+    params(("address" = String, Path, description = "Contract address of the token")),
+    responses(
+        (status = 200, body = TokenDetails),
+        (status = 304, description = "Unchanged since the ETag sent in If-None-Match"),
+        (status = 404, body = ErrorResponse),
+        (status = 500, body = ErrorResponse),
+    )
+)]
+#[get("/tokens/{address}")]
+pub async fn get_token(
+    req: HttpRequest,
+    db: web::Data<Arc<PgPool>>,
+    cache: web::Data<ResponseCache>,
+    sniper_config: web::Data<SniperConfig>,
+    funding_config: web::Data<FundingConfig>,
+    address: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let conn = db.get_ref();
+
+    cache
+        .json(
+            &req,
+            &format!("token:{}", address),
+            &[token_tag(&address)],
+            Duration::from_secs(cache.config().token_ttl_secs),
+            async {
+                fetch_token_data(
+                    conn,
+                    sniper_config.get_ref(),
+                    funding_config.get_ref(),
+                    Some(&address),
+                )
+                .await?
+                .into_iter()
+                .next()
+                .ok_or_else(|| ApiError::NotFound("Token not found".to_string()))
+            },
+        )
+        .await
+}
+
+//* This endpoint returns the OHLCV candles of the price of a token in SOL */
+//* Use http://localhost:8000/tokens/<contract address>/candles?interval_secs=300 */
+#[utoipa::path(
+    tag = "tokens",
+    params(("address" = String, Path, description = "Contract address of the token"), CandlesQuery),
+    responses(
+        (status = 200, description = "Candles, oldest first", body = [CandleObject]),
+        (status = 304, description = "Unchanged since the ETag sent in If-None-Match"),
+        (status = 500, body = ErrorResponse),
+    )
+)]
+#[get("/tokens/{address}/candles")]
+pub async fn get_token_candles(
+    req: HttpRequest,
+    db: web::Data<Arc<PgPool>>,
+    cache: web::Data<ResponseCache>,
+    address: web::Path<String>,
+    query: web::Query<CandlesQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let interval_secs = query
+        .interval_secs
+        .unwrap_or(DEFAULT_CANDLE_INTERVAL_SECS)
+        .clamp(1, MAX_CANDLE_INTERVAL_SECS);
+
+    let to = query.to.unwrap_or_else(Utc::now);
+    let from = query
+        .from
+        .unwrap_or_else(|| to - sqlx::types::chrono::Duration::days(1));
+
+    // A range ending now gets a new key every interval, the newest candle is still moving
+    let key = format!(
+        "candles:{}:{}:{}:{}",
+        address,
+        interval_secs,
+        query
+            .from
+            .map(|from| from.timestamp().to_string())
+            .unwrap_or_default(),
+        query
+            .to
+            .map(|to| to.timestamp().to_string())
+            .unwrap_or_else(|| format!("now{}", to.timestamp() / interval_secs)),
+    );
+
+    cache
+        .json(
+            &req,
+            &key,
+            &[candles_tag(&address)],
+            Duration::from_secs(cache.config().candles_ttl_secs),
+            async {
+                Ok(
+                    fetch_candles(db.get_ref(), &address, interval_secs, from, to, MAX_CANDLES)
+                        .await?,
+                )
+            },
+        )
+        .await
+}
@@ -0,0 +1,265 @@
+use std::{
+    collections::HashSet,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use sqlx::PgPool;
+use tokio::sync::Semaphore;
+use tokio_util::task::TaskTracker;
+
+use crate::{
+    cache::CacheInvalidator, config::TradeWriterConfig, db::trade::copy_trades,
+    events::EventEnvelope, metrics::metrics,
+};
+
+// Wait before retrying a failed write, doubled on every attempt up to the maximum
+const RETRY_DELAY: Duration = Duration::from_millis(200);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(10);
+
+// Buffers the trades and writes them with `copy_trades` once a batch is full or the flush interval has passed. The
+// batch size adapts to the latency of the writes and at most `max_in_flight` writes run at the same time, pushing
+// waits for a free slot beyond that. A failed write is retried with backoff while it holds its slot, so a database
+// outage slows the consumer down instead of losing the trades. The cached responses of the tokens of a batch are
+// invalidated once it is stored.
+pub struct TradeWriter {
+    db: Arc<PgPool>,
+    config: TradeWriterConfig,
+    cache: CacheInvalidator,
+    buffer: Vec<EventEnvelope>,
+    batch_size: Arc<AtomicUsize>,
+    in_flight: Arc<Semaphore>,
+    tracker: TaskTracker,
+    last_flush: Instant,
+}
+
+impl TradeWriter {
+    pub fn new(db: Arc<PgPool>, config: TradeWriterConfig) -> Self {
+        let batch_size = config.min_batch_size.max(1);
+
+        metrics().trade_batch_target.set(batch_size as i64);
+
+        Self {
+            db,
+            in_flight: Arc::new(Semaphore::new(config.max_in_flight.max(1))),
+            config,
+            cache: CacheInvalidator::default(),
+            buffer: Vec::with_capacity(batch_size),
+            batch_size: Arc::new(AtomicUsize::new(batch_size)),
+            tracker: TaskTracker::new(),
+            last_flush: Instant::now(),
+        }
+    }
+
+    pub fn cache(mut self, cache: CacheInvalidator) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    pub fn flush_interval(&self) -> Duration {
+        Duration::from_millis(self.config.flush_interval_ms.max(1))
+    }
+
+    // Current target size of a batch
+    pub fn batch_size(&self) -> usize {
+        self.batch_size.load(Ordering::Relaxed)
+    }
+
+    pub fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+
+    // Whether buffered trades waited for a whole flush interval without filling a batch
+    pub fn is_due(&self) -> bool {
+        !self.buffer.is_empty() && self.last_flush.elapsed() >= self.flush_interval()
+    }
+
+    pub async fn push(&mut self, trade: EventEnvelope) {
+        self.buffer.push(trade);
+
+        if self.buffer.len() >= self.batch_size() {
+            self.flush().await;
+        }
+
+        metrics().trade_buffer_depth.set(self.buffer.len() as i64);
+    }
+
+    // Hands the buffered trades to a new write, waiting first if `max_in_flight` writes are already running
+    pub async fn flush(&mut self) {
+        self.last_flush = Instant::now();
+
+        if self.buffer.is_empty() {
+            return;
+        }
+
+        let permit = self
+            .in_flight
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("The write semaphore is never closed");
+
+        let trades = std::mem::replace(&mut self.buffer, Vec::with_capacity(self.batch_size()));
+        let db = self.db.clone();
+        let batch_size = self.batch_size.clone();
+        let config = self.config.clone();
+        let cache = self.cache.clone();
+
+        metrics().trade_buffer_depth.set(0);
+        metrics().trade_writes_in_flight.inc();
+
+        self.tracker.spawn(async move {
+            let mut delay = RETRY_DELAY;
+
+            for attempt in 1..=config.max_write_attempts.max(1) {
+                let started = Instant::now();
+                let result = copy_trades(&db, &trades).await;
+
+                metrics().observe_db_batch("copy_trades", trades.len(), started, result.is_err());
+
+                adapt_batch_size(&batch_size, &config, started.elapsed(), result.is_ok());
+
+                match result {
+                    Ok(stored) => {
+                        tracing::debug!(
+                            trades = trades.len(),
+                            stored,
+                            batch_size = batch_size.load(Ordering::Relaxed),
+                            "Stored trades batch"
+                        );
+
+                        let mints: HashSet<&str> =
+                            trades.iter().map(|trade| trade.mint.as_str()).collect();
+
+                        for mint in mints {
+                            cache.token_changed(mint);
+                        }
+
+                        break;
+                    }
+                    Err(err) if attempt < config.max_write_attempts => {
+                        tracing::warn!(error = ?err, trades = trades.len(), attempt, "Failed to store trades batch, retrying");
+
+                        tokio::time::sleep(delay).await;
+                        delay = (delay * 2).min(MAX_RETRY_DELAY);
+                    }
+                    Err(err) => {
+                        tracing::error!(error = ?err, trades = trades.len(), attempt, "Failed to store trades batch, giving up");
+
+                        metrics()
+                            .trades_dropped
+                            .with_label_values(&["write_failed"])
+                            .inc_by(trades.len() as u64);
+                    }
+                }
+            }
+
+            metrics().trade_writes_in_flight.dec();
+
+            drop(permit);
+        });
+    }
+
+    // Writes what is left and waits for every write to finish
+    pub async fn close(mut self) {
+        self.flush().await;
+
+        self.tracker.close();
+        self.tracker.wait().await;
+    }
+}
+
+// Grows the batches by a quarter while the writes take less than half the target latency and halves them once a write
+// goes over it or fails, within the configured bounds
+fn adapt_batch_size(
+    batch_size: &AtomicUsize,
+    config: &TradeWriterConfig,
+    latency: Duration,
+    succeeded: bool,
+) {
+    let target = Duration::from_millis(config.target_latency_ms);
+    let current = batch_size.load(Ordering::Relaxed);
+
+    let next = if !succeeded || latency > target {
+        current / 2
+    } else if latency < target / 2 {
+        current + current / 4 + 1
+    } else {
+        current
+    };
+
+    let next = next.clamp(
+        config.min_batch_size.max(1),
+        config.max_batch_size.max(config.min_batch_size).max(1),
+    );
+
+    if next != current {
+        batch_size.store(next, Ordering::Relaxed);
+
+        metrics().trade_batch_target.set(next as i64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> TradeWriterConfig {
+        TradeWriterConfig {
+            min_batch_size: 100,
+            max_batch_size: 1_000,
+            target_latency_ms: 200,
+            ..TradeWriterConfig::default()
+        }
+    }
+
+    fn adapt(current: usize, latency_ms: u64, succeeded: bool) -> usize {
+        let batch_size = AtomicUsize::new(current);
+
+        adapt_batch_size(
+            &batch_size,
+            &config(),
+            Duration::from_millis(latency_ms),
+            succeeded,
+        );
+
+        batch_size.load(Ordering::Relaxed)
+    }
+
+    #[test]
+    fn grows_while_the_writes_are_fast() {
+        assert_eq!(adapt(100, 50, true), 126);
+        assert_eq!(adapt(900, 50, true), 1_000);
+    }
+
+    #[test]
+    fn keeps_the_size_near_the_target_latency() {
+        assert_eq!(adapt(400, 150, true), 400);
+        assert_eq!(adapt(400, 200, true), 400);
+    }
+
+    #[test]
+    fn halves_on_slow_or_failed_writes() {
+        assert_eq!(adapt(400, 250, true), 200);
+        assert_eq!(adapt(400, 10, false), 200);
+        assert_eq!(adapt(150, 500, true), 100);
+    }
+
+    #[test]
+    fn stays_within_the_bounds_when_misconfigured() {
+        let batch_size = AtomicUsize::new(1);
+        let config = TradeWriterConfig {
+            min_batch_size: 0,
+            max_batch_size: 0,
+            target_latency_ms: 200,
+            ..TradeWriterConfig::default()
+        };
+
+        adapt_batch_size(&batch_size, &config, Duration::from_millis(10), true);
+
+        assert_eq!(batch_size.load(Ordering::Relaxed), 1);
+    }
+}
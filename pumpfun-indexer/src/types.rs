@@ -0,0 +1,263 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{
+    prelude::FromRow,
+    types::chrono::{DateTime, NaiveDate, Utc},
+    Type,
+};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Type, Serialize, Deserialize, ToSchema)]
+#[sqlx(type_name = "Text")]
+#[sqlx(rename_all = "snake_case")]
+pub enum BondStatus {
+    NewlyLaunched,
+    Graduating,
+    Graduated,
+}
+
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct BondingCurveAndMcInfo {
+    pub contract_address: String,
+    pub bonding_curve_address: String,
+    pub bonding_curve_percentage: i32,
+    pub market_cap: Option<i64>,
+    // Slot of the trade the market cap comes from
+    pub market_cap_slot: Option<i64>,
+}
+
+// Creator and creation time of a stored token
+#[derive(FromRow, Debug, Clone)]
+pub struct TokenLaunch {
+    pub creator_address: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[allow(dead_code)]
+#[derive(FromRow, Clone)]
+pub struct Trade {
+    pub id: uuid::Uuid,
+    pub sol_amount: i64,
+    pub token_amount: i64,
+    pub is_buy: bool,
+    pub user_address: String,
+    pub token_id: uuid::Uuid,
+    pub slot: Option<i64>,
+    pub signature: Option<String>,
+}
+
+#[allow(dead_code)]
+#[derive(FromRow, Clone)]
+pub struct Token {
+    pub id: uuid::Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub name: String,
+    pub ticker: String,
+    pub contract_address: String,
+    pub bonding_curve_percentage: i32,
+    pub bond_status: BondStatus,
+    pub market_cap: Option<i64>,
+    pub uri: String,
+    pub bonding_curve_address: String,
+    pub creator_address: String,
+    pub created_slot: Option<i64>,
+    pub created_signature: Option<String>,
+}
+#[allow(dead_code)]
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct TokenDetails {
+    pub id: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub name: String,
+    pub ticker: String,
+    pub contract_address: String,
+    pub bonding_curve_percentage: i32,
+    pub bond_status: BondStatus,
+    pub volume: Option<f64>,
+    pub market_cap: Option<i64>,
+    pub uri: String,
+    pub bonding_curve_address: String,
+    pub creator_address: String,
+    pub funds_percent_by_top_10: f64,
+    pub holder_count: usize,
+    pub creator_percent: f64,
+    // Same as holder_count and funds_percent_by_top_10 but with wallets merged by funding cluster
+    pub cluster_holder_count: usize,
+    pub funds_percent_by_top_10_clusters: f64,
+    pub sniper_percent: f64,
+    pub bundled: bool,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct Holding {
+    pub token_id: Uuid,
+    pub user: String,
+    pub net_tokens: i64,
+}
+
+#[derive(FromRow, Debug, Clone)]
+pub struct Checkpoint {
+    pub datasource: String,
+    pub slot: i64,
+    pub signature: String,
+}
+
+// Recent trading activity of a token together with the snapshot stored by the previous ranking run
+#[derive(FromRow, Debug, Clone)]
+pub struct TokenActivity {
+    pub token_id: Uuid,
+    pub bond_status: BondStatus,
+    pub bonding_curve_percentage: i32,
+    pub market_cap: Option<i64>,
+    pub decayed_volume_sol: f64,
+    pub unique_buyers: i64,
+    pub holders: i64,
+    pub holders_before: i64,
+    pub previous_bonding_curve_percentage: Option<i32>,
+    pub previous_market_cap: Option<i64>,
+    pub previous_updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TokenRank {
+    pub token_id: Uuid,
+    pub rank: i32,
+    pub score: f64,
+    pub volume_sol: f64,
+    pub unique_buyers: i64,
+    pub holder_growth: i64,
+    pub curve_velocity: f64,
+    pub market_cap_momentum: f64,
+    pub bonding_curve_percentage: i32,
+    pub market_cap: i64,
+}
+
+#[derive(FromRow, Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct RankedToken {
+    pub id: String,
+    pub name: String,
+    pub ticker: String,
+    pub contract_address: String,
+    pub uri: String,
+    pub bond_status: BondStatus,
+    pub bonding_curve_percentage: i32,
+    pub market_cap: Option<i64>,
+    pub rank: i32,
+    pub score: f64,
+    pub volume_sol: f64,
+    pub unique_buyers: i64,
+    pub holder_growth: i64,
+    pub curve_velocity: f64,
+    pub market_cap_momentum: f64,
+    pub ranked_at: DateTime<Utc>,
+}
+
+// First known SOL funding source of a wallet, funder is None when the lookup found no system transfer
+#[derive(FromRow, Debug, Clone)]
+pub struct WalletFunding {
+    pub wallet: String,
+    pub funder: Option<String>,
+}
+
+#[derive(FromRow, Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct Alert {
+    pub id: String,
+    pub rule: String,
+    pub contract_address: String,
+    pub wallet: Option<String>,
+    pub message: String,
+    pub value: f64,
+    pub threshold: f64,
+    pub signature: String,
+    pub slot: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(FromRow, Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct Webhook {
+    pub id: String,
+    pub url: String,
+    // Only returned once, when the webhook is registered
+    #[serde(skip_serializing)]
+    pub secret: String,
+    pub event_types: Vec<String>,
+    pub mints: Option<Vec<String>>,
+    pub creators: Option<Vec<String>>,
+    pub enabled: bool,
+    pub consecutive_failures: i32,
+    pub created_at: DateTime<Utc>,
+    pub disabled_at: Option<DateTime<Utc>>,
+}
+
+#[derive(FromRow, Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct WebhookDelivery {
+    pub id: String,
+    pub webhook_id: String,
+    pub event_type: String,
+    pub payload: String,
+    pub status: String,
+    pub attempts: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub last_status_code: Option<i32>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+// Resources a caller can see and manage. A key only reaches what it registered itself, requests without a key what
+// was registered without one, and admins everything.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OwnerScope {
+    All,
+    Key(Option<Uuid>),
+}
+
+impl OwnerScope {
+    pub fn is_all(&self) -> bool {
+        matches!(self, OwnerScope::All)
+    }
+
+    pub fn api_key_id(&self) -> Option<Uuid> {
+        match self {
+            OwnerScope::All => None,
+            OwnerScope::Key(api_key_id) => *api_key_id,
+        }
+    }
+}
+
+// A delivery claimed by the worker together with where and how to send it
+#[derive(FromRow, Clone, Debug)]
+pub struct DueDelivery {
+    pub id: Uuid,
+    pub webhook_id: Uuid,
+    pub event_type: String,
+    pub payload: String,
+    pub attempts: i32,
+    pub url: String,
+    pub secret: String,
+}
+
+#[derive(FromRow, Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct ApiKey {
+    pub id: String,
+    pub name: String,
+    // First characters of the key, enough to recognize it without revealing it
+    pub key_prefix: String,
+    pub tier: String,
+    pub is_admin: bool,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+// Requests served and rejected for one key on one UTC day
+#[derive(FromRow, Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct ApiKeyUsage {
+    pub api_key_id: String,
+    pub day: NaiveDate,
+    pub requests: i64,
+    pub rate_limited: i64,
+}
@@ -0,0 +1,800 @@
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+
+use actix_web::{delete, get, post, web, HttpRequest, HttpResponse};
+use carbon_pumpfun_decoder::instructions::{create_event::CreateEvent, trade_event::TradeEvent};
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+use solana_client::client_error::reqwest;
+use solana_pubkey::Pubkey;
+use sqlx::{
+    types::chrono::{DateTime, Utc},
+    PgPool,
+};
+use tokio::{
+    sync::mpsc::{UnboundedReceiver, UnboundedSender},
+    time,
+};
+use tokio_util::sync::CancellationToken;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::{
+    auth::{caller_key_id, owner_scope},
+    config::WebhookConfig,
+    db::webhook::{
+        claim_due_deliveries, create_webhook, delete_webhook, enqueue_webhook_deliveries,
+        fetch_webhook_deliveries, fetch_webhooks, mark_delivery_failed, mark_delivery_succeeded,
+        prune_webhook_deliveries, set_webhook_enabled,
+    },
+    errors::{ApiError, ErrorResponse},
+    types::{DueDelivery, OwnerScope, Webhook, WebhookDelivery},
+};
+
+pub const LAUNCH_EVENT: &str = "launch";
+pub const GRADUATION_EVENT: &str = "graduation";
+pub const LARGE_TRADE_EVENT: &str = "large_trade";
+
+const EVENT_TYPES: [&str; 3] = [LAUNCH_EVENT, GRADUATION_EVENT, LARGE_TRADE_EVENT];
+
+// Extra time on top of the request timeout before a claimed delivery can be picked up again
+const CLAIM_LEASE_PADDING_SECS: i64 = 30;
+
+// How often the old deliveries are pruned and how many go per statement
+const PRUNE_INTERVAL: Duration = Duration::from_secs(3600);
+const PRUNE_BATCH_SIZE: i64 = 10_000;
+
+const DEFAULT_DELIVERIES_LIMIT: i64 = 100;
+const MAX_DELIVERIES_LIMIT: i64 = 1000;
+
+// An event the processor wants to push to the registered webhooks
+#[derive(Debug, Clone)]
+pub struct WebhookEvent {
+    pub event_type: &'static str,
+    pub mint: String,
+    pub creator: Option<String>,
+    pub payload: serde_json::Value,
+}
+
+// Turns decoded instructions into webhook events. Owned by the instruction processor, the events are queued by the
+// webhook enqueuer so the processor never waits on Postgres or on the receivers.
+pub struct WebhookEmitter {
+    sender: UnboundedSender<WebhookEvent>,
+    large_trade_lamports: u64,
+}
+
+impl WebhookEmitter {
+    pub fn new(sender: UnboundedSender<WebhookEvent>, large_trade_sol: f64) -> Self {
+        Self {
+            sender,
+            large_trade_lamports: (large_trade_sol * 1_000_000_000.0) as u64,
+        }
+    }
+
+    fn emit(
+        &self,
+        event_type: &'static str,
+        mint: &Pubkey,
+        creator: Option<String>,
+        data: serde_json::Value,
+    ) {
+        let payload = serde_json::json!({
+            "type": event_type,
+            "created_at": Utc::now(),
+            "data": data,
+        });
+
+        let _ = self.sender.send(WebhookEvent {
+            event_type,
+            mint: mint.to_string(),
+            creator,
+            payload,
+        });
+    }
+
+    pub fn launch(&self, event: &CreateEvent, slot: u64, signature: &str) {
+        self.emit(
+            LAUNCH_EVENT,
+            &event.mint,
+            Some(event.user.to_string()),
+            serde_json::json!({
+                "mint": event.mint.to_string(),
+                "name": event.name,
+                "symbol": event.symbol,
+                "uri": event.uri,
+                "creator": event.user.to_string(),
+                "bonding_curve": event.bonding_curve.to_string(),
+                "slot": slot,
+                "signature": signature,
+            }),
+        );
+    }
+
+    pub fn trade(&self, event: &TradeEvent, market_cap: i64, slot: u64, signature: &str) {
+        if event.sol_amount < self.large_trade_lamports {
+            return;
+        }
+
+        self.emit(
+            LARGE_TRADE_EVENT,
+            &event.mint,
+            None,
+            serde_json::json!({
+                "mint": event.mint.to_string(),
+                "user": event.user.to_string(),
+                "is_buy": event.is_buy,
+                "sol_amount": event.sol_amount,
+                "token_amount": event.token_amount,
+                "market_cap": market_cap,
+                "timestamp": event.timestamp,
+                "slot": slot,
+                "signature": signature,
+            }),
+        );
+    }
+
+    pub fn graduation(&self, mint: &Pubkey, slot: u64, signature: &str) {
+        self.emit(
+            GRADUATION_EVENT,
+            mint,
+            None,
+            serde_json::json!({
+                "mint": mint.to_string(),
+                "slot": slot,
+                "signature": signature,
+            }),
+        );
+    }
+}
+
+// Hex encoded HMAC-SHA256 of "<timestamp>.<body>", receivers recompute it with their secret to authenticate the call
+pub fn sign_payload(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+
+    hex::encode(mac.finalize().into_bytes())
+}
+
+// Exponential backoff after the given number of failed attempts, capped at the configured maximum
+fn retry_delay_secs(config: &WebhookConfig, attempts: i32) -> i64 {
+    let exponent = (attempts - 1).clamp(0, 30) as u32;
+
+    config
+        .backoff_base_secs
+        .saturating_mul(1i64 << exponent)
+        .min(config.backoff_max_secs)
+}
+
+// Loopback, private, link-local (where the cloud metadata services live), shared, unspecified and multicast
+// addresses, none of which a webhook may point to
+fn is_internal_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let octets = ip.octets();
+
+            ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                || octets[0] == 0
+                // 100.64.0.0/10, carrier-grade NAT
+                || (octets[0] == 100 && octets[1] & 0xc0 == 64)
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_internal_address(IpAddr::V4(ip)),
+            None => {
+                let first = ip.segments()[0];
+
+                ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_multicast()
+                    // fc00::/7 unique local and fe80::/10 link-local
+                    || first & 0xfe00 == 0xfc00
+                    || first & 0xffc0 == 0xfe80
+            }
+        },
+    }
+}
+
+// Rejects webhook URLs that aren't http(s) or whose host resolves to an internal address, so a webhook can't be used
+// to reach the services next to the indexer. Checked on registration and again before every delivery, the DNS
+// record may have changed in between. `allow_private` lets local receivers through.
+async fn check_webhook_url(url: &str, allow_private: bool) -> Result<(), String> {
+    let url = reqwest::Url::parse(url).map_err(|err| format!("Invalid url: {}", err))?;
+
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return Err("url must be an http(s) URL".to_string());
+    }
+
+    let host = url
+        .host_str()
+        .ok_or_else(|| "url has no host".to_string())?
+        .trim_start_matches('[')
+        .trim_end_matches(']');
+
+    if allow_private {
+        return Ok(());
+    }
+
+    let port = url.port_or_known_default().unwrap_or(443);
+
+    let addresses: Vec<SocketAddr> = match host.parse::<IpAddr>() {
+        Ok(ip) => vec![SocketAddr::new(ip, port)],
+        Err(_) => tokio::net::lookup_host((host, port))
+            .await
+            .map_err(|err| format!("Failed to resolve {}: {}", host, err))?
+            .collect(),
+    };
+
+    match addresses
+        .iter()
+        .find(|address| is_internal_address(address.ip()))
+    {
+        Some(address) => Err(format!(
+            "{} resolves to the internal address {}",
+            host,
+            address.ip()
+        )),
+        None => Ok(()),
+    }
+}
+
+// Client of the deliveries. Redirects are not followed, they could lead to an internal address.
+fn delivery_client(config: &WebhookConfig) -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(config.timeout_secs))
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap_or_default()
+}
+
+// Posts the signed payload of a delivery, returns the status code of a 2xx answer or the status code and error of
+// the failed attempt
+async fn send_delivery(
+    client: &reqwest::Client,
+    delivery: &DueDelivery,
+) -> Result<i32, (Option<i32>, String)> {
+    let timestamp = Utc::now().timestamp();
+    let signature = sign_payload(&delivery.secret, timestamp, &delivery.payload);
+
+    let result = client
+        .post(&delivery.url)
+        .header("Content-Type", "application/json")
+        .header("X-Webhook-Id", delivery.id.to_string())
+        .header("X-Webhook-Event", &delivery.event_type)
+        .header("X-Webhook-Timestamp", timestamp.to_string())
+        .header("X-Webhook-Signature", format!("sha256={}", signature))
+        .body(delivery.payload.clone())
+        .send()
+        .await;
+
+    match result {
+        Ok(response) if response.status().is_success() => Ok(response.status().as_u16() as i32),
+        Ok(response) => Err((
+            Some(response.status().as_u16() as i32),
+            format!("Receiver responded with {}", response.status()),
+        )),
+        Err(err) => Err((None, err.to_string())),
+    }
+}
+
+async fn deliver(
+    db: Arc<PgPool>,
+    client: &reqwest::Client,
+    config: &WebhookConfig,
+    delivery: DueDelivery,
+) {
+    let result = match check_webhook_url(&delivery.url, config.allow_private_urls).await {
+        Ok(()) => send_delivery(client, &delivery).await,
+        Err(err) => Err((None, err)),
+    };
+
+    let (status_code, error) = match result {
+        Ok(status_code) => {
+            mark_delivery_succeeded(db, &delivery, status_code).await;
+            return;
+        }
+        Err(failure) => failure,
+    };
+
+    let attempts = delivery.attempts + 1;
+
+    let retry_at: Option<DateTime<Utc>> = (attempts < config.max_attempts).then(|| {
+        Utc::now() + sqlx::types::chrono::Duration::seconds(retry_delay_secs(config, attempts))
+    });
+
+    tracing::warn!(
+        delivery_id = %delivery.id,
+        webhook_id = %delivery.webhook_id,
+        attempts,
+        error = %error,
+        "Webhook delivery failed"
+    );
+
+    mark_delivery_failed(
+        db,
+        &delivery,
+        status_code,
+        &error,
+        retry_at,
+        config.disable_after_failures,
+    )
+    .await;
+}
+
+// Queues the events emitted by the processor as deliveries of the matching webhooks, runs next to the processor
+pub async fn run_webhook_enqueuer(
+    db: Arc<PgPool>,
+    mut rx: UnboundedReceiver<WebhookEvent>,
+    shutdown: CancellationToken,
+) {
+    loop {
+        let event = tokio::select! {
+            event = rx.recv() => match event {
+                Some(event) => event,
+                None => break,
+            },
+            // Queue whatever the processor emitted while draining, it is delivered by the next poll of a worker
+            _ = shutdown.cancelled() => match rx.try_recv() {
+                Ok(event) => event,
+                Err(_) => break,
+            },
+        };
+
+        enqueue_webhook_deliveries(
+            db.clone(),
+            event.event_type,
+            &event.mint,
+            event.creator.as_deref(),
+            &event.payload.to_string(),
+        )
+        .await;
+    }
+}
+
+// Delivers the due deliveries on every poll. Deliveries are claimed with a lease, so any number of workers can run
+// against the same database. Once an hour the finished deliveries older than the retention are deleted.
+pub async fn run_webhook_delivery(
+    db: Arc<PgPool>,
+    config: WebhookConfig,
+    shutdown: CancellationToken,
+) {
+    let client = delivery_client(&config);
+
+    let lease_secs = config.timeout_secs as i64 + CLAIM_LEASE_PADDING_SECS;
+
+    let mut interval = time::interval(Duration::from_millis(config.poll_interval_ms.max(1)));
+    interval.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+
+    let mut prune_interval = time::interval(PRUNE_INTERVAL);
+    prune_interval.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => break,
+            _ = prune_interval.tick() => {
+                prune_deliveries(&db, &config).await;
+                continue;
+            }
+            _ = interval.tick() => {}
+        }
+
+        let due = match claim_due_deliveries(&db, config.batch_size, lease_secs).await {
+            Ok(due) => due,
+            Err(err) => {
+                tracing::error!(error = ?err, "Failed to claim webhook deliveries");
+                continue;
+            }
+        };
+
+        futures::future::join_all(
+            due.into_iter()
+                .map(|delivery| deliver(db.clone(), &client, &config, delivery)),
+        )
+        .await;
+    }
+}
+
+// Deletes the finished deliveries past the retention, a batch at a time so the table isn't locked for long
+async fn prune_deliveries(db: &PgPool, config: &WebhookConfig) {
+    let before = Utc::now() - sqlx::types::chrono::Duration::days(config.delivery_retention_days);
+
+    loop {
+        match prune_webhook_deliveries(db, before, PRUNE_BATCH_SIZE).await {
+            Ok(deleted) if deleted as i64 == PRUNE_BATCH_SIZE => continue,
+            Ok(deleted) => {
+                tracing::debug!(deleted, "Pruned webhook deliveries");
+                break;
+            }
+            Err(err) => {
+                tracing::error!(error = ?err, "Failed to prune webhook deliveries");
+                break;
+            }
+        }
+    }
+}
+
+// Webhook ids are UUIDs, anything else can't name a webhook
+fn parse_webhook_id(id: &str) -> Result<Uuid, ApiError> {
+    Uuid::parse_str(id).map_err(|_| ApiError::BadRequest(format!("Invalid webhook id {}", id)))
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateWebhookRequest {
+    pub url: String,
+    // Any of launch, graduation and large_trade
+    pub event_types: Vec<String>,
+    pub mints: Option<Vec<String>>,
+    pub creators: Option<Vec<String>>,
+    // Generated when not provided
+    pub secret: Option<String>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct DeliveriesQuery {
+    // Number of deliveries to return, 1 to 1000 (default 100)
+    pub limit: Option<i64>,
+}
+
+//* This endpoint registers a webhook owned by the calling key, the response is the only place where its signing secret is returned */
+#[utoipa::path(
+    tag = "webhooks",
+    request_body = CreateWebhookRequest,
+    responses(
+        (status = 201, description = "The registered webhook together with its `secret`", body = Webhook),
+        (status = 400, body = ErrorResponse),
+        (status = 500, body = ErrorResponse),
+    )
+)]
+#[post("/webhooks")]
+pub async fn post_webhook(
+    req: HttpRequest,
+    db: web::Data<Arc<PgPool>>,
+    config: web::Data<WebhookConfig>,
+    body: web::Json<CreateWebhookRequest>,
+) -> Result<HttpResponse, ApiError> {
+    check_webhook_url(&body.url, config.allow_private_urls)
+        .await
+        .map_err(ApiError::BadRequest)?;
+
+    if body.event_types.is_empty() {
+        return Err(ApiError::BadRequest(
+            "event_types must not be empty".to_string(),
+        ));
+    }
+
+    if let Some(unknown) = body
+        .event_types
+        .iter()
+        .find(|event_type| !EVENT_TYPES.contains(&event_type.as_str()))
+    {
+        return Err(ApiError::BadRequest(format!(
+            "Unknown event type {}, expected one of {}",
+            unknown,
+            EVENT_TYPES.join(", ")
+        )));
+    }
+
+    let secret = body
+        .secret
+        .clone()
+        .unwrap_or_else(|| format!("whsec_{}", uuid::Uuid::new_v4().simple()));
+
+    let webhook = create_webhook(
+        db.get_ref(),
+        caller_key_id(&req),
+        &body.url,
+        &secret,
+        &body.event_types,
+        body.mints.as_deref(),
+        body.creators.as_deref(),
+    )
+    .await?;
+
+    let mut result = serde_json::to_value(&webhook).unwrap_or_default();
+    result["secret"] = serde_json::Value::String(secret);
+
+    Ok(HttpResponse::Created().json(result))
+}
+
+//* This endpoint returns the webhooks registered by the calling key, every webhook for an admin key */
+#[utoipa::path(
+    tag = "webhooks",
+    responses(
+        (status = 200, body = [Webhook]),
+        (status = 500, body = ErrorResponse),
+    )
+)]
+#[get("/webhooks")]
+pub async fn get_webhooks(
+    req: HttpRequest,
+    db: web::Data<Arc<PgPool>>,
+) -> Result<HttpResponse, ApiError> {
+    let result = fetch_webhooks(db.get_ref(), owner_scope(&req)).await?;
+
+    Ok(HttpResponse::Ok().json(&result))
+}
+
+//* This endpoint deletes a webhook together with its delivery log */
+#[utoipa::path(
+    tag = "webhooks",
+    params(("id" = String, Path, description = "Webhook id")),
+    responses(
+        (status = 204, description = "The webhook was deleted"),
+        (status = 400, description = "The id is not a UUID", body = ErrorResponse),
+        (status = 404, body = ErrorResponse),
+        (status = 500, body = ErrorResponse),
+    )
+)]
+#[delete("/webhooks/{id}")]
+pub async fn delete_webhook_by_id(
+    req: HttpRequest,
+    db: web::Data<Arc<PgPool>>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let id = parse_webhook_id(&id)?;
+
+    if delete_webhook(db.get_ref(), id, owner_scope(&req)).await? {
+        Ok(HttpResponse::NoContent().finish())
+    } else {
+        Err(ApiError::NotFound("Webhook not found".to_string()))
+    }
+}
+
+// Webhooks registered by another key are reported as not found
+async fn update_webhook_enabled(
+    db: &PgPool,
+    id: &str,
+    enabled: bool,
+    scope: OwnerScope,
+) -> Result<HttpResponse, ApiError> {
+    let id = parse_webhook_id(id)?;
+
+    match set_webhook_enabled(db, id, enabled, scope).await? {
+        Some(result) => Ok(HttpResponse::Ok().json(&result)),
+        None => Err(ApiError::NotFound("Webhook not found".to_string())),
+    }
+}
+
+//* This endpoint re-enables a webhook, e.g. after it was disabled for failing repeatedly */
+#[utoipa::path(
+    tag = "webhooks",
+    params(("id" = String, Path, description = "Webhook id")),
+    responses(
+        (status = 200, body = Webhook),
+        (status = 400, description = "The id is not a UUID", body = ErrorResponse),
+        (status = 404, body = ErrorResponse),
+        (status = 500, body = ErrorResponse),
+    )
+)]
+#[post("/webhooks/{id}/enable")]
+pub async fn enable_webhook(
+    req: HttpRequest,
+    db: web::Data<Arc<PgPool>>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    update_webhook_enabled(db.get_ref(), &id, true, owner_scope(&req)).await
+}
+
+//* This endpoint pauses the deliveries of a webhook */
+#[utoipa::path(
+    tag = "webhooks",
+    params(("id" = String, Path, description = "Webhook id")),
+    responses(
+        (status = 200, body = Webhook),
+        (status = 400, description = "The id is not a UUID", body = ErrorResponse),
+        (status = 404, body = ErrorResponse),
+        (status = 500, body = ErrorResponse),
+    )
+)]
+#[post("/webhooks/{id}/disable")]
+pub async fn disable_webhook(
+    req: HttpRequest,
+    db: web::Data<Arc<PgPool>>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    update_webhook_enabled(db.get_ref(), &id, false, owner_scope(&req)).await
+}
+
+//* This endpoint returns the delivery log of a webhook */
+#[utoipa::path(
+    tag = "webhooks",
+    params(("id" = String, Path, description = "Webhook id"), DeliveriesQuery),
+    responses(
+        (status = 200, description = "Deliveries, newest first", body = [WebhookDelivery]),
+        (status = 400, description = "The id is not a UUID", body = ErrorResponse),
+        (status = 500, body = ErrorResponse),
+    )
+)]
+#[get("/webhooks/{id}/deliveries")]
+pub async fn get_webhook_deliveries(
+    req: HttpRequest,
+    db: web::Data<Arc<PgPool>>,
+    id: web::Path<String>,
+    query: web::Query<DeliveriesQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_DELIVERIES_LIMIT)
+        .clamp(1, MAX_DELIVERIES_LIMIT);
+
+    let id = parse_webhook_id(&id)?;
+
+    let result = fetch_webhook_deliveries(db.get_ref(), id, limit, owner_scope(&req)).await?;
+
+    Ok(HttpResponse::Ok().json(&result))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    use super::*;
+
+    fn config() -> WebhookConfig {
+        WebhookConfig {
+            timeout_secs: 5,
+            backoff_base_secs: 5,
+            backoff_max_secs: 3600,
+            ..WebhookConfig::default()
+        }
+    }
+
+    #[test]
+    fn signs_the_timestamp_and_body() {
+        assert_eq!(
+            sign_payload("whsec_test", 1_700_000_000, r#"{"mint":"mint"}"#),
+            "6071aa3f3a8f9a5ada457944a8e0cb7f1dc9ac36e78788af9b5bfa92eeac6214"
+        );
+
+        // The timestamp is part of the signature so a captured delivery can't be replayed later
+        assert_ne!(
+            sign_payload("whsec_test", 1_700_000_001, r#"{"mint":"mint"}"#),
+            sign_payload("whsec_test", 1_700_000_000, r#"{"mint":"mint"}"#)
+        );
+        assert_ne!(
+            sign_payload("whsec_other", 1_700_000_000, r#"{"mint":"mint"}"#),
+            sign_payload("whsec_test", 1_700_000_000, r#"{"mint":"mint"}"#)
+        );
+    }
+
+    #[test]
+    fn backs_off_exponentially_up_to_the_maximum() {
+        let config = config();
+
+        assert_eq!(retry_delay_secs(&config, 0), 5);
+        assert_eq!(retry_delay_secs(&config, 1), 5);
+        assert_eq!(retry_delay_secs(&config, 2), 10);
+        assert_eq!(retry_delay_secs(&config, 3), 20);
+        assert_eq!(retry_delay_secs(&config, 10), 2560);
+        assert_eq!(retry_delay_secs(&config, 11), 3600);
+        assert_eq!(retry_delay_secs(&config, i32::MAX), 3600);
+    }
+
+    // Reads one HTTP request and answers 200, returns the lowercased header names with their values and the body
+    async fn receive(listener: TcpListener) -> (HashMap<String, String>, String) {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut request = Vec::new();
+        let mut buffer = [0u8; 4096];
+
+        let (head, body_start) = loop {
+            let read = stream.read(&mut buffer).await.unwrap();
+            request.extend_from_slice(&buffer[..read]);
+
+            if let Some(end) = request.windows(4).position(|window| window == b"\r\n\r\n") {
+                break (
+                    String::from_utf8_lossy(&request[..end]).to_string(),
+                    end + 4,
+                );
+            }
+        };
+
+        let headers: HashMap<String, String> = head
+            .lines()
+            .skip(1)
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+            .collect();
+
+        let length: usize = headers["content-length"].parse().unwrap();
+
+        while request.len() < body_start + length {
+            let read = stream.read(&mut buffer).await.unwrap();
+            request.extend_from_slice(&buffer[..read]);
+        }
+
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\nconnection: close\r\n\r\n")
+            .await
+            .unwrap();
+
+        let body = String::from_utf8_lossy(&request[body_start..body_start + length]).to_string();
+
+        (headers, body)
+    }
+
+    #[tokio::test]
+    async fn rejects_internal_webhook_urls() {
+        for url in [
+            "http://127.0.0.1:8080/hook",
+            "http://localhost/hook",
+            "http://10.0.0.5/hook",
+            "http://192.168.1.1/hook",
+            "http://169.254.169.254/latest/meta-data",
+            "http://100.64.0.1/hook",
+            "http://0.0.0.0/hook",
+            "http://[::1]/hook",
+            "http://[fe80::1]/hook",
+            "http://[fd00::1]/hook",
+            "http://[::ffff:127.0.0.1]/hook",
+            "ftp://1.1.1.1/hook",
+            "not a url",
+        ] {
+            assert!(
+                check_webhook_url(url, false).await.is_err(),
+                "{} was accepted",
+                url
+            );
+        }
+
+        assert!(check_webhook_url("https://1.1.1.1/hook", false)
+            .await
+            .is_ok());
+        assert!(check_webhook_url("http://[2606:4700::1111]/hook", false)
+            .await
+            .is_ok());
+        assert!(check_webhook_url("http://127.0.0.1:8080/hook", true)
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn delivers_signed_payload_to_local_receiver() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let receiver = tokio::spawn(receive(listener));
+
+        let delivery = DueDelivery {
+            id: Uuid::new_v4(),
+            webhook_id: Uuid::new_v4(),
+            event_type: LAUNCH_EVENT.to_string(),
+            payload: r#"{"mint":"mint"}"#.to_string(),
+            attempts: 0,
+            url: format!("http://{}/hook", address),
+            secret: "whsec_test".to_string(),
+        };
+
+        assert!(check_webhook_url(&delivery.url, false).await.is_err());
+
+        let status = send_delivery(&delivery_client(&config()), &delivery).await;
+
+        assert_eq!(status, Ok(200));
+
+        let (headers, body) = receiver.await.unwrap();
+        let timestamp: i64 = headers["x-webhook-timestamp"].parse().unwrap();
+
+        assert_eq!(body, delivery.payload);
+        assert_eq!(headers["x-webhook-id"], delivery.id.to_string());
+        assert_eq!(headers["x-webhook-event"], LAUNCH_EVENT);
+        assert_eq!(
+            headers["x-webhook-signature"],
+            format!("sha256={}", sign_payload("whsec_test", timestamp, &body))
+        );
+    }
+}